@@ -0,0 +1,135 @@
+use cgmath::{Vector3, Vector4, Matrix4};
+
+use crate::ray::Ray;
+
+/// Axis aligned bounding box, empty when `min` is above `max`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::empty()
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>) -> Aabb {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vector3<f64>>>(points: I) -> Aabb {
+        let mut result = Aabb::empty();
+        for p in points {
+            result.grow(*p);
+        }
+
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn grow(&mut self, p: Vector3<f64>) {
+        for k in 0..3 {
+            self.min[k] = self.min[k].min(p[k]);
+            self.max[k] = self.max[k].max(p[k]);
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut result = *self;
+        for k in 0..3 {
+            result.min[k] = result.min[k].min(other.min[k]);
+            result.max[k] = result.max[k].max(other.max[k]);
+        }
+
+        result
+    }
+
+    pub fn centroid(&self) -> Vector3<f64> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn diagonal(&self) -> Vector3<f64> {
+        self.max - self.min
+    }
+
+    /// Index of the longest axis
+    pub fn max_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x >= d.y && d.x >= d.z {
+            0
+        } else if d.y >= d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn corners(&self) -> [Vector3<f64>; 8] {
+        let mut result = [self.min; 8];
+        for (i, c) in result.iter_mut().enumerate() {
+            *c = Vector3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+        }
+
+        result
+    }
+
+    /// Bounding box of this box after an affine transform
+    pub fn transform(&self, m: &Matrix4<f64>) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        let mut result = Aabb::empty();
+        for c in self.corners().iter() {
+            let p = m * Vector4::new(c.x, c.y, c.z, 1.0);
+            result.grow(Vector3::new(p.x, p.y, p.z));
+        }
+
+        result
+    }
+
+    /// Entry and exit distances of `ray` clipped to [0, t_max], `inv_dir` is
+    /// the componentwise reciprocal of the ray's direction
+    pub fn intersect(&self, ray: &Ray, inv_dir: Vector3<f64>, t_max: f64) -> Option<(f64, f64)> {
+        let mut t0: f64 = 0.0;
+        let mut t1 = t_max;
+        for k in 0..3 {
+            let mut near = (self.min[k] - ray.pos[k]) * inv_dir[k];
+            let mut far = (self.max[k] - ray.pos[k]) * inv_dir[k];
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // comparisons with NaN are false, which keeps the range unchanged
+            if near > t0 {
+                t0 = near;
+            }
+            if far < t1 {
+                t1 = far;
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        Some((t0, t1))
+    }
+}
@@ -0,0 +1,226 @@
+use cgmath::Vector3;
+
+use super::aabb::Aabb;
+use crate::ray::Ray;
+use crate::util::binio::{BinWriter, BinReader};
+
+// primitives per leaf before a node is split
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Debug)]
+struct BvhNode {
+    bounds: Aabb,
+    // leaves hold `count` primitives from `start` in `indices`,
+    // inner nodes have their left child right after them and the right one at `right`
+    start: usize,
+    count: usize,
+    right: usize,
+}
+
+/// Bounding volume hierarchy over any list of primitives, which are only
+/// known through their bounding boxes. Built by splitting at the median of
+/// the longest axis, stored flat in depth first order.
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn build(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            let centroids: Vec<Vector3<f64>> = bounds.iter().map(|b| b.centroid()).collect();
+            bvh.build_node(bounds, &centroids, 0, bounds.len());
+        }
+
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Vector3<f64>], start: usize, end: usize) -> usize {
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &i in self.indices[start..end].iter() {
+            node_bounds = node_bounds.union(&bounds[i]);
+            centroid_bounds.grow(centroids[i]);
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: node_bounds,
+            start,
+            count: end - start,
+            right: 0,
+        });
+
+        let axis = centroid_bounds.max_axis();
+        if end - start <= MAX_LEAF_SIZE || centroid_bounds.diagonal()[axis] <= 0.0 {
+            return index;
+        }
+
+        let mid = (start + end) / 2;
+        self.indices[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+            centroids[a][axis].partial_cmp(&centroids[b][axis]).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        self.build_node(bounds, centroids, start, mid);
+        let right = self.build_node(bounds, centroids, mid, end);
+        self.nodes[index].count = 0;
+        self.nodes[index].right = right;
+
+        index
+    }
+
+    pub(crate) fn write_cache(&self, w: &mut BinWriter) {
+        w.u64(self.nodes.len() as u64);
+        for node in self.nodes.iter() {
+            w.vector3(node.bounds.min);
+            w.vector3(node.bounds.max);
+            w.u64(node.start as u64);
+            w.u64(node.count as u64);
+            w.u64(node.right as u64);
+        }
+        w.u64(self.indices.len() as u64);
+        self.indices.iter().for_each(|&i| w.u64(i as u64));
+    }
+
+    /// Reads a tree written by `write_cache` over `primitives` primitives,
+    /// checking every reference so traversal can't go out of bounds
+    pub(crate) fn read_cache(r: &mut BinReader, primitives: usize) -> Result<Bvh, String> {
+        let count = r.len(72)?;
+        let mut nodes = Vec::with_capacity(count);
+        for _ in 0..count {
+            nodes.push(BvhNode {
+                bounds: Aabb::new(r.vector3()?, r.vector3()?),
+                start: r.u64()? as usize,
+                count: r.u64()? as usize,
+                right: r.u64()? as usize,
+            });
+        }
+        let count = r.len(8)?;
+        let indices = (0..count).map(|_| r.u64().map(|i| i as usize)).collect::<Result<Vec<usize>, String>>()?;
+
+        let valid = indices.len() == primitives
+            && indices.iter().all(|&i| i < primitives)
+            && nodes.iter().enumerate().all(|(i, n)| {
+                if n.count > 0 {
+                    n.start.saturating_add(n.count) <= indices.len()
+                } else {
+                    n.right > i + 1 && n.right < nodes.len()
+                }
+            });
+        if !valid || (nodes.is_empty() && primitives > 0) {
+            return Err(String::from("cached bvh is broken"));
+        }
+
+        Ok(Bvh {
+            nodes,
+            indices,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), |n| n.bounds)
+    }
+
+    /// Visits the primitives whose boxes `ray` crosses before the closest hit
+    /// so far. `hit(i, t_max)` tests primitive `i` and returns its distance
+    /// when it is hit closer than `t_max`, which then shrinks the search.
+    pub fn intersect<F: FnMut(usize, f64) -> Option<f64>>(&self, ray: &Ray, t_max: f64, mut hit: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inv_dir = Vector3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+
+        let mut closest = t_max;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.intersect(ray, inv_dir, closest).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                for &i in self.indices[node.start..node.start + node.count].iter() {
+                    if let Some(t) = hit(i, closest) {
+                        closest = closest.min(t);
+                    }
+                }
+            } else {
+                stack.push(node.right);
+                stack.push(index + 1);
+            }
+        }
+    }
+
+    /// Any-hit traversal, returns as soon as `hit` reports a hit for one of the
+    /// primitives whose box the ray crosses before `t_max`
+    pub fn any<F: FnMut(usize) -> bool>(&self, ray: &Ray, t_max: f64, mut hit: F) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inv_dir = Vector3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.intersect(ray, inv_dir, t_max).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                if self.indices[node.start..node.start + node.count].iter().any(|&i| hit(i)) {
+                    return true;
+                }
+            } else {
+                stack.push(node.right);
+                stack.push(index + 1);
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod bvh_test {
+    use super::Bvh;
+    use crate::accel::Aabb;
+    use crate::ray::Ray;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_closest_box() {
+        // unit boxes along -z, the BVH should report the nearest one
+        let bounds: Vec<Aabb> = (0..20).map(|i| {
+            let z = -(i as f64) * 2.0 - 2.0;
+            Aabb::new(Vector3::new(-0.5, -0.5, z - 0.5), Vector3::new(0.5, 0.5, z + 0.5))
+        }).collect();
+        let bvh = Bvh::build(&bounds);
+        let ray = Ray::new_nz();
+
+        let mut nearest = None;
+        let inv_dir = Vector3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        bvh.intersect(&ray, f64::INFINITY, |i, t_max| {
+            let (t0, _) = bounds[i].intersect(&ray, inv_dir, t_max)?;
+            nearest = Some(i);
+            Some(t0)
+        });
+        assert_eq!(nearest, Some(0));
+    }
+
+    #[test]
+    fn test_any_respects_t_max() {
+        let bounds = vec![Aabb::new(Vector3::new(-0.5, -0.5, -5.5), Vector3::new(0.5, 0.5, -4.5))];
+        let bvh = Bvh::build(&bounds);
+        let ray = Ray::new_nz();
+
+        assert!(bvh.any(&ray, f64::INFINITY, |_| true));
+        assert!(!bvh.any(&ray, 4.0, |_| true));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod aabb;
+pub mod bvh;
+
+pub use aabb::Aabb;
+pub use bvh::Bvh;
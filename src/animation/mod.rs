@@ -0,0 +1,5 @@
+pub mod track;
+pub mod timeline;
+
+pub use track::{Track, Interpolation, KeyValue};
+pub use timeline::{Timeline, ObjectTracks, CameraTracks, LightTracks};
@@ -0,0 +1,199 @@
+use cgmath::{Vector3, Point3, EuclideanSpace};
+
+use super::track::Track;
+use crate::object::Object;
+use crate::light::Light;
+use crate::camera::PerspectiveCamera;
+use crate::transform::Transform;
+
+/// Animated placement of an object, translate * rotate * scale with rotation
+/// as X, Y, Z Euler angles in radians. It is composed onto the base transform
+/// the object had when first posed, so the placement given by a scene graph is
+/// kept and components without keys leave the base alone
+#[derive(Clone, Debug, Default)]
+pub struct ObjectTracks {
+    pub translate: Track<Vector3<f64>>,
+    pub rotate: Track<Vector3<f64>>,
+    pub scale: Track<Vector3<f64>>,
+    // the keyed transform is applied within this one, taken from the object
+    // by the first `apply` unless set
+    pub base: Option<Transform>,
+}
+
+impl ObjectTracks {
+    /// The keyed part of the transform, without the base
+    pub fn evaluate(&self, time: f64) -> Transform {
+        let mut result = Transform::identity();
+        if let Some(t) = self.translate.evaluate(time) {
+            result = result * Transform::translation(t);
+        }
+        if let Some(r) = self.rotate.evaluate(time) {
+            result = result * Transform::rotate_x(r.x) * Transform::rotate_y(r.y) * Transform::rotate_z(r.z);
+        }
+        if let Some(s) = self.scale.evaluate(time) {
+            result = result * Transform::scale(s.x, s.y, s.z);
+        }
+
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.translate.is_empty() && self.rotate.is_empty() && self.scale.is_empty()
+    }
+
+    pub fn apply(&mut self, object: &mut Object, time: f64) {
+        let base = *self.base.get_or_insert(object.transform);
+        object.set_transform(base * self.evaluate(time));
+    }
+}
+
+/// Animated `PerspectiveCamera` parameters, the ones without keys are left alone
+#[derive(Clone, Debug, Default)]
+pub struct CameraTracks {
+    pub eye: Track<Vector3<f64>>,
+    pub center: Track<Vector3<f64>>,
+    pub fovy: Track<f64>,
+}
+
+impl CameraTracks {
+    pub fn is_empty(&self) -> bool {
+        self.eye.is_empty() && self.center.is_empty() && self.fovy.is_empty()
+    }
+
+    pub fn apply(&self, camera: &mut PerspectiveCamera, time: f64) {
+        if let Some(eye) = self.eye.evaluate(time) {
+            camera.eye = Point3::from_vec(eye);
+        }
+        if let Some(center) = self.center.evaluate(time) {
+            camera.center = Point3::from_vec(center);
+        }
+        if let Some(fovy) = self.fovy.evaluate(time) {
+            camera.fovy = fovy;
+        }
+    }
+}
+
+/// Animated light parameters, the ones without keys are left alone
+#[derive(Clone, Debug, Default)]
+pub struct LightTracks {
+    pub position: Track<Vector3<f64>>,
+    pub color: Track<Vector3<f64>>,
+}
+
+impl LightTracks {
+    pub fn apply(&self, light: &mut dyn Light, time: f64) {
+        if let Some(position) = self.position.evaluate(time) {
+            light.set_position(position);
+        }
+        if let Some(color) = self.color.evaluate(time) {
+            light.set_color(color);
+        }
+    }
+}
+
+/// Tracks of a scene, objects and lights are referred to by their index in the
+/// `Scene`. Tracks whose index is past the end, or whose object has `motion`
+/// keyframes, are skipped by `Scene::set_time`
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    pub frame_rate: f64,
+    pub objects: Vec<(usize, ObjectTracks)>,
+    pub lights: Vec<(usize, LightTracks)>,
+    pub camera: CameraTracks,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline {
+            frame_rate: 24.0,
+            objects: Vec::new(),
+            lights: Vec::new(),
+            camera: CameraTracks::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty() && self.lights.is_empty() && self.camera.is_empty()
+    }
+
+    /// Tracks of the object at `index`, created on first use
+    pub fn object(&mut self, index: usize) -> &mut ObjectTracks {
+        let position = match self.objects.iter().position(|(i, _)| *i == index) {
+            Some(p) => p,
+            None => {
+                self.objects.push((index, ObjectTracks::default()));
+                self.objects.len() - 1
+            },
+        };
+
+        &mut self.objects[position].1
+    }
+
+    /// Tracks of the light at `index`, created on first use
+    pub fn light(&mut self, index: usize) -> &mut LightTracks {
+        let position = match self.lights.iter().position(|(i, _)| *i == index) {
+            Some(p) => p,
+            None => {
+                self.lights.push((index, LightTracks::default()));
+                self.lights.len() - 1
+            },
+        };
+
+        &mut self.lights[position].1
+    }
+
+    /// Time in seconds at which `frame` is shown
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        frame as f64 / self.frame_rate
+    }
+}
+
+#[cfg(test)]
+mod timeline_test {
+    use crate::animation::Interpolation;
+    use crate::scene::Scene;
+    use crate::object::Object;
+    use crate::transform::Transform;
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_tracks_keep_base_and_skip_stale() {
+        let mut scene = Scene::new();
+        let mut obj = Object::new();
+        // placement from e.g. a scene graph, kept under the animation
+        obj.set_transform(Transform::translation(Vector3::new(0.0, 0.0, -5.0)) * Transform::uniform_scale(2.0));
+        scene.add_object(obj);
+        assert!(scene.timeline.is_empty());
+
+        scene.timeline.object(0).translate
+            .add_key(0.0, Vector3::new(0.0, 0.0, 0.0), Interpolation::Linear)
+            .add_key(1.0, Vector3::new(1.0, 0.0, 0.0), Interpolation::Linear);
+        scene.timeline.object(3).translate.add_key(0.0, Vector3::new(1.0, 0.0, 0.0), Interpolation::Linear);
+        scene.timeline.light(0).color.add_key(0.0, Vector3::new(1.0, 1.0, 1.0), Interpolation::Linear);
+        // motion keyframes win over tracks
+        let mut moving = Object::new();
+        moving.add_keyframe(0.0, Transform::identity()).add_keyframe(1.0, Transform::uniform_scale(2.0));
+        scene.add_object(moving);
+        scene.timeline.object(1).scale.add_key(0.0, Vector3::new(3.0, 3.0, 3.0), Interpolation::Linear);
+
+        assert_eq!(scene.set_time(0.5), 3);
+        assert_eq!(scene.objects()[1].transform, Transform::identity());
+        // the keyed translation is in the base's space, the unkeyed scale is kept
+        let p = scene.objects()[0].transform.transform_point(Vector3::new(0.0, 0.0, 0.0));
+        assert!((p - Vector3::new(1.0, 0.0, -5.0)).magnitude() < 1e-12);
+        scene.set_time(1.0);
+        let p = scene.objects()[0].transform.transform_point(Vector3::new(0.0, 1.0, 0.0));
+        assert!((p - Vector3::new(2.0, 2.0, -5.0)).magnitude() < 1e-12);
+
+        let mut camera_only = Scene::new();
+        camera_only.timeline.camera.fovy.add_key(0.0, 1.0, Interpolation::Linear);
+        assert!(!camera_only.timeline.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use std::ops::{Add, Mul, Sub};
+
+use cgmath::Vector3;
+
+/// Values a track can be keyed with
+pub trait KeyValue: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self> {
+    fn zero() -> Self;
+
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self * (1.0 - t) + other * t
+    }
+}
+
+impl KeyValue for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+}
+
+impl KeyValue for Vector3<f64> {
+    fn zero() -> Self {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+}
+
+/// How a track moves from a key to the next one
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    /// cubic Bezier with handles set from the neighbouring keys, so the curve
+    /// passes smoothly through the keys and eases in and out at both ends
+    Bezier,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Key<T> {
+    pub time: f64,
+    pub value: T,
+    // used for the segment starting at this key
+    pub interpolation: Interpolation,
+}
+
+/// Keyframed value over time, constant before the first and after the last key
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    pub keys: Vec<Key<T>>,
+}
+
+impl<T: KeyValue> Default for Track<T> {
+    fn default() -> Self {
+        Track::new()
+    }
+}
+
+impl<T: KeyValue> Track<T> {
+    pub fn new() -> Track<T> {
+        Track {
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, time: f64, value: T, interpolation: Interpolation) -> &mut Self {
+        let index = self.keys.iter().position(|k| k.time > time).unwrap_or(self.keys.len());
+        self.keys.insert(index, Key {
+            time,
+            value,
+            interpolation,
+        });
+
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Slope at key `i` for the Bezier handles, zero at the first and last key
+    fn tangent(&self, i: usize) -> T {
+        if i == 0 || i + 1 >= self.keys.len() {
+            return T::zero();
+        }
+        let prev = &self.keys[i - 1];
+        let next = &self.keys[i + 1];
+
+        (next.value - prev.value) * (1.0 / (next.time - prev.time))
+    }
+
+    pub fn evaluate(&self, time: f64) -> Option<T> {
+        let first = self.keys.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        let i = match self.keys.iter().position(|k| k.time > time) {
+            Some(i) => i - 1,
+            None => return self.keys.last().map(|k| k.value),
+        };
+
+        let a = &self.keys[i];
+        let b = &self.keys[i + 1];
+        let dt = b.time - a.time;
+        let u = (time - a.time) / dt;
+
+        Some(match a.interpolation {
+            Interpolation::Linear => a.value.lerp(b.value, u),
+            Interpolation::Bezier => {
+                let c1 = a.value + self.tangent(i) * (dt / 3.0);
+                let c2 = b.value - self.tangent(i + 1) * (dt / 3.0);
+                let v = 1.0 - u;
+                a.value * (v * v * v) + c1 * (3.0 * v * v * u) + c2 * (3.0 * v * u * u) + b.value * (u * u * u)
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod track_test {
+    use super::{Track, Interpolation};
+
+    #[test]
+    fn test_linear() {
+        let mut track = Track::new();
+        track.add_key(1.0, 2.0, Interpolation::Linear).add_key(0.0, 0.0, Interpolation::Linear);
+        assert_eq!(track.evaluate(0.25), Some(0.5));
+        assert_eq!(track.evaluate(-1.0), Some(0.0));
+        assert_eq!(track.evaluate(5.0), Some(2.0));
+    }
+
+    #[test]
+    fn test_bezier_passes_keys_and_eases() {
+        let mut track = Track::new();
+        track.add_key(0.0, 0.0, Interpolation::Bezier)
+            .add_key(1.0, 1.0, Interpolation::Bezier)
+            .add_key(2.0, 0.0, Interpolation::Bezier);
+        assert!((track.evaluate(1.0).unwrap() - 1.0).abs() < 1e-12);
+        // flat handles at the ends make the start slower than linear
+        assert!(track.evaluate(0.1).unwrap() < 0.1);
+        // symmetric keys give a symmetric curve
+        assert!((track.evaluate(0.7).unwrap() - track.evaluate(1.3).unwrap()).abs() < 1e-12);
+    }
+}
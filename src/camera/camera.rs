@@ -1,5 +1,6 @@
-use cgmath::{Matrix4, Vector3, Point3, Rad, Vector4, SquareMatrix, InnerSpace, Transform, Matrix};
+use cgmath::{Matrix4, Vector3, Point3, Rad, Vector4, SquareMatrix, InnerSpace};
 use crate::ray::Ray;
+use rand::random;
 
 pub trait Camera {
     fn view(&self) -> Matrix4<f64>;
@@ -10,6 +11,18 @@ pub trait Camera {
 
     fn get_ray(&self, x: f64, y: f64) -> Ray;
 
+    /// Height in pixels of a sphere seen in an image `height` pixels high,
+    /// infinite when the camera is inside or next to it
+    fn projected_size(&self, center: Vector3<f64>, radius: f64, height: usize) -> f64 {
+        let p = self.view() * Vector4::new(center.x, center.y, center.z, 1.0);
+        let depth = -p.z;
+        if depth <= radius {
+            return f64::INFINITY;
+        }
+
+        2.0 * radius * self.proj().y.y / depth * height as f64 / 2.0
+    }
+
     fn map(&self, pos: Vector3<f64>) -> Vector3<f64> {
         let temp = Vector4::new(pos.x, pos.y, pos.z, 1.0);
         let view = self.view();
@@ -33,6 +46,10 @@ pub struct PerspectiveCamera {
     pub aspect: f64,
     pub near: f64,
     pub far: f64,
+
+    // rays are spread over [shutter_open, shutter_close] for motion blur
+    pub shutter_open: f64,
+    pub shutter_close: f64,
 }
 
 impl PerspectiveCamera {
@@ -46,6 +63,9 @@ impl PerspectiveCamera {
             aspect,     // width / height
             near,
             far,
+
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
@@ -66,6 +86,13 @@ impl PerspectiveCamera {
 
         self
     }
+
+    pub fn set_shutter(&mut self, open: f64, close: f64) -> &mut Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+
+        self
+    }
 }
 
 impl Camera for PerspectiveCamera {
@@ -91,13 +118,13 @@ impl Camera for PerspectiveCamera {
         let world = view_inv * world;
         let world = Vector3::new(world.x, world.y, world.z);
 
-        let dir = world.clone() - Vector3::new(self.eye[0], self.eye[1], self.eye[2]);
+        let dir = world - Vector3::new(self.eye[0], self.eye[1], self.eye[2]);
         let dir = dir.normalize();
 
-        Ray {
-            pos: world,
-            dir,
-        }
+        // uniform over the shutter interval
+        let time = self.shutter_open + (self.shutter_close - self.shutter_open) * random::<f64>();
+
+        Ray::new(world, dir).with_time(time)
     }
 }
 
@@ -106,36 +133,27 @@ mod camera_test {
     use super::PerspectiveCamera;
     use crate::camera::Camera;
     use crate::ray::Ray;
-    use cgmath::{Vector3, Matrix4, Vector4};
+    use cgmath::Vector3;
 
     #[test]
     fn test_perspective_camera_1() {
         let camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 1.0, 1.0, 2.0);
         let ray = camera.get_ray(0.0, 0.0);
-        assert_eq!(ray, Ray {
-            pos: Vector3::new(0.0, 0.0, -1.0),
-            dir: Vector3::new(0.0, 0.0, -1.0),
-        });
+        assert_eq!(ray, Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, -1.0)));
     }
 
     #[test]
     fn test_perspective_camera_2() {
         let camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 1.0, 1.0, 3.0);
         let ray = camera.get_ray(0.0, 0.0);
-        assert_eq!(ray, Ray {
-            pos: Vector3::new(0.0, 0.0, -1.0),
-            dir: Vector3::new(0.0, 0.0, -1.0),
-        });
+        assert_eq!(ray, Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, -1.0)));
     }
 
     #[test]
     fn test_perspective_camera_3() {
         let camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 2.0, 2.0, 4.0);
         let ray = camera.get_ray(0.0, 0.0);
-        assert_eq!(ray, Ray {
-            pos: Vector3::new(0.0, 0.0, -2.0),
-            dir: Vector3::new(0.0, 0.0, -1.0),
-        });
+        assert_eq!(ray, Ray::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(0.0, 0.0, -1.0)));
     }
 
     // #[test]
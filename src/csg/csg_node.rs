@@ -0,0 +1,91 @@
+use cgmath::InnerSpace;
+
+use super::solid::{Solid, Span, CsgOperation, combine};
+use crate::ray::Ray;
+use crate::accel::Aabb;
+use crate::transform::Transform;
+
+/// Boolean expression over solids
+pub enum CsgNode {
+    Solid(Box<dyn Solid>),
+    Transformed(Box<Transform>, Box<CsgNode>),
+    Union(Box<CsgNode>, Box<CsgNode>),
+    Intersection(Box<CsgNode>, Box<CsgNode>),
+    Difference(Box<CsgNode>, Box<CsgNode>),
+}
+
+impl CsgNode {
+    pub fn solid<S: Solid + 'static>(solid: S) -> CsgNode {
+        CsgNode::Solid(Box::new(solid))
+    }
+
+    pub fn union(self, other: CsgNode) -> CsgNode {
+        CsgNode::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: CsgNode) -> CsgNode {
+        CsgNode::Intersection(Box::new(self), Box::new(other))
+    }
+
+    /// `self` with `other` cut away
+    pub fn difference(self, other: CsgNode) -> CsgNode {
+        CsgNode::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn transformed(self, transform: Transform) -> CsgNode {
+        CsgNode::Transformed(Box::new(transform), Box::new(self))
+    }
+}
+
+impl Solid for CsgNode {
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        match self {
+            CsgNode::Solid(s) => s.spans(ray),
+            CsgNode::Transformed(transform, node) => {
+                // distances are kept since the direction is not renormalized
+                let mut spans = node.spans(&transform.inverse().transform_ray(ray));
+                for span in spans.iter_mut() {
+                    for hit in [&mut span.enter, &mut span.exit].iter_mut() {
+                        if hit.normal != cgmath::Vector3::new(0.0, 0.0, 0.0) {
+                            hit.normal = transform.transform_normal(hit.normal).normalize();
+                        }
+                    }
+                }
+                spans
+            },
+            CsgNode::Union(a, b) => combine(&a.spans(ray), &b.spans(ray), CsgOperation::Union),
+            CsgNode::Intersection(a, b) => {
+                let left = a.spans(ray);
+                if left.is_empty() {
+                    return left;
+                }
+                combine(&left, &b.spans(ray), CsgOperation::Intersection)
+            },
+            CsgNode::Difference(a, b) => {
+                let left = a.spans(ray);
+                if left.is_empty() {
+                    return left;
+                }
+                combine(&left, &b.spans(ray), CsgOperation::Difference)
+            },
+        }
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self {
+            CsgNode::Solid(s) => s.bounds(),
+            CsgNode::Transformed(transform, node) => transform.transform_aabb(&node.bounds()),
+            CsgNode::Union(a, b) => a.bounds().union(&b.bounds()),
+            CsgNode::Intersection(a, b) => {
+                let (a, b) = (a.bounds(), b.bounds());
+                let mut result = a;
+                for k in 0..3 {
+                    result.min[k] = a.min[k].max(b.min[k]);
+                    result.max[k] = a.max[k].min(b.max[k]);
+                }
+                result
+            },
+            CsgNode::Difference(a, _) => a.bounds(),
+        }
+    }
+}
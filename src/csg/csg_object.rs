@@ -0,0 +1,135 @@
+use cgmath::Vector3;
+
+use super::solid::Solid;
+use super::csg_node::CsgNode;
+use crate::ray::{Ray, gamma};
+use crate::accel::Aabb;
+use crate::material::{Material, NaiveMaterial};
+use crate::medium::HomogeneousMedium;
+use crate::object::{IntersectResult, IntersectDirection, Primitive};
+use crate::object::mesh::Point;
+
+// the solids' intersections carry no error bound of their own, their hits are
+// taken to be off by up to this much, so rays leaving them start clear
+const CSG_ERROR: f64 = 1e-7;
+
+/// A CSG tree placed in the scene with a single material
+pub struct CsgObject {
+    pub root: CsgNode,
+    pub material: Box<dyn Material>,
+    pub interior: Option<HomogeneousMedium>,
+}
+
+impl CsgObject {
+    pub fn new(root: CsgNode) -> CsgObject {
+        CsgObject {
+            root,
+            material: Box::new(NaiveMaterial::default()) as Box<dyn Material>,
+            interior: None,
+        }
+    }
+
+    pub fn set_material(&mut self, material: Box<dyn Material>) -> &mut CsgObject {
+        self.material = material;
+
+        self
+    }
+
+    pub fn set_interior(&mut self, medium: HomogeneousMedium) -> &mut CsgObject {
+        self.interior = Some(medium);
+
+        self
+    }
+}
+
+impl Primitive for CsgObject {
+    fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        for span in self.root.spans(ray) {
+            let (hit, direction) = if span.enter.t >= ray.t_min {
+                (span.enter, IntersectDirection::Positive)
+            } else if span.exit.t >= ray.t_min {
+                (span.exit, IntersectDirection::Negative)
+            } else {
+                continue;
+            };
+            if hit.t > ray.t_max {
+                break;
+            }
+
+            return IntersectResult {
+                point: Some(Point {
+                    vertex: ray.pos + hit.t * ray.dir,
+                    texture: hit.texture,
+                    normal: hit.normal,
+                    tangent: Vector3::new(0.0, 0.0, 0.0),
+                    color: None,
+                }),
+                direction,
+                is_intersect: true,
+                dis: hit.t,
+                object: Some(self),
+                // rounding of the hit point plus the allowance for the solids
+                error: (ray.pos.map(f64::abs) + (hit.t * ray.dir).map(f64::abs)) * gamma(3)
+                    + Vector3::new(CSG_ERROR, CSG_ERROR, CSG_ERROR),
+                geometric_normal: hit.normal,
+                barycentric: Vector3::new(0.0, 0.0, 0.0),
+                uv_sets: Vec::new(),
+            };
+        }
+
+        IntersectResult::no_intersect()
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.root.bounds()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.as_ref()
+    }
+
+    fn interior(&self) -> Option<&HomogeneousMedium> {
+        self.interior.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod csg_object_test {
+    use super::CsgObject;
+    use crate::csg::{CsgNode, Cuboid, Sphere};
+    use crate::object::{IntersectDirection, Primitive};
+    use crate::ray::Ray;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_cube_minus_sphere() {
+        // a sphere bigger than the cube's inscribed one carves out the face centers
+        let solid = CsgNode::solid(Cuboid::cube(2.0))
+            .difference(CsgNode::solid(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.2)));
+        let object = CsgObject::new(solid);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(!object.intersect(&ray).is_intersect);
+
+        // near a corner the ray goes through the cube's face, then into the hollow
+        let ray = Ray {
+            pos: Vector3::new(0.9, 0.9, 5.0),
+            ..ray
+        };
+        let hit = object.intersect(&ray);
+        assert!(hit.is_intersect);
+        assert_eq!(hit.direction, IntersectDirection::Positive);
+        assert!((hit.dis - 4.0).abs() < 1e-9);
+        assert_eq!(hit.point.clone().unwrap().normal, Vector3::new(0.0, 0.0, 1.0));
+
+        // the ray interval is honored at both ends
+        assert!(!object.intersect(&ray.with_t_max(3.9)).is_intersect);
+        let late = object.intersect(&Ray { t_min: 4.1, ..ray });
+        assert!(late.is_intersect);
+        assert_eq!(late.direction, IntersectDirection::Negative);
+
+        // a ray spawned off the surface doesn't find it again
+        let inside = object.intersect(&hit.spawn_ray(Vector3::new(0.0, 0.0, -1.0)));
+        assert!(inside.is_intersect);
+        assert!(inside.dis > 0.01);
+    }
+}
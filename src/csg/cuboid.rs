@@ -0,0 +1,82 @@
+use cgmath::{Vector2, Vector3};
+
+use super::solid::{Solid, Span, SurfaceHit};
+use crate::ray::Ray;
+use crate::accel::Aabb;
+
+/// Axis aligned box, rotate it with a transformed `CsgNode`
+#[derive(Clone, Copy, Debug)]
+pub struct Cuboid {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Cuboid {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>) -> Cuboid {
+        Cuboid {
+            min,
+            max,
+        }
+    }
+
+    /// Cube of edge `size` centered at the origin
+    pub fn cube(size: f64) -> Cuboid {
+        let h = size / 2.0;
+        Cuboid::new(Vector3::new(-h, -h, -h), Vector3::new(h, h, h))
+    }
+
+    fn hit(&self, ray: &Ray, t: f64, axis: usize, sign: f64) -> SurfaceHit {
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        normal[axis] = sign;
+        // the face's other two axes, normalized over the box
+        let p = ray.pos + t * ray.dir;
+        let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+        let u = (p[i] - self.min[i]) / (self.max[i] - self.min[i]);
+        let v = (p[j] - self.min[j]) / (self.max[j] - self.min[j]);
+
+        SurfaceHit::new(t, normal, Vector2::new(u, v))
+    }
+}
+
+impl Solid for Cuboid {
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let mut t0 = f64::NEG_INFINITY;
+        let mut t1 = f64::INFINITY;
+        let mut enter_face = (0, -1.0);
+        let mut exit_face = (0, 1.0);
+        for k in 0..3 {
+            if ray.dir[k] == 0.0 {
+                if ray.pos[k] < self.min[k] || ray.pos[k] > self.max[k] {
+                    return Vec::new();
+                }
+                continue;
+            }
+            let inv = 1.0 / ray.dir[k];
+            let (near, far, sign) = if inv > 0.0 {
+                ((self.min[k] - ray.pos[k]) * inv, (self.max[k] - ray.pos[k]) * inv, -1.0)
+            } else {
+                ((self.max[k] - ray.pos[k]) * inv, (self.min[k] - ray.pos[k]) * inv, 1.0)
+            };
+            if near > t0 {
+                t0 = near;
+                enter_face = (k, sign);
+            }
+            if far < t1 {
+                t1 = far;
+                exit_face = (k, -sign);
+            }
+        }
+        if t0 >= t1 {
+            return Vec::new();
+        }
+
+        vec![Span {
+            enter: self.hit(ray, t0, enter_face.0, enter_face.1),
+            exit: self.hit(ray, t1, exit_face.0, exit_face.1),
+        }]
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
+}
@@ -0,0 +1,97 @@
+use std::f64::consts::PI;
+
+use cgmath::{Vector2, Vector3};
+
+use super::solid::{Solid, Span, SurfaceHit};
+use crate::ray::Ray;
+use crate::accel::Aabb;
+
+/// Capped cylinder standing on `base`, its axis along +y
+#[derive(Clone, Copy, Debug)]
+pub struct Cylinder {
+    pub base: Vector3<f64>,
+    pub radius: f64,
+    pub height: f64,
+}
+
+impl Cylinder {
+    pub fn new(base: Vector3<f64>, radius: f64, height: f64) -> Cylinder {
+        Cylinder {
+            base,
+            radius,
+            height,
+        }
+    }
+
+    fn side_hit(&self, ray: &Ray, t: f64) -> SurfaceHit {
+        let p = ray.pos + t * ray.dir - self.base;
+        let normal = Vector3::new(p.x, 0.0, p.z) / self.radius;
+        let u = 0.5 + p.z.atan2(p.x) / (2.0 * PI);
+
+        SurfaceHit::new(t, normal, Vector2::new(u, p.y / self.height))
+    }
+
+    fn cap_hit(&self, ray: &Ray, t: f64, top: bool) -> SurfaceHit {
+        let p = ray.pos + t * ray.dir - self.base;
+        let normal = Vector3::new(0.0, if top { 1.0 } else { -1.0 }, 0.0);
+        let texture = Vector2::new(0.5 + p.x / (2.0 * self.radius), 0.5 + p.z / (2.0 * self.radius));
+
+        SurfaceHit::new(t, normal, texture)
+    }
+}
+
+impl Solid for Cylinder {
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let o = ray.pos - self.base;
+        let d = ray.dir;
+
+        // infinite cylinder
+        let a = d.x * d.x + d.z * d.z;
+        let (side0, side1) = if a == 0.0 {
+            if o.x * o.x + o.z * o.z > self.radius * self.radius {
+                return Vec::new();
+            }
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            let half_b = o.x * d.x + o.z * d.z;
+            let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+            let discriminant = half_b * half_b - a * c;
+            if discriminant <= 0.0 {
+                return Vec::new();
+            }
+            let root = discriminant.sqrt();
+            ((-half_b - root) / a, (-half_b + root) / a)
+        };
+
+        // slab between the caps
+        let (cap0, cap1, bottom_first) = if d.y == 0.0 {
+            if o.y < 0.0 || o.y > self.height {
+                return Vec::new();
+            }
+            (f64::NEG_INFINITY, f64::INFINITY, true)
+        } else {
+            let t_bottom = -o.y / d.y;
+            let t_top = (self.height - o.y) / d.y;
+            (t_bottom.min(t_top), t_bottom.max(t_top), d.y > 0.0)
+        };
+
+        let t0 = side0.max(cap0);
+        let t1 = side1.min(cap1);
+        if t0 >= t1 {
+            return Vec::new();
+        }
+
+        let enter = if side0 >= cap0 { self.side_hit(ray, t0) } else { self.cap_hit(ray, t0, !bottom_first) };
+        let exit = if side1 <= cap1 { self.side_hit(ray, t1) } else { self.cap_hit(ray, t1, bottom_first) };
+
+        vec![Span {
+            enter,
+            exit,
+        }]
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vector3::new(self.radius, 0.0, self.radius);
+        Aabb::new(self.base - r, self.base + r + Vector3::new(0.0, self.height, 0.0))
+    }
+}
@@ -0,0 +1,13 @@
+pub mod solid;
+pub mod sphere;
+pub mod cuboid;
+pub mod cylinder;
+pub mod csg_node;
+pub mod csg_object;
+
+pub use solid::{Solid, Span, SurfaceHit};
+pub use sphere::Sphere;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use csg_node::CsgNode;
+pub use csg_object::CsgObject;
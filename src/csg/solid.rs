@@ -0,0 +1,176 @@
+use cgmath::{Vector2, Vector3};
+
+use crate::ray::Ray;
+use crate::accel::Aabb;
+use crate::object::{Object, IntersectDirection};
+
+/// A point where a ray crosses the boundary of a solid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceHit {
+    pub t: f64,
+    // pointing out of the solid
+    pub normal: Vector3<f64>,
+    pub texture: Vector2<f64>,
+}
+
+impl SurfaceHit {
+    pub fn new(t: f64, normal: Vector3<f64>, texture: Vector2<f64>) -> SurfaceHit {
+        SurfaceHit {
+            t,
+            normal,
+            texture,
+        }
+    }
+
+    /// Stands for a boundary behind the ray's origin that was not found
+    pub fn unbounded(t: f64) -> SurfaceHit {
+        SurfaceHit::new(t, Vector3::new(0.0, 0.0, 0.0), Vector2::new(0.0, 0.0))
+    }
+
+    fn flipped(self) -> SurfaceHit {
+        SurfaceHit {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+/// Part of a ray inside a solid, from where it enters to where it leaves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub enter: SurfaceHit,
+    pub exit: SurfaceHit,
+}
+
+/// Closed shapes that report every interval a ray spends inside them.
+/// Distances may be negative, spans behind the origin are kept so rays
+/// starting inside a solid are classified correctly.
+pub trait Solid {
+    /// Sorted, non overlapping spans along `ray`
+    fn spans(&self, ray: &Ray) -> Vec<Span>;
+
+    fn bounds(&self) -> Aabb;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(&self, a: bool, b: bool) -> bool {
+        match self {
+            CsgOperation::Union => a || b,
+            CsgOperation::Intersection => a && b,
+            CsgOperation::Difference => a && !b,
+        }
+    }
+}
+
+/// Merges the spans of two solids following `op`. Boundaries taken from the
+/// subtracted solid of a difference get their normals flipped.
+pub fn combine(a: &[Span], b: &[Span], op: CsgOperation) -> Vec<Span> {
+    // (hit, from b, entering)
+    let mut events: Vec<(SurfaceHit, bool, bool)> = Vec::with_capacity(2 * (a.len() + b.len()));
+    for (spans, from_b) in [(a, false), (b, true)].iter() {
+        for span in spans.iter() {
+            events.push((span.enter, *from_b, true));
+            events.push((span.exit, *from_b, false));
+        }
+    }
+    events.sort_by(|x, y| x.0.t.partial_cmp(&y.0.t).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut result = Vec::new();
+    let mut in_a = false;
+    let mut in_b = false;
+    let mut enter: Option<SurfaceHit> = None;
+    for (hit, from_b, entering) in events {
+        let was_inside = op.inside(in_a, in_b);
+        if from_b {
+            in_b = entering;
+        } else {
+            in_a = entering;
+        }
+        let inside = op.inside(in_a, in_b);
+        if inside == was_inside {
+            continue;
+        }
+
+        let hit = if from_b && op == CsgOperation::Difference { hit.flipped() } else { hit };
+        if inside {
+            enter = Some(hit);
+        } else if let Some(e) = enter.take() {
+            result.push(Span {
+                enter: e,
+                exit: hit,
+            });
+        }
+    }
+
+    result
+}
+
+/// Closed meshes, triangles facing the ray open a span and the others close it
+impl Solid for Object {
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let mut result = Vec::new();
+        let mut enter: Option<SurfaceHit> = None;
+        // spans must cover the whole line, the ray interval is applied after combining
+        let line = Ray {
+            t_max: f64::INFINITY,
+            ..*ray
+        };
+        for hit in self.intersect_all(&line) {
+            let point = hit.point.unwrap();
+            let surface = SurfaceHit::new(hit.dis, point.normal, point.texture);
+            match hit.direction {
+                IntersectDirection::Positive => {
+                    if enter.is_none() {
+                        enter = Some(surface);
+                    }
+                },
+                IntersectDirection::Negative => {
+                    // hits behind the origin are not reported, the ray started inside
+                    let e = enter.take().unwrap_or_else(|| SurfaceHit::unbounded(f64::NEG_INFINITY));
+                    result.push(Span {
+                        enter: e,
+                        exit: surface,
+                    });
+                },
+            }
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Aabb {
+        Object::bounds(self)
+    }
+}
+
+#[cfg(test)]
+mod solid_test {
+    use super::{combine, CsgOperation, Span, SurfaceHit};
+
+    fn span(a: f64, b: f64) -> Span {
+        Span {
+            enter: SurfaceHit::unbounded(a),
+            exit: SurfaceHit::unbounded(b),
+        }
+    }
+
+    fn ranges(spans: &[Span]) -> Vec<(f64, f64)> {
+        spans.iter().map(|s| (s.enter.t, s.exit.t)).collect()
+    }
+
+    #[test]
+    fn test_combine() {
+        let a = [span(1.0, 4.0), span(6.0, 8.0)];
+        let b = [span(3.0, 7.0)];
+        assert_eq!(ranges(&combine(&a, &b, CsgOperation::Union)), vec![(1.0, 8.0)]);
+        assert_eq!(ranges(&combine(&a, &b, CsgOperation::Intersection)), vec![(3.0, 4.0), (6.0, 7.0)]);
+        assert_eq!(ranges(&combine(&a, &b, CsgOperation::Difference)), vec![(1.0, 3.0), (7.0, 8.0)]);
+    }
+}
@@ -0,0 +1,58 @@
+use std::f64::consts::PI;
+
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+
+use super::solid::{Solid, Span, SurfaceHit};
+use crate::ray::Ray;
+use crate::accel::Aabb;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sphere {
+    pub center: Vector3<f64>,
+    pub radius: f64,
+}
+
+impl Sphere {
+    pub fn new(center: Vector3<f64>, radius: f64) -> Sphere {
+        Sphere {
+            center,
+            radius,
+        }
+    }
+
+    fn hit(&self, ray: &Ray, t: f64) -> SurfaceHit {
+        let normal = (ray.pos + t * ray.dir - self.center) / self.radius;
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / PI;
+
+        SurfaceHit::new(t, normal, Vector2::new(u, v))
+    }
+}
+
+impl Solid for Sphere {
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let oc = ray.pos - self.center;
+        let a = ray.dir.magnitude2();
+        let half_b = oc.dot(ray.dir);
+        let c = oc.magnitude2() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return Vec::new();
+        }
+
+        let root = discriminant.sqrt();
+        let t0 = (-half_b - root) / a;
+        let t1 = (-half_b + root) / a;
+
+        vec![Span {
+            enter: self.hit(ray, t0),
+            exit: self.hit(ray, t1),
+        }]
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
@@ -0,0 +1,279 @@
+use std::f64::consts::SQRT_2;
+
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+
+use crate::ray::Ray;
+use crate::accel::Aabb;
+use crate::ray::coordinate_system;
+use crate::object::{IntersectResult, IntersectDirection};
+use crate::object::mesh::Point;
+
+/// How the width of a curve is turned into a surface
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveType {
+    /// A flat strip always facing the ray
+    Ribbon,
+    /// A tube, intersected as a ribbon but with normals bending around it
+    Cylinder,
+}
+
+/// One cubic Bézier segment of a strand with a width varying linearly along it
+#[derive(Clone, Debug)]
+pub struct Curve {
+    pub points: [Vector3<f64>; 4],
+    // width at the start and the end
+    pub width: [f64; 2],
+    pub kind: CurveType,
+    // part of the whole strand the segment covers, becomes u of the hits
+    pub u_range: [f64; 2],
+}
+
+fn bezier_point(cp: &[Vector3<f64>; 4], u: f64) -> Vector3<f64> {
+    let s = 1.0 - u;
+    cp[0] * (s * s * s) + cp[1] * (3.0 * s * s * u) + cp[2] * (3.0 * s * u * u) + cp[3] * (u * u * u)
+}
+
+fn bezier_derivative(cp: &[Vector3<f64>; 4], u: f64) -> Vector3<f64> {
+    let s = 1.0 - u;
+    (cp[1] - cp[0]) * (3.0 * s * s) + (cp[2] - cp[1]) * (6.0 * s * u) + (cp[3] - cp[2]) * (3.0 * u * u)
+}
+
+/// The two halves of the curve split at u = 0.5, by de Casteljau
+fn bezier_split(cp: &[Vector3<f64>; 4]) -> ([Vector3<f64>; 4], [Vector3<f64>; 4]) {
+    let a = (cp[0] + cp[1]) / 2.0;
+    let b = (cp[1] + cp[2]) / 2.0;
+    let c = (cp[2] + cp[3]) / 2.0;
+    let ab = (a + b) / 2.0;
+    let bc = (b + c) / 2.0;
+    let mid = (ab + bc) / 2.0;
+
+    ([cp[0], a, ab, mid], [mid, bc, c, cp[3]])
+}
+
+impl Curve {
+    pub fn new(points: [Vector3<f64>; 4], width: [f64; 2], kind: CurveType) -> Curve {
+        Curve {
+            points,
+            width,
+            kind,
+            u_range: [0.0, 1.0],
+        }
+    }
+
+    /// The span of a uniform cubic B-spline controlled by `points`
+    pub fn from_bspline(points: [Vector3<f64>; 4], width: [f64; 2], kind: CurveType) -> Curve {
+        let [p0, p1, p2, p3] = points;
+        Curve::new([
+            (p0 + p1 * 4.0 + p2) / 6.0,
+            (p1 * 2.0 + p2) / 3.0,
+            (p1 + p2 * 2.0) / 3.0,
+            (p1 + p2 * 4.0 + p3) / 6.0,
+        ], width, kind)
+    }
+
+    pub fn point(&self, u: f64) -> Vector3<f64> {
+        bezier_point(&self.points, u)
+    }
+
+    /// Derivative by u, falls back to the chord where the curve stops
+    pub fn tangent(&self, u: f64) -> Vector3<f64> {
+        let d = bezier_derivative(&self.points, u);
+        if d.magnitude2() > 0.0 { d } else { self.points[3] - self.points[0] }
+    }
+
+    pub fn width_at(&self, u: f64) -> f64 {
+        self.width[0] + (self.width[1] - self.width[0]) * u
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        // the curve stays in the hull of its control points
+        let mut b = Aabb::from_points(self.points.iter());
+        let r = self.width[0].max(self.width[1]) / 2.0;
+        b.min -= Vector3::new(r, r, r);
+        b.max += Vector3::new(r, r, r);
+        b
+    }
+
+    /// Nearest hit along `ray`, the result has no object attached.
+    /// The curve is split in ray space until its pieces are close enough to
+    /// lines, which are then tested against the width, as in pbrt
+    pub fn intersect<'a>(&self, ray: &Ray) -> IntersectResult<'a> {
+        // a frame with the ray starting at the origin and pointing along +z
+        let length = ray.dir.magnitude();
+        let dz = ray.dir / length;
+        let (dx, dy) = coordinate_system(dz);
+        let to_ray = |p: Vector3<f64>| {
+            let d = p - ray.pos;
+            Vector3::new(d.dot(dx), d.dot(dy), d.dot(dz))
+        };
+        let cp = self.points.map(to_ray);
+
+        // splits needed before a piece is within a twentieth of the width of its chord
+        let flatness = (0..2)
+            .map(|i| {
+                let d = cp[i] - cp[i + 1] * 2.0 + cp[i + 2];
+                d.x.abs().max(d.y.abs()).max(d.z.abs())
+            })
+            .fold(0.0, f64::max);
+        let eps = self.width[0].max(self.width[1]) * 0.05;
+        let depth = if flatness > 0.0 && eps > 0.0 {
+            ((SQRT_2 * 6.0 * flatness / (8.0 * eps)).log2() / 2.0).ceil().clamp(0.0, 10.0) as u32
+        } else {
+            0
+        };
+
+        let (z, u) = match self.recursive_intersect(&cp, 0.0, 1.0, depth, ray.t_min * length, ray.t_max * length) {
+            Some(hit) => hit,
+            None => return IntersectResult::no_intersect(),
+        };
+        let t = z / length;
+        let vertex = ray.pos + ray.dir * t;
+        let width = self.width_at(u);
+
+        // the ribbon is the plane through the tangent facing the ray
+        let tangent = self.tangent(u).normalize();
+        let facing = -dz;
+        let mut ribbon = facing - tangent * facing.dot(tangent);
+        ribbon = if ribbon.magnitude2() > 0.0 { ribbon.normalize() } else { facing };
+        let offset = vertex - self.point(u);
+        let side = offset.dot(ribbon.cross(tangent)) / width;
+        let normal = match self.kind {
+            CurveType::Ribbon => ribbon,
+            CurveType::Cylinder => {
+                // the part of the circle seen at this offset from the axis
+                let s = (offset.magnitude() * 2.0 / width).min(1.0);
+                let across = if offset.magnitude2() > 0.0 { offset.normalize() } else { Vector3::zero() };
+                (ribbon * (1.0 - s * s).sqrt() + across * s).normalize()
+            },
+        };
+
+        IntersectResult {
+            point: Some(Point {
+                vertex,
+                texture: Vector2::new(self.u_range[0] + (self.u_range[1] - self.u_range[0]) * u, 0.5 + side),
+                normal,
+                tangent,
+                color: None,
+            }),
+            // curves have no inside
+            direction: IntersectDirection::Positive,
+            is_intersect: true,
+            dis: t,
+            object: None,
+            // the hit lies on the ribbon, anywhere across the width of the strand
+            error: Vector3::new(width, width, width),
+            geometric_normal: normal,
+            barycentric: Vector3::zero(),
+            uv_sets: Vec::new(),
+        }
+    }
+
+    /// Depth along the ray and u of the nearest hit on the piece `cp` covering
+    /// [u0, u1], given in ray space
+    fn recursive_intersect(&self, cp: &[Vector3<f64>; 4], u0: f64, u1: f64, depth: u32, z_min: f64, z_max: f64) -> Option<(f64, f64)> {
+        let r = self.width_at(u0).max(self.width_at(u1)) / 2.0;
+        let b = Aabb::from_points(cp.iter());
+        if b.min.x > r || b.max.x < -r || b.min.y > r || b.max.y < -r || b.min.z > z_max + r || b.max.z < z_min - r {
+            return None;
+        }
+
+        if depth > 0 {
+            let (first, second) = bezier_split(cp);
+            let mid = (u0 + u1) / 2.0;
+            let first_hit = self.recursive_intersect(&first, u0, mid, depth - 1, z_min, z_max);
+            let second_hit = self.recursive_intersect(&second, mid, u1, depth - 1, z_min, first_hit.map_or(z_max, |hit| hit.0));
+            return second_hit.or(first_hit);
+        }
+
+        // the ray must pass between the planes through the ends of the piece
+        // perpendicular to its tangents there
+        if (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x) < 0.0 {
+            return None;
+        }
+        if (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x) < 0.0 {
+            return None;
+        }
+
+        // closest point of the chord to the ray
+        let chord = Vector2::new(cp[3].x - cp[0].x, cp[3].y - cp[0].y);
+        let denom = chord.magnitude2();
+        if denom == 0.0 {
+            return None;
+        }
+        let w = (-(cp[0].x * chord.x + cp[0].y * chord.y) / denom).clamp(0.0, 1.0);
+        let u = u0 + (u1 - u0) * w;
+        let p = bezier_point(cp, w);
+        let width = self.width_at(u);
+        if p.x * p.x + p.y * p.y > width * width / 4.0 || p.z < z_min || p.z > z_max {
+            return None;
+        }
+
+        Some((p.z, u))
+    }
+}
+
+#[cfg(test)]
+mod curve_test {
+    use super::{Curve, CurveType};
+    use crate::ray::Ray;
+    use cgmath::{InnerSpace, Vector3};
+
+    #[test]
+    fn test_intersect_varying_width() {
+        // a straight strand along x at z = -2, 0.2 wide at the start and 0.1 at the end
+        let points = [-1.0, -1.0 / 3.0, 1.0 / 3.0, 1.0].map(|x| Vector3::new(x, 0.0, -2.0));
+        let curve = Curve::new(points, [0.2, 0.1], CurveType::Cylinder);
+
+        let ray = |x: f64, y: f64| Ray::new(Vector3::new(x, y, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = curve.intersect(&ray(0.0, 0.0));
+        assert!(hit.is_intersect);
+        assert!((hit.dis - 2.0).abs() < 1e-9);
+        let point = hit.point.unwrap();
+        assert!((point.texture.x - 0.5).abs() < 1e-9);
+        assert!((point.tangent - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((point.normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+
+        // 0.06 off the axis is inside near the start, outside near the end
+        assert!(curve.intersect(&ray(-0.8, 0.06)).is_intersect);
+        assert!(!curve.intersect(&ray(0.8, 0.06)).is_intersect);
+        assert!(!curve.intersect(&ray(1.1, 0.0)).is_intersect);
+
+        // the normal of a tube bends towards the side that is hit
+        let side = curve.intersect(&ray(-0.8, 0.05)).point.unwrap();
+        assert!(side.normal.y > 0.5 && side.normal.z > 0.0);
+        assert!(side.texture.y > 0.5);
+    }
+
+    #[test]
+    fn test_intersect_bent_curve() {
+        // an arc from (-1, 0) through about (0, 0.75) to (1, 0) in the z = -1 plane
+        let curve = Curve::new([
+            Vector3::new(-1.0, 0.0, -1.0),
+            Vector3::new(-1.0, 1.0, -1.0),
+            Vector3::new(1.0, 1.0, -1.0),
+            Vector3::new(1.0, 0.0, -1.0),
+        ], [0.02, 0.02], CurveType::Ribbon);
+
+        let top = curve.intersect(&Ray::new(Vector3::new(0.0, 0.75, 0.0), Vector3::new(0.0, 0.0, -1.0)));
+        assert!(top.is_intersect);
+        assert!((top.point.unwrap().texture.x - 0.5).abs() < 1e-3);
+        assert!(!curve.intersect(&Ray::new(Vector3::new(0.0, 0.7, 0.0), Vector3::new(0.0, 0.0, -1.0))).is_intersect);
+
+        // a ray in the plane of the arc sees it edge on
+        let along = curve.intersect(&Ray::new(Vector3::new(-0.5, 2.0, -1.0), Vector3::new(0.0, -1.0, 0.0)));
+        assert!(along.is_intersect);
+        let p = along.point.unwrap().vertex;
+        assert!((bezier_y(&curve, -0.5) - p.y).abs() < 0.02);
+    }
+
+    // height of the arc above x, found by bisection on u
+    fn bezier_y(curve: &Curve, x: f64) -> f64 {
+        let (mut lo, mut hi) = (0.0, 0.5);
+        for _ in 0..60 {
+            let mid = (lo + hi) / 2.0;
+            if curve.point(mid).x < x { lo = mid } else { hi = mid }
+        }
+        curve.point(lo).y
+    }
+}
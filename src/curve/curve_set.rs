@@ -0,0 +1,235 @@
+use std::cell::OnceCell;
+use std::fs;
+
+use cgmath::Vector3;
+
+use super::curve::{Curve, CurveType};
+use crate::ray::Ray;
+use crate::accel::{Aabb, Bvh};
+use crate::material::{Material, NaiveMaterial};
+use crate::object::{IntersectResult, Primitive};
+
+/// How the control points of a strand are joined
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveBasis {
+    /// Piecewise cubic Bézier, 3n + 1 points passing through every third one
+    Bezier,
+    /// Uniform cubic B-spline, at least four points, smooth but passing through none
+    BSpline,
+}
+
+/// Strands of hair or fur, each split into cubic segments that get their own
+/// entries in a bvh
+pub struct CurveSet {
+    pub curves: Vec<Curve>,
+    pub material: Box<dyn Material>,
+
+    bvh: OnceCell<Bvh>,
+}
+
+impl Default for CurveSet {
+    fn default() -> Self {
+        CurveSet::new()
+    }
+}
+
+impl CurveSet {
+    pub fn new() -> CurveSet {
+        CurveSet {
+            curves: Vec::new(),
+            material: Box::new(NaiveMaterial::default()) as Box<dyn Material>,
+
+            bvh: OnceCell::new(),
+        }
+    }
+
+    /// Reads a curves file, one strand per line:
+    /// `strand <bezier|bspline> <ribbon|cylinder> <root width> <tip width> x y z ...`.
+    /// Empty lines and lines starting with `#` are skipped
+    pub fn from_file(filename: &str) -> Result<CurveSet, String> {
+        let contents = fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        CurveSet::from_curves_str(&contents)
+    }
+
+    pub fn from_curves_str(contents: &str) -> Result<CurveSet, String> {
+        let mut set = CurveSet::new();
+        for (number, line) in contents.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => continue,
+                Some(word) if word.starts_with('#') => continue,
+                Some("strand") => (),
+                Some(word) => return Err(format!("line {}: unknown keyword `{}`", number + 1, word)),
+            }
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+
+            let basis = match words.next() {
+                Some("bezier") => CurveBasis::Bezier,
+                Some("bspline") => CurveBasis::BSpline,
+                other => return Err(error(format!("unknown curve basis `{}`", other.unwrap_or("")))),
+            };
+            let kind = match words.next() {
+                Some("ribbon") => CurveType::Ribbon,
+                Some("cylinder") => CurveType::Cylinder,
+                other => return Err(error(format!("unknown curve type `{}`", other.unwrap_or("")))),
+            };
+            let values = words
+                .map(|w| w.parse::<f64>().map_err(|_| error(format!("`{}` is not a number", w))))
+                .collect::<Result<Vec<f64>, String>>()?;
+            if values.len() < 2 || !(values.len() - 2).is_multiple_of(3) {
+                return Err(error(String::from("expected two widths and then x y z of each point")));
+            }
+            let points: Vec<Vector3<f64>> = values[2..].chunks(3)
+                .map(|c| Vector3::new(c[0], c[1], c[2]))
+                .collect();
+            set.add_strand(&points, basis, kind, [values[0], values[1]]).map_err(error)?;
+        }
+
+        Ok(set)
+    }
+
+    pub fn set_material(&mut self, material: Box<dyn Material>) -> &mut Self {
+        self.material = material;
+
+        self
+    }
+
+    pub fn add_curve(&mut self, curve: Curve) -> &mut Self {
+        self.curves.push(curve);
+        self.bvh = OnceCell::new();
+
+        self
+    }
+
+    /// Adds a strand as its cubic segments, the width goes linearly from
+    /// `width[0]` at the root to `width[1]` at the tip. Widths must be finite
+    /// and not negative, points finite
+    pub fn add_strand(&mut self, points: &[Vector3<f64>], basis: CurveBasis, kind: CurveType, width: [f64; 2]) -> Result<&mut Self, String> {
+        if let Some(w) = width.iter().find(|w| !w.is_finite() || **w < 0.0) {
+            return Err(format!("invalid strand width {}", w));
+        }
+        if let Some(p) = points.iter().find(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+            return Err(format!("invalid strand point {} {} {}", p.x, p.y, p.z));
+        }
+        let spans: Vec<Curve> = match basis {
+            CurveBasis::Bezier => {
+                if points.len() < 4 || !(points.len() - 1).is_multiple_of(3) {
+                    return Err(format!("a bezier strand needs 3n + 1 points, got {}", points.len()));
+                }
+                points.windows(4).step_by(3)
+                    .map(|p| Curve::new([p[0], p[1], p[2], p[3]], width, kind))
+                    .collect()
+            },
+            CurveBasis::BSpline => {
+                if points.len() < 4 {
+                    return Err(format!("a b-spline strand needs at least 4 points, got {}", points.len()));
+                }
+                points.windows(4)
+                    .map(|p| Curve::from_bspline([p[0], p[1], p[2], p[3]], width, kind))
+                    .collect()
+            },
+        };
+
+        let count = spans.len() as f64;
+        for (i, mut curve) in spans.into_iter().enumerate() {
+            curve.u_range = [i as f64 / count, (i + 1) as f64 / count];
+            let lerp = |u: f64| width[0] + (width[1] - width[0]) * u;
+            curve.width = [lerp(curve.u_range[0]), lerp(curve.u_range[1])];
+            self.add_curve(curve);
+        }
+
+        Ok(self)
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<Aabb> = self.curves.iter().map(|c| c.bounds()).collect();
+            Bvh::build(&bounds)
+        })
+    }
+}
+
+impl Primitive for CurveSet {
+    fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        let mut result = IntersectResult::no_intersect();
+        self.bvh().intersect(ray, ray.t_max, |i, t_max| {
+            let r = self.curves[i].intersect(&ray.with_t_max(t_max));
+            if r.is_intersect {
+                let t = r.dis;
+                result = r;
+                Some(t)
+            } else {
+                None
+            }
+        });
+        if result.is_intersect {
+            result.object = Some(self);
+        }
+
+        result
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh().any(ray, ray.t_max, |i| self.curves[i].intersect(ray).is_intersect)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bvh().bounds()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod curve_set_test {
+    use super::CurveSet;
+    use crate::object::Primitive;
+    use crate::ray::Ray;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_load_and_intersect() {
+        let set = CurveSet::from_curves_str("\
+            # two strands standing on the z = 0 plane\n\
+            strand bezier cylinder 0.1 0.02  0 0 0  0 0 1  0 0 2  0 0 3  0 0 4  0 0 5  0 0 6\n\
+            \n\
+            strand bspline ribbon 0.1 0.1  2 0 0  2 0 0  2 0 1  2 0 2  2 0 2\n\
+        ").unwrap();
+        assert_eq!(set.curves.len(), 4);
+        assert_eq!(set.curves[1].u_range, [0.5, 1.0]);
+        assert!((set.curves[1].width[0] - 0.06).abs() < 1e-12);
+
+        // a ray across the first strand half way up meets it where its two segments join
+        let hit = set.intersect(&Ray::new(Vector3::new(0.0, -1.0, 3.0), Vector3::new(0.0, 1.0, 0.0)));
+        assert!(hit.is_intersect);
+        assert!((hit.point.as_ref().unwrap().texture.x - 0.5).abs() < 1e-6);
+        assert!(hit.object.is_some());
+
+        // the b-spline ends short of its doubled end points
+        let side = Ray::new(Vector3::new(2.0, -1.0, 0.1), Vector3::new(0.0, 1.0, 0.0));
+        assert!(!set.occluded(&side));
+        assert!(set.occluded(&Ray { pos: Vector3::new(2.0, -1.0, 1.0), ..side }));
+
+        assert!(CurveSet::from_curves_str("strand bezier ribbon 0.1 0.1  0 0 0  1 1 1").is_err());
+        assert!(CurveSet::from_curves_str("hair bezier ribbon 0.1").is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_numbers() {
+        let points = "0 0 0  0 0 1  0 0 2  0 0 3";
+        for widths in ["nan 0.1", "0.1 inf", "-0.1 0.1"] {
+            let line = format!("strand bezier ribbon {}  {}", widths, points);
+            let error = CurveSet::from_curves_str(&format!("# comment\n{}\n", line)).err().unwrap();
+            assert!(error.starts_with("line 2: invalid strand width"), "{}", error);
+        }
+        for point in ["nan 0 3", "0 -inf 3", "0 0 1e400"] {
+            let line = format!("strand bspline cylinder 0.1 0.1  0 0 0  0 0 1  0 0 2  {}", point);
+            let error = CurveSet::from_curves_str(&line).err().unwrap();
+            assert!(error.starts_with("line 1: invalid strand point"), "{}", error);
+        }
+        // zero widths and negative coordinates are fine
+        assert!(CurveSet::from_curves_str("strand bezier ribbon 0 0  0 0 -1  0 -1 -1  -1 -1 -1  -1 -1 -2").is_ok());
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod curve;
+pub mod curve_set;
+
+pub use curve::{Curve, CurveType};
+pub use curve_set::{CurveSet, CurveBasis};
@@ -0,0 +1,391 @@
+use std::rc::Rc;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector2, Vector3, Vector4};
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use image::RgbImage;
+
+use crate::object::{Mesh, Object};
+use crate::object::mesh::{FaceStruct, PointStruct};
+use crate::scene::{Scene, SceneNode};
+use crate::camera::PerspectiveCamera;
+use crate::light::{PointLight, DirectionalLight, SpotLight, Falloff};
+use crate::material::{PbrMaterial, MaterialValue1, MaterialValue3};
+use crate::texture::{ImageTexture, Multiply};
+use crate::transform::Transform;
+
+/// Contents of a glTF file. The scene holds the objects and lights, cameras
+/// are returned separately in the order of the nodes using them
+pub struct GltfScene {
+    pub scene: Scene,
+    pub cameras: Vec<PerspectiveCamera>,
+}
+
+/// Loads `.gltf` and `.glb` files: the node hierarchy, triangle meshes with
+/// metallic-roughness materials, perspective cameras and KHR_lights_punctual
+/// lights. Orthographic cameras, skins and morph targets are skipped
+pub struct GltfImporter {
+    // glTF light intensities are physical (candela, lux), the color is
+    // multiplied by intensity times this scale
+    pub light_scale: f64,
+    // aspect ratio of cameras that don't specify one
+    pub aspect: f64,
+}
+
+impl Default for GltfImporter {
+    fn default() -> Self {
+        GltfImporter::new()
+    }
+}
+
+fn to_matrix(m: [[f32; 4]; 4]) -> Matrix4<f64> {
+    let c = |i: usize| Vector4::new(m[i][0] as f64, m[i][1] as f64, m[i][2] as f64, m[i][3] as f64);
+    Matrix4::from_cols(c(0), c(1), c(2), c(3))
+}
+
+fn to_vector3(v: [f32; 3]) -> Vector3<f64> {
+    Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+}
+
+/// glTF puts the uv origin at the top left of the image, here it is the bottom left
+fn to_uv(uv: [f32; 2]) -> Vector2<f64> {
+    Vector2::new(uv[0] as f64, 1.0 - uv[1] as f64)
+}
+
+fn to_rgb_image(data: &gltf::image::Data) -> RgbImage {
+    let (channels, size) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let value = |i: usize| -> u8 {
+        let b = &data.pixels[i * size..(i + 1) * size];
+        match size {
+            1 => b[0],
+            2 => (u16::from_ne_bytes([b[0], b[1]]) >> 8) as u8,
+            _ => (f32::from_ne_bytes([b[0], b[1], b[2], b[3]]).clamp(0.0, 1.0) * 255.0) as u8,
+        }
+    };
+
+    RgbImage::from_fn(data.width, data.height, |x, y| {
+        let first = (y * data.width + x) as usize * channels;
+        // one and two channel images are gray, possibly with alpha
+        if channels <= 2 {
+            let v = value(first);
+            image::Rgb([v, v, v])
+        } else {
+            image::Rgb([value(first), value(first + 1), value(first + 2)])
+        }
+    })
+}
+
+/// One mesh per triangle primitive, None for points and lines
+fn read_primitive(primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data]) -> Result<Option<Mesh>, String> {
+    if primitive.mode() != Mode::Triangles {
+        return Ok(None);
+    }
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let mut mesh = Mesh::new();
+    for p in reader.read_positions().ok_or("gltf primitive has no positions")? {
+        mesh.add_vertex(to_vector3(p));
+    }
+    if let Some(normals) = reader.read_normals() {
+        mesh.normals = normals.map(to_vector3).collect();
+    }
+    if let Some(uvs) = reader.read_tex_coords(0) {
+        mesh.textures = uvs.into_f32().map(to_uv).collect();
+    }
+    let mut set = 1;
+    while let Some(uvs) = reader.read_tex_coords(set) {
+        mesh.add_uv_set(uvs.into_f32().map(to_uv).collect());
+        set += 1;
+    }
+    if let Some(colors) = reader.read_colors(0) {
+        mesh.colors = colors.into_rgb_f32().map(to_vector3).collect();
+    }
+    if let Some(tangents) = reader.read_tangents() {
+        mesh.tangents = tangents.map(|t| Vector3::new(t[0] as f64, t[1] as f64, t[2] as f64)).collect();
+    }
+
+    let count = mesh.vertices.len();
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..count as u32).collect(),
+    };
+    if let Some(&i) = indices.iter().find(|&&i| i as usize >= count) {
+        return Err(format!("gltf primitive refers to missing vertex {}", i));
+    }
+    let has_textures = !mesh.textures.is_empty();
+    let has_normals = !mesh.normals.is_empty();
+    // every attribute is indexed like the positions
+    let point = |i: u32| {
+        let index = i as i32 + 1;
+        PointStruct::new(index, if has_textures { index } else { 0 }, if has_normals { index } else { 0 })
+    };
+    for triangle in indices.chunks_exact(3) {
+        mesh.add_face(FaceStruct {
+            points: triangle.iter().map(|&i| point(i)).collect(),
+        });
+    }
+    mesh.remove_degenerate_faces();
+
+    Ok(Some(mesh))
+}
+
+/// Textures read the uv set their `texCoord` names
+fn read_material(material: &gltf::Material, images: &[Rc<RgbImage>]) -> PbrMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let base_color = Vector3::new(r as f64, g as f64, b as f64);
+    let metallic = pbr.metallic_factor() as f64;
+    let roughness = pbr.roughness_factor() as f64;
+
+    let mut result = PbrMaterial::new(base_color, metallic, roughness);
+    if let Some(info) = pbr.base_color_texture() {
+        let mut texture = ImageTexture::new(images[info.texture().source().index()].clone());
+        texture.set_uv_set(info.tex_coord() as usize);
+        result.set_base_color(MaterialValue3::from_texture(Multiply::new(texture, base_color)));
+    }
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        let image = &images[info.texture().source().index()];
+        // roughness is stored in green, metalness in blue
+        let mut roughness_texture = ImageTexture::new(image.clone());
+        roughness_texture.set_channel(1).set_uv_set(info.tex_coord() as usize);
+        let mut metallic_texture = ImageTexture::new(image.clone());
+        metallic_texture.set_channel(2).set_uv_set(info.tex_coord() as usize);
+        result.set_roughness(MaterialValue1::from_texture(Multiply::new(roughness_texture, roughness)));
+        result.set_metallic(MaterialValue1::from_texture(Multiply::new(metallic_texture, metallic)));
+    }
+
+    result
+}
+
+impl GltfImporter {
+    pub fn new() -> GltfImporter {
+        GltfImporter {
+            light_scale: 1.0,
+            aspect: 1.0,
+        }
+    }
+
+    pub fn set_light_scale(&mut self, scale: f64) -> &mut Self {
+        self.light_scale = scale;
+
+        self
+    }
+
+    pub fn set_aspect(&mut self, aspect: f64) -> &mut Self {
+        self.aspect = aspect;
+
+        self
+    }
+
+    /// Loads the default scene of the file, or its first scene
+    pub fn load(&self, filename: &str) -> Result<GltfScene, String> {
+        let (document, buffers, images) = gltf::import(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let images: Vec<Rc<RgbImage>> = images.iter().map(|data| Rc::new(to_rgb_image(data))).collect();
+
+        // meshes are shared by every node instancing them
+        let mut meshes: Vec<Vec<Option<Rc<Mesh>>>> = Vec::new();
+        for mesh in document.meshes() {
+            let mut primitives = Vec::new();
+            for primitive in mesh.primitives() {
+                primitives.push(read_primitive(&primitive, &buffers)?.map(Rc::new));
+            }
+            meshes.push(primitives);
+        }
+
+        let gltf_scene = document.default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| format!("{}: no scene", filename))?;
+        let mut result = GltfScene {
+            scene: Scene::new(),
+            cameras: Vec::new(),
+        };
+        let mut root = SceneNode::new();
+        for node in gltf_scene.nodes() {
+            if let Some(child) = self.visit(&node, Matrix4::identity(), &meshes, &images, &mut result)? {
+                root.add_child(child);
+            }
+        }
+        result.scene.add_node(root);
+
+        Ok(result)
+    }
+
+    /// Builds the scene graph node of `node` and its children. Cameras and
+    /// lights are placed directly with the world matrix of their node.
+    /// A node scaled to nothing hides its whole subtree and gives `None`
+    fn visit(
+        &self,
+        node: &gltf::Node,
+        parent: Matrix4<f64>,
+        meshes: &[Vec<Option<Rc<Mesh>>>],
+        images: &[Rc<RgbImage>],
+        result: &mut GltfScene,
+    ) -> Result<Option<SceneNode>, String> {
+        let local = to_matrix(node.transform().matrix());
+        let world = parent * local;
+
+        let mut scene_node = SceneNode::new();
+        let transform = match Transform::try_from_matrix(local) {
+            Some(transform) => transform,
+            None => return Ok(None),
+        };
+        scene_node.set_transform(transform);
+
+        if let Some(mesh) = node.mesh() {
+            for (primitive, data) in mesh.primitives().zip(meshes[mesh.index()].iter()) {
+                if let Some(data) = data {
+                    let mut object = Object::from_mesh(data.clone());
+                    object.set_material(Box::new(read_material(&primitive.material(), images)));
+                    scene_node.add_object(object);
+                }
+            }
+        }
+
+        let position = (world * Vector4::new(0.0, 0.0, 0.0, 1.0)).truncate();
+        let forward = (world * Vector4::new(0.0, 0.0, -1.0, 0.0)).truncate().normalize();
+        let up = (world * Vector4::new(0.0, 1.0, 0.0, 0.0)).truncate().normalize();
+
+        if let Some(camera) = node.camera() {
+            if let gltf::camera::Projection::Perspective(p) = camera.projection() {
+                let aspect = p.aspect_ratio().map_or(self.aspect, |a| a as f64);
+                let far = p.zfar().map_or(1000.0, |f| f as f64);
+                let mut result_camera = PerspectiveCamera::new(p.yfov() as f64, aspect, p.znear() as f64, far);
+                result_camera.eye = Point3::from_vec(position);
+                result_camera.center = Point3::from_vec(position + forward);
+                result_camera.up = up;
+                result.cameras.push(result_camera);
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let color = to_vector3(light.color()) * (light.intensity() as f64 * self.light_scale);
+            let falloff = Falloff::InverseSquare { range: light.range().map(|r| r as f64) };
+            match light.kind() {
+                Kind::Directional => result.scene.add_light(Box::new(DirectionalLight::new(forward, color))),
+                Kind::Point => {
+                    let mut point = PointLight::new(position, color, 0.0, 1.0, 1.0);
+                    point.set_falloff(falloff);
+                    result.scene.add_light(Box::new(point));
+                },
+                Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+                    let mut spot = SpotLight::new(position, forward, color, inner_cone_angle as f64, outer_cone_angle as f64);
+                    spot.set_distance_falloff(falloff);
+                    result.scene.add_light(Box::new(spot));
+                },
+            }
+        }
+
+        for child in node.children() {
+            if let Some(child) = self.visit(&child, world, meshes, images, result)? {
+                scene_node.add_child(child);
+            }
+        }
+
+        Ok(Some(scene_node))
+    }
+}
+
+#[cfg(test)]
+mod gltf_importer_test {
+    use super::GltfImporter;
+    use crate::ray::Ray;
+    use crate::texture::TexturePoint;
+    use cgmath::{InnerSpace, Vector2, Vector3};
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_load_nodes_camera_and_light() {
+        let dir = std::env::temp_dir().join(format!("hakaze_gltf_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // red on the left half, green on the right
+        RgbImage::from_fn(4, 1, |x, _| if x < 2 { Rgb([255, 0, 0]) } else { Rgb([0, 255, 0]) })
+            .save(dir.join("colors.png")).unwrap();
+        let mut buffer = Vec::new();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        std::fs::write(dir.join("triangle.bin"), &buffer).unwrap();
+        // the triangle is moved by both nodes, the spot light looks down the -z
+        // axis of its node. The zero scaled node hides another copy, the far
+        // away one has a texture read with the second uv set
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [
+                {"type": "spot", "color": [1, 0.5, 0.5], "intensity": 2, "spot": {"innerConeAngle": 0.1, "outerConeAngle": 0.5}},
+                {"type": "point", "color": [1, 1, 1], "intensity": 1, "range": 4}
+            ]}},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 2, 4, 5, 7]}],
+            "nodes": [
+                {"translation": [0, 0, -5], "children": [1]},
+                {"mesh": 0, "scale": [2, 2, 2]},
+                {"camera": 0, "translation": [0, 0, 3], "children": [3]},
+                {"extensions": {"KHR_lights_punctual": {"light": 0}}},
+                {"translation": [10, 0, 0], "extensions": {"KHR_lights_punctual": {"light": 1}}},
+                {"scale": [0, 0, 0], "children": [6]},
+                {"mesh": 0},
+                {"mesh": 1, "translation": [100, 0, 0]}
+            ],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "aspectRatio": 1.5, "znear": 0.1}}],
+            "meshes": [
+                {"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]},
+                {"primitives": [{"attributes": {"POSITION": 0}, "material": 1}]}
+            ],
+            "materials": [
+                {"pbrMetallicRoughness": {"baseColorFactor": [0.2, 0.4, 0.6, 1], "metallicFactor": 0, "roughnessFactor": 0.5}},
+                {"pbrMetallicRoughness": {"baseColorTexture": {"index": 0, "texCoord": 1}}}
+            ],
+            "textures": [{"source": 0}],
+            "images": [{"uri": "colors.png"}],
+            "buffers": [{"uri": "triangle.bin", "byteLength": 36}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0, 0, 0], "max": [1, 1, 0]}]
+        }"#;
+        let path = dir.join("scene.gltf");
+        std::fs::write(&path, json).unwrap();
+        let loaded = GltfImporter::new().set_light_scale(0.5).load(path.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let scene = &loaded.scene;
+        assert_eq!(scene.objects().len(), 2);
+        let hit = scene.intersect(&Ray::new(Vector3::new(0.5, 0.5, 0.0), Vector3::new(0.0, 0.0, -1.0)));
+        assert!(hit.is_intersect);
+        assert!((hit.dis - 5.0).abs() < 1e-9);
+        let color = scene.objects()[0].material.get_color(&TexturePoint::from_uv(0.0, 0.0));
+        assert!((color - Vector3::new(0.2, 0.4, 0.6)).magnitude() < 1e-6);
+        let p = TexturePoint::from_uv(0.375, 0.5).with_uv_sets(vec![Vector2::new(0.875, 0.5)]);
+        assert!((scene.objects()[1].material.get_color(&p) - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-6);
+
+        assert_eq!(loaded.cameras.len(), 1);
+        let camera = &loaded.cameras[0];
+        assert_eq!(camera.aspect, 1.5);
+        assert!((camera.eye.z - 3.0).abs() < 1e-9);
+
+        assert_eq!(scene.lights.len(), 2);
+        let light = &scene.lights[0];
+        // on the axis of the cone in front of the light, 3 away
+        assert!((light.get_color(Vector3::new(0.0, 0.0, 0.0)) - Vector3::new(1.0, 0.5, 0.5) / 9.0).magnitude() < 1e-6);
+        assert_eq!(light.get_color(Vector3::new(0.0, 0.0, 10.0)), Vector3::new(0.0, 0.0, 0.0));
+
+        // inverse square, windowed to nothing at the range
+        let point = &scene.lights[1];
+        let expected = (1.0 - 0.5f64.powi(4)) / 4.0 * 0.5;
+        assert!((point.get_color(Vector3::new(8.0, 0.0, 0.0)).x - expected).abs() < 1e-9);
+        assert_eq!(point.get_color(Vector3::new(5.0, 0.0, 0.0)), Vector3::new(0.0, 0.0, 0.0));
+    }
+}
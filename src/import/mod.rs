@@ -0,0 +1,3 @@
+pub mod gltf_importer;
+
+pub use gltf_importer::{GltfImporter, GltfScene};
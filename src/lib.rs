@@ -4,4 +4,15 @@ pub mod scene;
 pub mod camera;
 pub mod tracing;
 pub mod light;
-pub mod material;
\ No newline at end of file
+pub mod material;
+pub mod texture;
+pub mod spectrum;
+pub mod medium;
+pub mod accel;
+pub mod transform;
+pub mod animation;
+pub mod csg;
+pub mod sdf;
+pub mod import;
+pub mod curve;
+pub mod util;
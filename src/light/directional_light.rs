@@ -0,0 +1,51 @@
+use cgmath::{Vector3, InnerSpace};
+use crate::light::Light;
+use crate::ray::Ray;
+
+/// Light from infinitely far away arriving along `dir`, like the sun
+pub struct DirectionalLight {
+    // direction the light travels in
+    pub dir: Vector3<f64>,
+    pub color: Vector3<f64>,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(dir: Vector3<f64>, color: Vector3<f64>) -> DirectionalLight {
+        DirectionalLight {
+            dir: dir.normalize(),
+            color,
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 1.0,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn get_ray(&self, point: Vector3<f64>) -> Ray {
+        Ray::new(point, -self.dir)
+    }
+
+    fn get_color(&self, _point: Vector3<f64>) -> Vector3<f64> {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Vector3<f64>) {
+        self.color = color;
+    }
+
+    fn get_ambient_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.ambient
+    }
+
+    fn get_diffuse_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.diffuse
+    }
+
+    fn get_specular_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.specular
+    }
+}
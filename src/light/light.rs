@@ -1,16 +1,49 @@
 use cgmath::Vector3;
 use crate::ray::Ray;
+use crate::spectrum::rgb_to_spectrum;
 
 pub trait Light {
+    /// Shadow ray from `point` towards the light, `t_max` stops it at the light
     fn get_ray(&self, point: Vector3<f64>) -> Ray;
 
     fn get_color(&self, point: Vector3<f64>) -> Vector3<f64>;
 
-    fn is_blocked(&self, object_point: Vector3<f64>, intersect_point: Vector3<f64>) -> bool;
+    /// Emitted spectrum at a wavelength in nm, used by the spectral renderer
+    fn get_spectrum(&self, point: Vector3<f64>, lambda: f64) -> f64 {
+        rgb_to_spectrum(self.get_color(point), lambda)
+    }
+
+    /// Moves the light, used by animation. Lights without a position ignore it
+    fn set_position(&mut self, _pos: Vector3<f64>) {}
+
+    /// Changes the emitted color, used by animation
+    fn set_color(&mut self, _color: Vector3<f64>) {}
 
     fn get_ambient_strength(&self, point: Vector3<f64>) -> f64;
 
     fn get_diffuse_strength(&self, point: Vector3<f64>) -> f64;
 
     fn get_specular_strength(&self, point: Vector3<f64>) -> f64;
-}
\ No newline at end of file
+}
+/// How a light with a position dims with distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Falloff {
+    /// The same color at any distance
+    Constant,
+    /// Inverse square, cut smoothly to nothing at `range` when there is one,
+    /// as glTF punctual lights do
+    InverseSquare { range: Option<f64> },
+}
+
+impl Falloff {
+    /// Share of the color reaching `distance`
+    pub fn at(&self, distance: f64) -> f64 {
+        match *self {
+            Falloff::Constant => 1.0,
+            Falloff::InverseSquare { range } => {
+                let window = range.map_or(1.0, |r| (1.0 - (distance / r).powi(4)).clamp(0.0, 1.0));
+                window / (distance * distance).max(1e-12)
+            },
+        }
+    }
+}
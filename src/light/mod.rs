@@ -1,5 +1,10 @@
+#[allow(clippy::module_inception)]
 pub mod light;
 pub mod point_light;
+pub mod directional_light;
+pub mod spot_light;
 
-pub use light::Light;
-pub use point_light::PointLight;
\ No newline at end of file
+pub use light::{Light, Falloff};
+pub use point_light::PointLight;
+pub use directional_light::DirectionalLight;
+pub use spot_light::SpotLight;
@@ -1,6 +1,7 @@
-use cgmath::{Vector3, InnerSpace, MetricSpace};
-use crate::light::Light;
+use cgmath::{Vector3, InnerSpace};
+use crate::light::{Light, Falloff};
 use crate::ray::Ray;
+use crate::spectrum::{Spectrum, rgb_to_spectrum};
 
 pub struct PointLight {
     pub pos: Vector3<f64>,
@@ -8,6 +9,9 @@ pub struct PointLight {
     pub ambient: f64,
     pub diffuse: f64,
     pub specular: f64,
+    // overrides `color` in spectral mode
+    pub spectrum: Option<Spectrum>,
+    pub falloff: Falloff,
 }
 
 impl PointLight {
@@ -18,40 +22,60 @@ impl PointLight {
             ambient,
             diffuse,
             specular,
+            spectrum: None,
+            falloff: Falloff::Constant,
         }
     }
+
+    pub fn set_spectrum(&mut self, spectrum: Spectrum) -> &mut Self {
+        self.spectrum = Some(spectrum);
+
+        self
+    }
+
+    pub fn set_falloff(&mut self, falloff: Falloff) -> &mut Self {
+        self.falloff = falloff;
+
+        self
+    }
 }
 
 impl Light for PointLight {
     fn get_ray(&self, point: Vector3<f64>) -> Ray {
-        Ray {
-            pos: point.clone(),
-            dir: (self.pos - point).normalize(),
-        }
+        let to_light = self.pos - point;
+        Ray::new(point, to_light.normalize()).with_t_max(to_light.magnitude())
     }
 
     fn get_color(&self, point: Vector3<f64>) -> Vector3<f64> {
-        self.color.clone()
+        self.color * self.falloff.at((self.pos - point).magnitude())
         // let temp = point.normalize();
         // Vector3::new(temp.x.abs(), temp.y.abs(), temp.z.abs())
     }
 
-    fn is_blocked(&self, object_point: Vector3<f64>, intersect_point: Vector3<f64>) -> bool {
-        let d1 = object_point.distance(intersect_point);
-        let d2 = object_point.distance(self.pos);
+    fn get_spectrum(&self, point: Vector3<f64>, lambda: f64) -> f64 {
+        match self.spectrum {
+            Some(ref spectrum) => spectrum.evaluate(lambda) * self.falloff.at((self.pos - point).magnitude()),
+            None => rgb_to_spectrum(self.get_color(point), lambda),
+        }
+    }
+
+    fn set_position(&mut self, pos: Vector3<f64>) {
+        self.pos = pos;
+    }
 
-        d1 < d2
+    fn set_color(&mut self, color: Vector3<f64>) {
+        self.color = color;
     }
 
-    fn get_ambient_strength(&self, point: Vector3<f64>) -> f64 {
+    fn get_ambient_strength(&self, _point: Vector3<f64>) -> f64 {
         self.ambient
     }
 
-    fn get_diffuse_strength(&self, point: Vector3<f64>) -> f64 {
+    fn get_diffuse_strength(&self, _point: Vector3<f64>) -> f64 {
         self.diffuse
     }
 
-    fn get_specular_strength(&self, point: Vector3<f64>) -> f64 {
+    fn get_specular_strength(&self, _point: Vector3<f64>) -> f64 {
         self.specular
     }
 }
\ No newline at end of file
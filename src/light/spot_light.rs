@@ -0,0 +1,85 @@
+use cgmath::{Vector3, InnerSpace};
+use crate::light::{Light, Falloff};
+use crate::ray::Ray;
+
+/// Point light restricted to a cone around `dir`. Full intensity inside
+/// `inner_angle`, fading smoothly to nothing at `outer_angle` (radians)
+pub struct SpotLight {
+    pub pos: Vector3<f64>,
+    pub dir: Vector3<f64>,
+    pub color: Vector3<f64>,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub distance_falloff: Falloff,
+}
+
+impl SpotLight {
+    pub fn new(pos: Vector3<f64>, dir: Vector3<f64>, color: Vector3<f64>, inner_angle: f64, outer_angle: f64) -> SpotLight {
+        SpotLight {
+            pos,
+            dir: dir.normalize(),
+            color,
+            inner_angle,
+            outer_angle,
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 1.0,
+            distance_falloff: Falloff::Constant,
+        }
+    }
+
+    pub fn set_distance_falloff(&mut self, falloff: Falloff) -> &mut Self {
+        self.distance_falloff = falloff;
+
+        self
+    }
+
+    /// Share of the light reaching `point`, 1 inside the inner cone
+    pub fn falloff(&self, point: Vector3<f64>) -> f64 {
+        let cos = (point - self.pos).normalize().dot(self.dir);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos >= cos_inner {
+            return 1.0;
+        }
+        if cos <= cos_outer || cos_inner <= cos_outer {
+            return 0.0;
+        }
+        let t = (cos - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn get_ray(&self, point: Vector3<f64>) -> Ray {
+        let to_light = self.pos - point;
+        Ray::new(point, to_light.normalize()).with_t_max(to_light.magnitude())
+    }
+
+    fn get_color(&self, point: Vector3<f64>) -> Vector3<f64> {
+        self.color * (self.falloff(point) * self.distance_falloff.at((self.pos - point).magnitude()))
+    }
+
+    fn set_position(&mut self, pos: Vector3<f64>) {
+        self.pos = pos;
+    }
+
+    fn set_color(&mut self, color: Vector3<f64>) {
+        self.color = color;
+    }
+
+    fn get_ambient_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.ambient
+    }
+
+    fn get_diffuse_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.diffuse
+    }
+
+    fn get_specular_strength(&self, _point: Vector3<f64>) -> f64 {
+        self.specular
+    }
+}
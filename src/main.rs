@@ -1,15 +1,13 @@
 use std::f64::consts::PI;
 
 use ray_tracing::object::{Object};
-use ray_tracing::ray::Ray;
 use ray_tracing::camera::{PerspectiveCamera, Camera};
 use ray_tracing::scene::Scene;
-use ray_tracing::tracing::{BinaryTracing, Tracing, MyTracing};
+use ray_tracing::tracing::{Tracing, MyTracing};
 use ray_tracing::light::{PointLight, Light};
 use cgmath::Vector3;
-use ray_tracing::material::{ChessBoardMaterial, Material, ImageMaterial, MaterialValue1, MaterialValue3, NaiveMaterial};
+use ray_tracing::material::{Material, ImageMaterial, MaterialValue1, MaterialValue3, NaiveMaterial};
 use ray_tracing::material::image_material::MaterialValue;
-use image::GrayImage;
 
 
 fn set_up_objects(scene: &mut Scene) {
@@ -94,7 +92,7 @@ fn main() {
         1.0,
         1.0,
     );
-    let light3 = PointLight::new(
+    let _light3 = PointLight::new(
         Vector3::new(-10.0, 1.0, -50.0),
         Vector3::new(1.0, 1.0, 1.0),
         0.5,
@@ -103,17 +101,17 @@ fn main() {
     );
     scene.add_light(Box::new(light1) as Box<dyn Light>);
     scene.add_light(Box::new(light2) as Box<dyn Light>);
-    // scene.add_light(Box::new(light3) as Box<dyn Light>);
+    // scene.add_light(Box::new(_light3) as Box<dyn Light>);
 
 
-    let mut camera = PerspectiveCamera::new(PI / 2.0, 1.0, 0.1, 10.0);
+    let camera = PerspectiveCamera::new(PI / 2.0, 1.0, 0.1, 10.0);
     // camera.set_eye(0.0, 2.0, 0.0);
 
     // let tracing = BinaryTracing::new(&scene, (&camera) as (&dyn Camera));
-    let tracing = MyTracing::new(&scene, (&camera) as (&dyn Camera));
+    let tracing = MyTracing::new(&scene, &camera as &dyn Camera);
     let img = tracing.trace(render_width, render_height);
 
-    img.save("test.png");
+    img.save("test.png").unwrap();
     // let ray = Ray::new_nz();
 
     // let result = obj.intersect(&ray);
@@ -1,49 +1,86 @@
 use crate::material::Material;
+use crate::texture::{CheckerTexture, Texture, TexturePoint};
 use cgmath::Vector3;
 
 pub struct ChessBoardMaterial {
-
+    pub checker: CheckerTexture<Vector3<f64>>,
+    pub reflect_ratio: f64,
+    pub diffuse_strength: f64,
+    pub specular_strength: f64,
 }
 
 impl ChessBoardMaterial {
     pub fn new() -> ChessBoardMaterial {
-        ChessBoardMaterial {}
+        let mut checker = CheckerTexture::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        checker.set_scale(100.0);
+
+        ChessBoardMaterial::from_checker(checker)
+    }
+
+    pub fn from_checker(checker: CheckerTexture<Vector3<f64>>) -> ChessBoardMaterial {
+        ChessBoardMaterial {
+            checker,
+            reflect_ratio: 0.1,
+            diffuse_strength: 0.5,
+            specular_strength: 0.5,
+        }
+    }
+
+    pub fn set_colors(&mut self, a: Vector3<f64>, b: Vector3<f64>) -> &mut Self {
+        self.checker.a = a;
+        self.checker.b = b;
+
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.checker.set_scale(scale);
+
+        self
+    }
+
+    pub fn set_reflect_ratio(&mut self, reflect_ratio: f64) -> &mut Self {
+        self.reflect_ratio = reflect_ratio;
+
+        self
+    }
+}
+
+impl Default for ChessBoardMaterial {
+    fn default() -> Self {
+        ChessBoardMaterial::new()
     }
 }
 
 impl Material for ChessBoardMaterial {
-    // fn get_shininess(&self, u: f64, v: f64) -> f64 {
+    // fn get_shininess(&self, p: &TexturePoint) -> f64 {
     //     256.0
     // }
 
-    fn get_color(&self, u: f64, v: f64) -> Vector3<f64> {
-        let x = (u * 100.0) as u32;
-        let y = (v * 100.0) as u32;
-
-        if (x + y) % 2 == 0 {
-            Vector3::new(0.0, 0.0, 0.0)
-        } else {
-            Vector3::new(1.0, 1.0, 1.0)
-        }
+    fn get_color(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.checker.evaluate(p)
     }
 
-    fn get_reflect_ratio(&self, u: f64, v: f64) -> f64 {
-        0.1
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        self.reflect_ratio
     }
 
-    fn get_refract_ratio(&self, u: f64, v: f64) -> f64 {
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
         0.0
     }
 
-    fn get_refract_index(&self, u: f64, v: f64) -> f64 {
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
         0.0
     }
 
-    fn get_diffuse_strength(&self, u: f64, v: f64) -> f64 {
-        0.5
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
+        self.diffuse_strength
     }
 
-    fn get_specular_strength(&self, y: f64, v: f64) -> f64 {
-        0.5
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        self.specular_strength
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,148 @@
+use cgmath::{Vector3, InnerSpace, ElementWise};
+use rand::random;
+
+use super::material::{Material, reflect};
+use super::fresnel::fresnel_conductor;
+use super::microfacet::MicrofacetDistribution;
+use crate::texture::TexturePoint;
+
+/// Metals, described by a complex index of refraction `eta + i k` per RGB channel.
+/// Without a distribution the surface is a perfect mirror, otherwise a rough
+/// microfacet surface.
+pub struct ConductorMaterial {
+    pub eta: Vector3<f64>,
+    pub k: Vector3<f64>,
+    pub distribution: Option<MicrofacetDistribution>,
+}
+
+impl ConductorMaterial {
+    pub fn new(eta: Vector3<f64>, k: Vector3<f64>) -> ConductorMaterial {
+        ConductorMaterial {
+            eta,
+            k,
+            distribution: None,
+        }
+    }
+
+    pub fn gold() -> ConductorMaterial {
+        ConductorMaterial::new(Vector3::new(0.143, 0.374, 1.442), Vector3::new(3.983, 2.385, 1.603))
+    }
+
+    pub fn copper() -> ConductorMaterial {
+        ConductorMaterial::new(Vector3::new(0.200, 0.924, 1.102), Vector3::new(3.912, 2.452, 2.142))
+    }
+
+    pub fn aluminum() -> ConductorMaterial {
+        ConductorMaterial::new(Vector3::new(1.657, 0.880, 0.521), Vector3::new(9.224, 6.270, 4.837))
+    }
+
+    pub fn silver() -> ConductorMaterial {
+        ConductorMaterial::new(Vector3::new(0.155, 0.117, 0.138), Vector3::new(4.828, 3.122, 2.147))
+    }
+
+    pub fn set_distribution(&mut self, distribution: MicrofacetDistribution) -> &mut Self {
+        self.distribution = Some(distribution);
+
+        self
+    }
+
+    /// Perceptual roughness in [0, 1] with a GGX distribution, 0 gives a mirror
+    pub fn set_roughness(&mut self, roughness: f64) -> &mut Self {
+        self.distribution = if roughness > 0.0 {
+            Some(MicrofacetDistribution::Ggx(roughness * roughness))
+        } else {
+            None
+        };
+
+        self
+    }
+
+    pub fn fresnel(&self, cos_i: f64) -> Vector3<f64> {
+        Vector3::new(
+            fresnel_conductor(cos_i, self.eta.x, self.k.x),
+            fresnel_conductor(cos_i, self.eta.y, self.k.y),
+            fresnel_conductor(cos_i, self.eta.z, self.k.z),
+        )
+    }
+}
+
+impl Material for ConductorMaterial {
+    fn get_color(&self, _p: &TexturePoint) -> Vector3<f64> {
+        // reflectance at normal incidence
+        self.fresnel(1.0)
+    }
+
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        1.0
+    }
+
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
+        1.0
+    }
+
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_split(&self, _p: &TexturePoint, _cos_i: f64, _eta: f64) -> (Vector3<f64>, Vector3<f64>) {
+        // the Fresnel term depends on the sampled microfacet, see `sample_reflect`
+        (Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.0, 0.0, 0.0))
+    }
+
+    fn shade(
+        &self,
+        _p: &TexturePoint,
+        normal: Vector3<f64>,
+        view_dir: Vector3<f64>,
+        light_dir: Vector3<f64>,
+        light_color: Vector3<f64>,
+    ) -> Vector3<f64> {
+        // a mirror never reflects a point light towards the viewer
+        let distribution = match self.distribution {
+            Some(d) => d,
+            None => return Vector3::new(0.0, 0.0, 0.0),
+        };
+        let cos_o = view_dir.dot(normal);
+        let cos_l = light_dir.dot(normal);
+        if cos_o <= 0.0 || cos_l <= 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let half = (view_dir + light_dir).normalize();
+        let f = self.fresnel(light_dir.dot(half));
+        let brdf = f * (distribution.d(half, normal) * distribution.g(view_dir, light_dir, normal) / (4.0 * cos_o * cos_l));
+
+        brdf.mul_element_wise(light_color) * cos_l
+    }
+
+    fn sample_reflect(&self, _p: &TexturePoint, normal: Vector3<f64>, view_dir: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let cos_o = view_dir.dot(normal).max(1e-6);
+        match self.distribution {
+            None => {
+                (reflect(view_dir, normal).normalize(), self.fresnel(cos_o))
+            },
+            Some(distribution) => {
+                let half = distribution.sample_h(normal, random::<f64>(), random::<f64>());
+                let cos_oh = view_dir.dot(half);
+                let dir = reflect(view_dir, half).normalize();
+                let cos_l = dir.dot(normal);
+                if cos_oh <= 0.0 || cos_l <= 0.0 {
+                    return (dir, Vector3::new(0.0, 0.0, 0.0));
+                }
+
+                // brdf * cos / pdf with pdf = d * cos_h / (4 cos_oh)
+                let cos_h = half.dot(normal);
+                let weight = distribution.g(view_dir, dir, normal) * cos_oh / (cos_o * cos_h);
+                (dir, self.fresnel(cos_oh) * weight)
+            },
+        }
+    }
+}
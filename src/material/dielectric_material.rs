@@ -0,0 +1,128 @@
+use cgmath::Vector3;
+use super::material::Material;
+use super::fresnel::fresnel_dielectric;
+use crate::texture::TexturePoint;
+use crate::spectrum::Dispersion;
+
+/// Clear, smooth dielectrics such as glass and water. The reflected and
+/// refracted energy is split by the Fresnel equations, light travelling
+/// inside is attenuated following the Beer-Lambert law.
+pub struct DielectricMaterial {
+    // physical index of refraction of the medium, 1.5 for glass, 1.33 for water
+    pub ior: f64,
+    // tint applied to transmitted light at the surface
+    pub color: Vector3<f64>,
+    // Beer-Lambert absorption coefficient per unit length
+    pub absorption: Vector3<f64>,
+    pub specular_strength: f64,
+    // wavelength dependence of `ior` in spectral mode, `ior` is used when absent
+    pub dispersion: Option<Dispersion>,
+}
+
+impl DielectricMaterial {
+    pub fn new(ior: f64) -> DielectricMaterial {
+        DielectricMaterial {
+            ior,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            absorption: Vector3::new(0.0, 0.0, 0.0),
+            specular_strength: 0.5,
+            dispersion: None,
+        }
+    }
+
+    pub fn glass() -> DielectricMaterial {
+        DielectricMaterial::new(1.5)
+    }
+
+    pub fn water() -> DielectricMaterial {
+        let mut water = DielectricMaterial::new(1.33);
+        water.set_absorption(Vector3::new(0.45, 0.06, 0.02));
+
+        water
+    }
+
+    pub fn set_color(&mut self, color: Vector3<f64>) -> &mut Self {
+        self.color = color;
+
+        self
+    }
+
+    pub fn set_dispersion(&mut self, dispersion: Dispersion) -> &mut Self {
+        self.dispersion = Some(dispersion);
+
+        self
+    }
+
+    pub fn set_absorption(&mut self, absorption: Vector3<f64>) -> &mut Self {
+        self.absorption = absorption;
+
+        self
+    }
+}
+
+impl Material for DielectricMaterial {
+    fn get_color(&self, _p: &TexturePoint) -> Vector3<f64> {
+        self.color
+    }
+
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        fresnel_dielectric(1.0, 1.0 / self.ior)
+    }
+
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
+        1.0 - fresnel_dielectric(1.0, 1.0 / self.ior)
+    }
+
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
+        // the tracer works with the ratio outside / inside
+        1.0 / self.ior
+    }
+
+    fn get_refract_index_at(&self, _p: &TexturePoint, lambda: f64) -> f64 {
+        match self.dispersion {
+            Some(d) => 1.0 / d.ior(lambda),
+            None => 1.0 / self.ior,
+        }
+    }
+
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        self.specular_strength
+    }
+
+    fn get_split(&self, _p: &TexturePoint, cos_i: f64, eta: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let r = fresnel_dielectric(cos_i, eta);
+
+        (Vector3::new(r, r, r), self.color * (1.0 - r))
+    }
+
+    fn get_absorption(&self, _p: &TexturePoint) -> Vector3<f64> {
+        self.absorption
+    }
+}
+
+#[cfg(test)]
+mod dielectric_material_test {
+    use super::DielectricMaterial;
+    use crate::material::Material;
+    use crate::texture::TexturePoint;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_split_conserves_energy() {
+        let glass = DielectricMaterial::glass();
+        let p = TexturePoint::from_uv(0.0, 0.0);
+        for cos_i in [1.0, 0.5, 0.1, 0.0] {
+            let (reflect, refract) = glass.get_split(&p, cos_i, 1.0 / 1.5);
+            assert!((reflect.x + refract.x - 1.0).abs() < 1e-12);
+        }
+
+        // beyond the critical angle from inside nothing gets out
+        let (reflect, refract) = glass.get_split(&p, 0.3, 1.5);
+        assert_eq!(reflect, Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(refract, Vector3::new(0.0, 0.0, 0.0));
+    }
+}
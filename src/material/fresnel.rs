@@ -0,0 +1,71 @@
+use cgmath::Vector3;
+
+/// Unpolarized Fresnel reflectance of a dielectric interface.
+/// `cos_i` is the cosine of the incident angle, `eta` the ratio of the indices
+/// on the incident side over the transmitted side. Returns 1 on total internal reflection.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t2 = eta * eta * (1.0 - cos_i * cos_i);
+    if sin_t2 >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t2).sqrt();
+
+    let r_s = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_p = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_s * r_s + r_p * r_p) / 2.0
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index `eta + i k`
+/// relative to the outside medium, for a single channel
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+
+    (r_s + r_p) / 2.0
+}
+
+/// Schlick's approximation with reflectance `f0` at normal incidence, per channel
+pub fn fresnel_schlick(cos_i: f64, f0: Vector3<f64>) -> Vector3<f64> {
+    let m = (1.0 - cos_i.clamp(0.0, 1.0)).powi(5);
+    f0 + (Vector3::new(1.0, 1.0, 1.0) - f0) * m
+}
+
+#[cfg(test)]
+mod fresnel_test {
+    use super::{fresnel_dielectric, fresnel_conductor};
+
+    #[test]
+    fn test_fresnel_dielectric() {
+        // glass head-on reflects about 4%
+        let r = fresnel_dielectric(1.0, 1.0 / 1.5);
+        assert!((r - 0.04).abs() < 1e-9);
+        // grazing angles reflect everything
+        assert!(fresnel_dielectric(1e-6, 1.0 / 1.5) > 0.99);
+        // total internal reflection from inside
+        assert_eq!(fresnel_dielectric(0.3, 1.5), 1.0);
+    }
+
+    #[test]
+    fn test_fresnel_conductor() {
+        // with k = 0 a conductor behaves like a dielectric seen from outside
+        let r = fresnel_conductor(0.7, 1.5, 0.0);
+        assert!((r - fresnel_dielectric(0.7, 1.0 / 1.5)).abs() < 1e-9);
+        // silver is highly reflective head-on
+        assert!(fresnel_conductor(1.0, 0.155, 4.828) > 0.9);
+    }
+}
@@ -0,0 +1,140 @@
+use cgmath::{Vector3, InnerSpace, ElementWise};
+
+use super::material::Material;
+use super::image_material::MaterialValue3;
+use crate::texture::TexturePoint;
+
+/// Kajiya-Kay hair. A strand is a thin cylinder, light is scattered around
+/// its tangent instead of a normal: the diffuse part follows the sine between
+/// tangent and light, the highlight is the cone of mirror directions around
+/// the tangent. Points without a tangent are shaded as lambertian
+pub struct HairMaterial {
+    pub color: MaterialValue3,
+    pub specular_color: Vector3<f64>,
+    pub diffuse_strength: f64,
+    pub specular_strength: f64,
+    // exponent narrowing the highlight cone
+    pub shininess: f64,
+    // tilt of the highlight cone in radians from the angled cuticle scales,
+    // usually a few degrees
+    pub shift: f64,
+}
+
+impl HairMaterial {
+    pub fn new(color: Vector3<f64>) -> HairMaterial {
+        HairMaterial {
+            color: MaterialValue3::from_constant(color),
+            specular_color: Vector3::new(1.0, 1.0, 1.0),
+            diffuse_strength: 0.6,
+            specular_strength: 0.3,
+            shininess: 64.0,
+            shift: 0.0,
+        }
+    }
+
+    pub fn set_color(&mut self, color: MaterialValue3) -> &mut Self {
+        self.color = color;
+
+        self
+    }
+
+    pub fn set_specular_color(&mut self, color: Vector3<f64>) -> &mut Self {
+        self.specular_color = color;
+
+        self
+    }
+
+    pub fn set_shininess(&mut self, shininess: f64) -> &mut Self {
+        self.shininess = shininess;
+
+        self
+    }
+
+    pub fn set_shift(&mut self, shift: f64) -> &mut Self {
+        self.shift = shift;
+
+        self
+    }
+}
+
+impl Material for HairMaterial {
+    fn get_color(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.color.get_value(p)
+    }
+
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
+        1.55
+    }
+
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
+        self.diffuse_strength
+    }
+
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        self.specular_strength
+    }
+
+    fn shade(
+        &self,
+        p: &TexturePoint,
+        normal: Vector3<f64>,
+        view_dir: Vector3<f64>,
+        light_dir: Vector3<f64>,
+        light_color: Vector3<f64>,
+    ) -> Vector3<f64> {
+        let color = self.get_color(p);
+        if p.tangent.magnitude2() == 0.0 {
+            return (light_color * light_dir.dot(normal).max(0.0) * self.diffuse_strength).mul_element_wise(color);
+        }
+        let tangent = p.tangent.normalize();
+        let light_dir = light_dir.normalize();
+        let view_dir = view_dir.normalize();
+        let sin = |cos: f64| (1.0 - cos * cos).max(0.0).sqrt();
+
+        let cos_light = tangent.dot(light_dir);
+        let diffuse = light_color * sin(cos_light) * self.diffuse_strength;
+
+        // the shifted tangent leans towards the normal, moving the cone
+        let shifted = (tangent * self.shift.cos() + normal * self.shift.sin()).normalize();
+        let (cos_l, cos_v) = (shifted.dot(light_dir), shifted.dot(view_dir));
+        let cone = (sin(cos_l) * sin(cos_v) - cos_l * cos_v).max(0.0);
+        let specular = light_color.mul_element_wise(self.specular_color) * cone.powf(self.shininess) * self.specular_strength;
+
+        diffuse.mul_element_wise(color) + specular
+    }
+}
+
+#[cfg(test)]
+mod hair_material_test {
+    use super::HairMaterial;
+    use crate::material::Material;
+    use crate::texture::TexturePoint;
+    use cgmath::{InnerSpace, Vector3};
+
+    #[test]
+    fn test_kajiya_kay() {
+        let material = HairMaterial::new(Vector3::new(0.5, 0.3, 0.1));
+        let p = TexturePoint::from_uv(0.5, 0.5).with_tangent(Vector3::new(1.0, 0.0, 0.0));
+        let normal = Vector3::new(0.0, 0.0, 1.0);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let light = Vector3::new(1.0, 0.0, 1.0).normalize();
+
+        // brightest on the mirror cone around the strand, whatever the normal says
+        let mirror = material.shade(&p, normal, Vector3::new(-1.0, 1.0, 0.0).normalize(), light, white);
+        let off = material.shade(&p, normal, Vector3::new(1.0, 1.0, 0.0).normalize(), light, white);
+        assert!((mirror.z - (0.1 * 0.6 / 2f64.sqrt() + 0.3)).abs() < 1e-9);
+        assert!(mirror.z > off.z + 0.29);
+
+        // light along the strand gives nothing diffuse
+        let along = material.shade(&p, normal, normal, Vector3::new(1.0, 0.0, 0.0), white);
+        assert!(along.magnitude() < 1e-9);
+    }
+}
@@ -2,48 +2,82 @@ use image::{GrayImage, RgbImage};
 use image::io::Reader as ImageReader;
 use cgmath::Vector3;
 use crate::material::Material;
+use crate::texture::{Texture, TexturePoint, parse_texture};
+use crate::texture::graph::GraphValue;
+use serde_json::Value;
+use std::fs;
 
 pub enum MaterialValue<T, U> {
     Image(U),
     Constant(T),
+    Texture(Box<dyn Texture<T>>),
 }
 
 pub type MaterialValue1 = MaterialValue<f64, GrayImage>;
 pub type MaterialValue3 = MaterialValue<Vector3<f64>, RgbImage>;
 
+impl<T, U> MaterialValue<T, U> {
+    pub fn from_texture<X: Texture<T> + 'static>(texture: X) -> Self {
+        MaterialValue::Texture(Box::new(texture))
+    }
+}
+
 impl MaterialValue<f64, GrayImage> {
-    pub fn get_value(&self, u: f64, v: f64) -> f64 {
+    pub fn get_value(&self, p: &TexturePoint) -> f64 {
         match *self {
             MaterialValue::Image(ref img) => {
-                let width = img.width() as f64;
-                let height = img.height() as f64;
-                img.get_pixel((u * width) as u32, (v * height) as u32).0[0] as f64 / 255.0
+                let width = img.width() as i64;
+                let height = img.height() as i64;
+                let x = ((p.uv.x * width as f64).floor() as i64).rem_euclid(width);
+                let y = ((p.uv.y * height as f64).floor() as i64).rem_euclid(height);
+                img.get_pixel(x as u32, y as u32).0[0] as f64 / 255.0
             },
             MaterialValue::Constant(v) => v,
+            MaterialValue::Texture(ref t) => t.evaluate(p),
         }
     }
 
     pub fn from_file(name: &str) -> Self {
-        let img = ImageReader::open(name).unwrap().decode().unwrap();
-        let img = img.to_luma8();
+        Self::try_from_file(name).unwrap()
+    }
 
-        MaterialValue::Image(img)
+    pub fn try_from_file(name: &str) -> Result<Self, String> {
+        let img = ImageReader::open(name)
+            .map_err(|e| format!("cannot open image {}: {}", name, e))?
+            .decode()
+            .map_err(|e| format!("cannot decode image {}: {}", name, e))?;
+
+        Ok(MaterialValue::Image(img.to_luma8()))
     }
 
     pub fn from_constant(value: f64) -> Self {
         MaterialValue::Constant(value)
     }
+
+    /// Builds a value from a shading graph description, see `texture::graph`
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        match f64::parse_constant(value) {
+            Some(c) => Ok(MaterialValue::Constant(c)),
+            None => Ok(MaterialValue::Texture(parse_texture(value)?)),
+        }
+    }
+}
+
+impl Texture<f64> for MaterialValue<f64, GrayImage> {
+    fn evaluate(&self, p: &TexturePoint) -> f64 {
+        self.get_value(p)
+    }
 }
 
 impl MaterialValue<Vector3<f64>, RgbImage> {
-    pub fn get_value(&self, u: f64, v: f64) -> Vector3<f64> {
+    pub fn get_value(&self, p: &TexturePoint) -> Vector3<f64> {
         match *self {
             MaterialValue::Image(ref img) => {
                 let width = img.width();
                 let height = img.height();
 
-                let x = (u * 100.0 * width as f64) as u32 % width;
-                let y = (v * 100.0 * height as f64) as u32 % height;
+                let x = (p.uv.x * 100.0 * width as f64) as u32 % width;
+                let y = (p.uv.y * 100.0 * height as f64) as u32 % height;
 
                 let pixel = img.get_pixel(x, y);
                 let r = pixel.0[0] as f64 / 255.0;
@@ -53,19 +87,40 @@ impl MaterialValue<Vector3<f64>, RgbImage> {
                 Vector3::new(r, g, b)
             },
             MaterialValue::Constant(ref v) => *v,
+            MaterialValue::Texture(ref t) => t.evaluate(p),
         }
     }
 
     pub fn from_file(name: &str) -> Self {
-        let img = ImageReader::open(name).unwrap().decode().unwrap();
-        let img = img.to_rgb8();
+        Self::try_from_file(name).unwrap()
+    }
+
+    pub fn try_from_file(name: &str) -> Result<Self, String> {
+        let img = ImageReader::open(name)
+            .map_err(|e| format!("cannot open image {}: {}", name, e))?
+            .decode()
+            .map_err(|e| format!("cannot decode image {}: {}", name, e))?;
 
-        MaterialValue::Image(img)
+        Ok(MaterialValue::Image(img.to_rgb8()))
     }
 
     pub fn from_constant(value: Vector3<f64>) -> Self {
         MaterialValue::Constant(value)
     }
+
+    /// Builds a value from a shading graph description, see `texture::graph`
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        match Vector3::parse_constant(value) {
+            Some(c) => Ok(MaterialValue::Constant(c)),
+            None => Ok(MaterialValue::Texture(parse_texture(value)?)),
+        }
+    }
+}
+
+impl Texture<Vector3<f64>> for MaterialValue<Vector3<f64>, RgbImage> {
+    fn evaluate(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.get_value(p)
+    }
 }
 
 pub struct ImageMaterial {
@@ -96,34 +151,64 @@ impl ImageMaterial {
             // scale,
         }
     }
+
+    /// Reads a material from a JSON object whose properties are shading graphs,
+    /// missing properties fall back to constants
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let property = |name: &str, default: f64| -> Result<MaterialValue1, String> {
+            match value.get(name) {
+                Some(v) => MaterialValue1::from_json(v),
+                None => Ok(MaterialValue1::from_constant(default)),
+            }
+        };
+        let color = match value.get("color") {
+            Some(v) => MaterialValue3::from_json(v)?,
+            None => MaterialValue3::from_constant(Vector3::new(0.5, 0.5, 0.5)),
+        };
+
+        Ok(ImageMaterial::new(
+            property("diffuse_strength", 0.9)?,
+            color,
+            property("reflect_ratio", 0.0)?,
+            property("refract_ratio", 0.0)?,
+            property("refract_index", 1.0)?,
+        ))
+    }
+
+    pub fn from_json_file(name: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(name).map_err(|e| format!("cannot read {}: {}", name, e))?;
+        let value: Value = serde_json::from_str(&contents).map_err(|e| format!("cannot parse {}: {}", name, e))?;
+
+        ImageMaterial::from_json(&value)
+    }
 }
 
 impl Material for ImageMaterial {
-    // fn get_shininess(&self, u: f64, v: f64) -> f64 {
-    //     self.shininess.get_value(u, v)
+    // fn get_shininess(&self, p: &TexturePoint) -> f64 {
+    //     self.shininess.get_value(p)
     // }
 
-    fn get_color(&self, u: f64, v: f64) -> Vector3<f64> {
-        self.color.get_value(u, v)
+    fn get_color(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.color.get_value(p)
     }
 
-    fn get_reflect_ratio(&self, u: f64, v: f64) -> f64 {
-        self.reflect_ratio.get_value(u, v)
+    fn get_reflect_ratio(&self, p: &TexturePoint) -> f64 {
+        self.reflect_ratio.get_value(p)
     }
 
-    fn get_refract_ratio(&self, u: f64, v: f64) -> f64 {
-        self.refract_ratio.get_value(u, v)
+    fn get_refract_ratio(&self, p: &TexturePoint) -> f64 {
+        self.refract_ratio.get_value(p)
     }
 
-    fn get_refract_index(&self, u: f64, v: f64) -> f64 {
-        self.refract_index.get_value(u, v)
+    fn get_refract_index(&self, p: &TexturePoint) -> f64 {
+        self.refract_index.get_value(p)
     }
 
-    fn get_diffuse_strength(&self, u: f64, v: f64) -> f64 {
-        self.diffuse_strength.get_value(u, v)
+    fn get_diffuse_strength(&self, p: &TexturePoint) -> f64 {
+        self.diffuse_strength.get_value(p)
     }
 
-    fn get_specular_strength(&self, u: f64, v: f64) -> f64 {
-        self.reflect_ratio.get_value(u, v)
+    fn get_specular_strength(&self, p: &TexturePoint) -> f64 {
+        self.reflect_ratio.get_value(p)
     }
 }
\ No newline at end of file
@@ -1,23 +1,85 @@
-use cgmath::Vector3;
+use cgmath::{Vector3, InnerSpace, ElementWise};
+use crate::texture::TexturePoint;
+use crate::medium::HomogeneousMedium;
+
+/// Mirrors `a` around `axis`, both pointing away from the surface
+pub fn reflect(a: Vector3<f64>, axis: Vector3<f64>) -> Vector3<f64> {
+    let temp = a.dot(axis) * axis;
+    2.0 * temp - a
+}
 
 pub trait Material {
-    // fn get_shininess(&self, u: f64, v: f64) -> f64;
+    // fn get_shininess(&self, p: &TexturePoint) -> f64;
+
+    // fn get_diffuse(&self, p: &TexturePoint) -> f64;
+
+    // fn get_ambient(&self, p: &TexturePoint) -> f64;
+
+    fn get_color(&self, p: &TexturePoint) -> Vector3<f64>;
+
+    fn get_reflect_ratio(&self, p: &TexturePoint) -> f64;
+
+    fn get_refract_ratio(&self, p: &TexturePoint) -> f64;
+
+    fn get_refract_index(&self, p: &TexturePoint) -> f64;
+
+    /// Refract index at a wavelength in nm, used by the spectral renderer
+    fn get_refract_index_at(&self, p: &TexturePoint, _lambda: f64) -> f64 {
+        self.get_refract_index(p)
+    }
+
+    fn get_diffuse_strength(&self, p: &TexturePoint) -> f64;
+
+    fn get_specular_strength(&self, p: &TexturePoint) -> f64;
 
-    // fn get_diffuse(&self, u: f64, v: f64) -> f64;
+    /// Weights of the reflected and the refracted ray, per channel.
+    /// `cos_i` is the cosine between the incoming ray and the normal on its side,
+    /// `eta` is the refract index actually used for this crossing
+    fn get_split(&self, p: &TexturePoint, _cos_i: f64, _eta: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let reflect = self.get_reflect_ratio(p);
+        let refract = self.get_refract_ratio(p);
 
-    // fn get_ambient(&self, u: f64, v: f64) -> f64;
+        (Vector3::new(reflect, reflect, reflect), Vector3::new(refract, refract, refract))
+    }
 
-    fn get_color(&self, u: f64, v: f64) -> Vector3<f64>;
+    /// Light reflected towards `view_dir` from a light arriving along `light_dir`,
+    /// all directions point away from the surface
+    fn shade(
+        &self,
+        p: &TexturePoint,
+        normal: Vector3<f64>,
+        view_dir: Vector3<f64>,
+        light_dir: Vector3<f64>,
+        light_color: Vector3<f64>,
+    ) -> Vector3<f64> {
+        // diffuse
+        let diffuse = light_color * light_dir.dot(normal).max(0.0) * self.get_diffuse_strength(p);
 
-    fn get_reflect_ratio(&self, u: f64, v: f64) -> f64;
+        // specular
+        let half = (view_dir + light_dir).normalize();
+        // let shininess = self.get_shininess(p);
+        let shininess = 128.0;
+        let specular = light_color * half.dot(normal).max(0.0).powf(shininess) * self.get_specular_strength(p);
 
-    fn get_refract_ratio(&self, u: f64, v: f64) -> f64;
+        (diffuse + specular).mul_element_wise(self.get_color(p))
+    }
 
-    fn get_refract_index(&self, u: f64, v: f64) -> f64;
+    /// Direction of the reflected ray and a weight applied on top of the reflect
+    /// weight of `get_split`. `normal` is on the same side as `view_dir`
+    fn sample_reflect(&self, _p: &TexturePoint, normal: Vector3<f64>, view_dir: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        (reflect(view_dir, normal).normalize(), Vector3::new(1.0, 1.0, 1.0))
+    }
 
-    fn get_diffuse_strength(&self, u: f64, v: f64) -> f64;
+    /// Beer-Lambert absorption coefficient of the medium enclosed by the surface
+    fn get_absorption(&self, _p: &TexturePoint) -> Vector3<f64> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
 
-    fn get_specular_strength(&self, y: f64, v: f64) -> f64;
+    /// Medium below the surface for subsurface scattering. When present the
+    /// refracted light random walks inside the object instead of being traced
+    fn get_subsurface(&self, _p: &TexturePoint) -> Option<HomogeneousMedium> {
+        None
+    }
 }
 
 // pub trait MaterialValue<T> {
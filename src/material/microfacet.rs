@@ -0,0 +1,107 @@
+use std::f64::consts::PI;
+
+use cgmath::{Vector3, InnerSpace};
+
+use crate::ray::coordinate_system;
+
+/// Distribution of microfacet normals of a rough surface, `alpha` is the
+/// width of the distribution (roughness squared in the usual parameterization)
+#[derive(Clone, Copy, Debug)]
+pub enum MicrofacetDistribution {
+    Beckmann(f64),
+    Ggx(f64),
+}
+
+impl MicrofacetDistribution {
+    pub fn alpha(&self) -> f64 {
+        match *self {
+            MicrofacetDistribution::Beckmann(a) => a,
+            MicrofacetDistribution::Ggx(a) => a,
+        }
+    }
+
+    /// Density of microfacets with normal `h` around the macro normal `n`
+    pub fn d(&self, h: Vector3<f64>, n: Vector3<f64>) -> f64 {
+        let cos = h.dot(n);
+        if cos <= 0.0 {
+            return 0.0;
+        }
+        let cos2 = cos * cos;
+        let tan2 = (1.0 - cos2) / cos2;
+        let a = self.alpha();
+        let a2 = a * a;
+
+        match *self {
+            MicrofacetDistribution::Beckmann(_) => (-tan2 / a2).exp() / (PI * a2 * cos2 * cos2),
+            MicrofacetDistribution::Ggx(_) => {
+                let t = a2 + tan2;
+                a2 / (PI * cos2 * cos2 * t * t)
+            },
+        }
+    }
+
+    fn lambda(&self, w: Vector3<f64>, n: Vector3<f64>) -> f64 {
+        let cos = w.dot(n);
+        let cos2 = cos * cos;
+        if cos2 >= 1.0 {
+            return 0.0;
+        }
+        let tan = ((1.0 - cos2) / cos2).sqrt();
+        let a = self.alpha();
+
+        match *self {
+            MicrofacetDistribution::Beckmann(_) => {
+                let x = 1.0 / (a * tan);
+                if x >= 1.6 {
+                    return 0.0;
+                }
+                (1.0 - 1.259 * x + 0.396 * x * x) / (3.535 * x + 2.181 * x * x)
+            },
+            MicrofacetDistribution::Ggx(_) => ((1.0 + a * a * tan * tan).sqrt() - 1.0) / 2.0,
+        }
+    }
+
+    /// Smith masking-shadowing for the pair of directions
+    pub fn g(&self, wo: Vector3<f64>, wi: Vector3<f64>, n: Vector3<f64>) -> f64 {
+        1.0 / (1.0 + self.lambda(wo, n) + self.lambda(wi, n))
+    }
+
+    /// Samples a microfacet normal proportionally to `d(h) * cos(h, n)`
+    pub fn sample_h(&self, n: Vector3<f64>, u1: f64, u2: f64) -> Vector3<f64> {
+        let a = self.alpha();
+        let tan2 = match *self {
+            MicrofacetDistribution::Beckmann(_) => -a * a * (1.0 - u1).ln(),
+            MicrofacetDistribution::Ggx(_) => a * a * u1 / (1.0 - u1),
+        };
+        let cos = 1.0 / (1.0 + tan2).sqrt();
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        let (t, b) = coordinate_system(n);
+        (t * (sin * phi.cos()) + b * (sin * phi.sin()) + n * cos).normalize()
+    }
+}
+
+#[cfg(test)]
+mod microfacet_test {
+    use super::MicrofacetDistribution;
+    use cgmath::{Vector3, InnerSpace};
+
+    #[test]
+    fn test_sample_h_in_hemisphere() {
+        let n = Vector3::new(0.3, 0.9, -0.2).normalize();
+        let ggx = MicrofacetDistribution::Ggx(0.3);
+        for i in 1..50 {
+            let h = ggx.sample_h(n, i as f64 / 50.0, (i * 7 % 50) as f64 / 50.0);
+            assert!(h.dot(n) > 0.0);
+            assert!((h.magnitude() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_g_head_on() {
+        let n = Vector3::new(0.0, 0.0, 1.0);
+        let beckmann = MicrofacetDistribution::Beckmann(0.2);
+        assert!((beckmann.g(n, n, n) - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,7 +1,15 @@
+#[allow(clippy::module_inception)]
 pub mod material;
 pub mod naive_material;
 pub mod chess_board_material;
 pub mod image_material;
+pub mod fresnel;
+pub mod dielectric_material;
+pub mod microfacet;
+pub mod conductor_material;
+pub mod subsurface_material;
+pub mod pbr_material;
+pub mod hair_material;
 
 pub use material::Material;
 // pub use material::MaterialValue;
@@ -9,5 +17,11 @@ pub use material::Material;
 pub use naive_material::NaiveMaterial;
 pub use chess_board_material::ChessBoardMaterial;
 pub use image_material::ImageMaterial;
+pub use dielectric_material::DielectricMaterial;
+pub use conductor_material::ConductorMaterial;
+pub use subsurface_material::SubsurfaceMaterial;
+pub use pbr_material::PbrMaterial;
+pub use hair_material::HairMaterial;
+pub use microfacet::MicrofacetDistribution;
 pub use image_material::MaterialValue1;
 pub use image_material::MaterialValue3;
\ No newline at end of file
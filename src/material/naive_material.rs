@@ -1,5 +1,6 @@
 use cgmath::Vector3;
 use super::material::Material;
+use crate::texture::TexturePoint;
 
 pub struct NaiveMaterial {
     // pub shininess: f64,
@@ -45,35 +46,35 @@ impl Default for NaiveMaterial {
 }
 
 impl Material for NaiveMaterial {
-    // fn get_shininess(&self, u: f64, v: f64) -> f64 {
+    // fn get_shininess(&self, _p: &TexturePoint) -> f64 {
     //     self.shininess
     // }
 
-    // fn get_ambient(&self, u: f64, v: f64) -> f64 {
+    // fn get_ambient(&self, _p: &TexturePoint) -> f64 {
     //     self.ambient
     // }
 
-    fn get_color(&self, u: f64, v: f64) -> Vector3<f64> {
+    fn get_color(&self, _p: &TexturePoint) -> Vector3<f64> {
         self.color
     }
 
-    fn get_reflect_ratio(&self, u: f64, v: f64) -> f64 {
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
         self.reflect_ratio
     }
 
-    fn get_refract_ratio(&self, u: f64, v: f64) -> f64 {
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
         self.refract_ratio
     }
 
-    fn get_refract_index(&self, u: f64, v: f64) -> f64 {
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
         self.refract_index
     }
 
-    fn get_diffuse_strength(&self, u: f64, v: f64) -> f64 {
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
         self.diffuse_strength
     }
 
-    fn get_specular_strength(&self, y: f64, v: f64) -> f64 {
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
         self.specular_strength
     }
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use cgmath::{Vector3, InnerSpace, ElementWise};
+use rand::random;
+
+use super::material::{Material, reflect};
+use super::fresnel::fresnel_schlick;
+use super::microfacet::MicrofacetDistribution;
+use super::image_material::{MaterialValue1, MaterialValue3};
+use crate::texture::TexturePoint;
+
+// reflectance at normal incidence of the dielectric part, an index of 1.5
+const DIELECTRIC_F0: f64 = 0.04;
+
+/// Metallic-roughness material as used by glTF. Metals reflect with a tint of
+/// `base_color` and have no diffuse part, dielectrics have a diffuse
+/// `base_color` under a 4% specular layer. Specular lobes are GGX
+pub struct PbrMaterial {
+    pub base_color: MaterialValue3,
+    pub metallic: MaterialValue1,
+    // perceptual roughness in [0, 1], alpha is its square
+    pub roughness: MaterialValue1,
+}
+
+impl PbrMaterial {
+    pub fn new(base_color: Vector3<f64>, metallic: f64, roughness: f64) -> PbrMaterial {
+        PbrMaterial {
+            base_color: MaterialValue3::from_constant(base_color),
+            metallic: MaterialValue1::from_constant(metallic),
+            roughness: MaterialValue1::from_constant(roughness),
+        }
+    }
+
+    pub fn set_base_color(&mut self, base_color: MaterialValue3) -> &mut Self {
+        self.base_color = base_color;
+
+        self
+    }
+
+    pub fn set_metallic(&mut self, metallic: MaterialValue1) -> &mut Self {
+        self.metallic = metallic;
+
+        self
+    }
+
+    pub fn set_roughness(&mut self, roughness: MaterialValue1) -> &mut Self {
+        self.roughness = roughness;
+
+        self
+    }
+
+    fn f0(&self, p: &TexturePoint) -> Vector3<f64> {
+        let metallic = self.metallic.get_value(p).clamp(0.0, 1.0);
+        let dielectric = Vector3::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0);
+        dielectric * (1.0 - metallic) + self.base_color.get_value(p) * metallic
+    }
+
+    /// None for surfaces smooth enough to be treated as mirrors
+    fn distribution(&self, p: &TexturePoint) -> Option<MicrofacetDistribution> {
+        let roughness = self.roughness.get_value(p).clamp(0.0, 1.0);
+        if roughness < 1e-3 {
+            None
+        } else {
+            Some(MicrofacetDistribution::Ggx(roughness * roughness))
+        }
+    }
+}
+
+impl Material for PbrMaterial {
+    fn get_color(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.base_color.get_value(p)
+    }
+
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        1.0
+    }
+
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
+        1.5
+    }
+
+    fn get_diffuse_strength(&self, p: &TexturePoint) -> f64 {
+        1.0 - self.metallic.get_value(p).clamp(0.0, 1.0)
+    }
+
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        1.0
+    }
+
+    fn get_split(&self, _p: &TexturePoint, _cos_i: f64, _eta: f64) -> (Vector3<f64>, Vector3<f64>) {
+        // the Fresnel term depends on the sampled microfacet, see `sample_reflect`
+        (Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.0, 0.0, 0.0))
+    }
+
+    fn shade(
+        &self,
+        p: &TexturePoint,
+        normal: Vector3<f64>,
+        view_dir: Vector3<f64>,
+        light_dir: Vector3<f64>,
+        light_color: Vector3<f64>,
+    ) -> Vector3<f64> {
+        let cos_o = view_dir.dot(normal);
+        let cos_l = light_dir.dot(normal);
+        if cos_o <= 0.0 || cos_l <= 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let f0 = self.f0(p);
+        let diffuse = self.get_color(p) * (self.get_diffuse_strength(p) * (1.0 - DIELECTRIC_F0));
+        let specular = match self.distribution(p) {
+            // a mirror never reflects a point light towards the viewer
+            None => Vector3::new(0.0, 0.0, 0.0),
+            Some(distribution) => {
+                let half = (view_dir + light_dir).normalize();
+                let f = fresnel_schlick(light_dir.dot(half), f0);
+                f * (distribution.d(half, normal) * distribution.g(view_dir, light_dir, normal) / (4.0 * cos_o * cos_l))
+            },
+        };
+
+        (diffuse + specular).mul_element_wise(light_color) * cos_l
+    }
+
+    fn sample_reflect(&self, p: &TexturePoint, normal: Vector3<f64>, view_dir: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let cos_o = view_dir.dot(normal).max(1e-6);
+        let f0 = self.f0(p);
+        match self.distribution(p) {
+            None => {
+                (reflect(view_dir, normal).normalize(), fresnel_schlick(cos_o, f0))
+            },
+            Some(distribution) => {
+                let half = distribution.sample_h(normal, random::<f64>(), random::<f64>());
+                let cos_oh = view_dir.dot(half);
+                let dir = reflect(view_dir, half).normalize();
+                let cos_l = dir.dot(normal);
+                if cos_oh <= 0.0 || cos_l <= 0.0 {
+                    return (dir, Vector3::new(0.0, 0.0, 0.0));
+                }
+
+                // brdf * cos / pdf with pdf = d * cos_h / (4 cos_oh)
+                let cos_h = half.dot(normal);
+                let weight = distribution.g(view_dir, dir, normal) * cos_oh / (cos_o * cos_h);
+                (dir, fresnel_schlick(cos_oh, f0) * weight)
+            },
+        }
+    }
+}
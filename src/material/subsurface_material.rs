@@ -0,0 +1,140 @@
+use cgmath::Vector3;
+use super::material::Material;
+use super::fresnel::fresnel_dielectric;
+use crate::texture::TexturePoint;
+use crate::medium::HomogeneousMedium;
+
+// shortest mean free path used, light scatters right below the surface well
+// before it and a zero path would make the coefficients infinite
+const MIN_FREE_PATH: f64 = 1e-6;
+
+/// Single scattering albedo that makes a semi-infinite slab appear with
+/// multiple scattering `albedo`, the inversion used by Cycles and pbrt
+fn single_scattering_albedo(albedo: f64) -> f64 {
+    let a = albedo.clamp(0.0, 0.999);
+    let t = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+
+    1.0 - t * t
+}
+
+/// Translucent materials such as skin, wax or marble. Light refracted into
+/// the surface performs a random walk inside the object and leaves it
+/// somewhere else, see `MyTracing`.
+pub struct SubsurfaceMaterial {
+    // color the material appears with once light has scattered inside it
+    pub albedo: Vector3<f64>,
+    // average distance light travels between two scattering events, per channel
+    pub mean_free_path: Vector3<f64>,
+    pub ior: f64,
+    // anisotropy of the phase function inside the medium
+    pub g: f64,
+    pub specular_strength: f64,
+}
+
+impl SubsurfaceMaterial {
+    pub fn new(albedo: Vector3<f64>, mean_free_path: Vector3<f64>) -> SubsurfaceMaterial {
+        SubsurfaceMaterial {
+            albedo,
+            mean_free_path,
+            ior: 1.4,
+            g: 0.0,
+            specular_strength: 0.3,
+        }
+    }
+
+    pub fn set_ior(&mut self, ior: f64) -> &mut Self {
+        self.ior = ior;
+
+        self
+    }
+
+    pub fn set_anisotropy(&mut self, g: f64) -> &mut Self {
+        self.g = g;
+
+        self
+    }
+
+    pub fn set_specular_strength(&mut self, value: f64) -> &mut Self {
+        self.specular_strength = value;
+
+        self
+    }
+
+    /// Scattering and absorption coefficients of the medium below the surface.
+    /// Mean free paths are clamped to a small positive length
+    pub fn medium(&self) -> HomogeneousMedium {
+        let sigma_t = self.mean_free_path.map(|d| 1.0 / d.max(MIN_FREE_PATH));
+        let sigma_s = Vector3::new(
+            sigma_t.x * single_scattering_albedo(self.albedo.x),
+            sigma_t.y * single_scattering_albedo(self.albedo.y),
+            sigma_t.z * single_scattering_albedo(self.albedo.z),
+        );
+
+        HomogeneousMedium::new(sigma_t - sigma_s, sigma_s, self.g)
+    }
+}
+
+impl Material for SubsurfaceMaterial {
+    fn get_color(&self, _p: &TexturePoint) -> Vector3<f64> {
+        self.albedo
+    }
+
+    fn get_reflect_ratio(&self, _p: &TexturePoint) -> f64 {
+        fresnel_dielectric(1.0, 1.0 / self.ior)
+    }
+
+    fn get_refract_ratio(&self, _p: &TexturePoint) -> f64 {
+        // opaque to shadow rays, light only gets through by the random walk
+        0.0
+    }
+
+    fn get_refract_index(&self, _p: &TexturePoint) -> f64 {
+        1.0 / self.ior
+    }
+
+    fn get_diffuse_strength(&self, _p: &TexturePoint) -> f64 {
+        0.0
+    }
+
+    fn get_specular_strength(&self, _p: &TexturePoint) -> f64 {
+        self.specular_strength
+    }
+
+    fn get_split(&self, _p: &TexturePoint, cos_i: f64, eta: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let r = fresnel_dielectric(cos_i, eta);
+
+        (Vector3::new(r, r, r), Vector3::new(1.0 - r, 1.0 - r, 1.0 - r))
+    }
+
+    fn get_subsurface(&self, _p: &TexturePoint) -> Option<HomogeneousMedium> {
+        Some(self.medium())
+    }
+}
+
+#[cfg(test)]
+mod subsurface_material_test {
+    use super::{single_scattering_albedo, SubsurfaceMaterial};
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_albedo_inversion() {
+        assert!(single_scattering_albedo(0.0).abs() < 1e-3);
+        // multiple scattering brightens, so the single scattering albedo is higher
+        let a = single_scattering_albedo(0.5);
+        assert!(a > 0.5 && a < 1.0);
+        assert!(single_scattering_albedo(0.8) > a);
+    }
+
+    #[test]
+    fn test_medium_at_the_limits() {
+        // no path, a negative one, and albedos outside [0, 1]
+        let material = SubsurfaceMaterial::new(Vector3::new(-0.5, 1.0, 2.0), Vector3::new(0.0, -1.0, 1.0));
+        let medium = material.medium();
+        for v in [medium.sigma_a, medium.sigma_s] {
+            assert!(v.x.is_finite() && v.y.is_finite() && v.z.is_finite());
+            assert!(v.x >= 0.0 && v.y >= 0.0 && v.z >= 0.0);
+        }
+        assert!(medium.sigma_s.x.abs() < 1e-3 * medium.sigma_t().x);
+        assert!((medium.sigma_t().z - 1.0).abs() < 1e-12);
+    }
+}
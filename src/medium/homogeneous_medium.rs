@@ -0,0 +1,34 @@
+use cgmath::Vector3;
+
+use super::phase::HenyeyGreenstein;
+
+/// A participating medium with constant coefficients, e.g. fog or murky water.
+/// Coefficients are per unit length and per RGB channel.
+#[derive(Clone, Debug)]
+pub struct HomogeneousMedium {
+    pub sigma_a: Vector3<f64>,
+    pub sigma_s: Vector3<f64>,
+    pub phase: HenyeyGreenstein,
+}
+
+impl HomogeneousMedium {
+    pub fn new(sigma_a: Vector3<f64>, sigma_s: Vector3<f64>, g: f64) -> HomogeneousMedium {
+        HomogeneousMedium {
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    /// Gray, isotropic fog thinning light by `density` per unit length
+    pub fn fog(density: f64, albedo: f64) -> HomogeneousMedium {
+        let s = density * albedo;
+        let a = density - s;
+        HomogeneousMedium::new(Vector3::new(a, a, a), Vector3::new(s, s, s), 0.0)
+    }
+
+    /// Extinction coefficient
+    pub fn sigma_t(&self) -> Vector3<f64> {
+        self.sigma_a + self.sigma_s
+    }
+}
@@ -0,0 +1,7 @@
+pub mod phase;
+pub mod homogeneous_medium;
+pub mod grid_volume;
+
+pub use phase::HenyeyGreenstein;
+pub use homogeneous_medium::HomogeneousMedium;
+pub use grid_volume::{DensityGrid, GridVolume};
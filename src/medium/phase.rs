@@ -0,0 +1,75 @@
+use std::f64::consts::PI;
+
+use cgmath::{Vector3, InnerSpace};
+
+use crate::ray::coordinate_system;
+
+/// Henyey-Greenstein phase function. `g` in (-1, 1) is the mean cosine of the
+/// scattering angle, positive values scatter forward.
+#[derive(Clone, Copy, Debug)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            g,
+        }
+    }
+
+    /// Density for light propagating along `dir_in` to continue along `dir_out`
+    pub fn p(&self, dir_in: Vector3<f64>, dir_out: Vector3<f64>) -> f64 {
+        let cos = dir_in.dot(dir_out);
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos;
+
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    /// Samples an outgoing direction for light propagating along `dir_in`,
+    /// exactly proportional to `p` so the sample weight is one
+    pub fn sample(&self, dir_in: Vector3<f64>, u1: f64, u2: f64) -> Vector3<f64> {
+        let g = self.g;
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u1
+        } else {
+            let sq = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+            (1.0 + g * g - sq * sq) / (2.0 * g)
+        };
+        let cos = cos.clamp(-1.0, 1.0);
+        let sin = (1.0 - cos * cos).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        let (t, b) = coordinate_system(dir_in);
+        (dir_in * cos + t * (sin * phi.cos()) + b * (sin * phi.sin())).normalize()
+    }
+}
+
+#[cfg(test)]
+mod phase_test {
+    use super::HenyeyGreenstein;
+    use cgmath::{Vector3, InnerSpace};
+
+    #[test]
+    fn test_isotropic() {
+        let hg = HenyeyGreenstein::new(0.0);
+        let a = Vector3::new(0.0, 0.0, 1.0);
+        let b = Vector3::new(1.0, 0.0, 0.0);
+        assert!((hg.p(a, b) - 1.0 / (4.0 * std::f64::consts::PI)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_forward_sampling() {
+        let hg = HenyeyGreenstein::new(0.8);
+        let dir = Vector3::new(0.0, 1.0, 0.0);
+        let mut mean = 0.0;
+        let n = 1000;
+        for i in 0..n {
+            let d = hg.sample(dir, (i as f64 + 0.5) / n as f64, 0.3);
+            mean += d.dot(dir);
+        }
+        // the mean cosine of the distribution is g
+        assert!((mean / n as f64 - 0.8).abs() < 0.02);
+    }
+}
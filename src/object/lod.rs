@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use super::mesh::Mesh;
+
+/// One level of detail of an object, used while the object covers at least
+/// `min_pixels` of the image height
+#[derive(Clone)]
+pub struct Lod {
+    pub mesh: Rc<Mesh>,
+    pub min_pixels: f64,
+}
+
+impl Lod {
+    pub fn new(mesh: Rc<Mesh>, min_pixels: f64) -> Lod {
+        Lod {
+            mesh,
+            min_pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod lod_test {
+    use crate::object::{Object, Mesh, Subdivision, SubdivisionScheme};
+    use crate::camera::{Camera, PerspectiveCamera};
+    use crate::transform::Transform;
+    use cgmath::{InnerSpace, Vector3};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_select_by_distance() {
+        let cube = Mesh::from_obj_str("v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+            v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+            f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n");
+        let mut obj = Object::from_mesh(Rc::new(Subdivision::new(SubdivisionScheme::CatmullClark, 3).apply(&cube)));
+        obj.generate_lods(2, 40.0);
+        assert_eq!(obj.lods.len(), 3);
+        assert!(obj.lods[1].mesh.faces.len() <= 192);
+        assert!(obj.lods[2].mesh.faces.len() <= 48);
+
+        // 90 degrees field of view, 100 pixels high: a unit radius sphere
+        // 10 away covers 10 pixels
+        let camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 1.0, 0.1, 100.0);
+        assert!((camera.projected_size(Vector3::new(0.0, 0.0, -10.0), 1.0, 100) - 10.0).abs() < 1e-9);
+
+        let radius = obj.bounds().diagonal().magnitude() / 2.0;
+        let mut at = |distance: f64| {
+            obj.transform = Transform::translation(Vector3::new(0.0, 0.0, -distance));
+            obj.select_lod(&camera, 100, 0.0)
+        };
+        assert_eq!(at(radius * 100.0 / 50.0), Some(0));
+        assert_eq!(at(radius * 100.0 / 30.0), Some(1));
+        assert_eq!(at(radius * 100.0 / 5.0), Some(2));
+        assert_eq!(obj.mesh.faces.len(), obj.lods[2].mesh.faces.len());
+
+        // a moving object is judged where it is at the given time
+        obj.add_keyframe(0.0, Transform::translation(Vector3::new(0.0, 0.0, -radius * 100.0 / 50.0)));
+        obj.add_keyframe(1.0, Transform::translation(Vector3::new(0.0, 0.0, -radius * 100.0 / 5.0)));
+        assert_eq!(obj.select_lod(&camera, 100, 0.0), Some(0));
+        assert_eq!(obj.select_lod(&camera, 100, 1.0), Some(2));
+    }
+}
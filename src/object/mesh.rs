@@ -0,0 +1,767 @@
+use std::fs;
+use std::path::Path;
+use std::cell::OnceCell;
+
+use cgmath::prelude::*;
+use cgmath::{Vector3, Vector2};
+
+use super::object::{IntersectResult, IntersectDirection};
+use crate::ray::{Ray, gamma};
+use crate::accel::{Aabb, Bvh};
+use super::ply::parse_ply;
+use super::stl::{parse_stl, is_binary_stl};
+use super::triangulate::triangulate;
+
+#[derive(Clone, Debug)]
+pub struct PointStruct {
+    pub vertex_index: i32,
+    pub texture_index: i32,
+    pub normal_index: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub vertex: Vector3<f64>,
+    pub texture: Vector2<f64>,
+    pub normal: Vector3<f64>,
+    // direction of increasing u, zero when the uvs don't define one
+    pub tangent: Vector3<f64>,
+    pub color: Option<Vector3<f64>>,
+}
+
+pub struct Face3 {
+    pub points: [Point; 3],
+}
+
+impl Face3 {
+    /// Watertight ray-triangle test (Woop et al. 2013). The triangle is moved
+    /// into a space where the ray runs along +z from the origin, and the edge
+    /// functions are evaluated there, so a ray through a shared edge or vertex
+    /// hits at least one of the adjacent triangles
+    pub fn intersect<'a>(&self, ray: &Ray) -> IntersectResult<'a> {
+        let p0 = self.points[0].vertex;
+        let p1 = self.points[1].vertex;
+        let p2 = self.points[2].vertex;
+
+        // permute the axes so the ray direction is largest along z
+        let abs_dir = Vector3::new(ray.dir.x.abs(), ray.dir.y.abs(), ray.dir.z.abs());
+        let kz = if abs_dir.x > abs_dir.y {
+            if abs_dir.x > abs_dir.z { 0 } else { 2 }
+        } else if abs_dir.y > abs_dir.z { 1 } else { 2 };
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+        let permute = |v: Vector3<f64>| Vector3::new(v[kx], v[ky], v[kz]);
+
+        let d = permute(ray.dir);
+        let mut p0t = permute(p0 - ray.pos);
+        let mut p1t = permute(p1 - ray.pos);
+        let mut p2t = permute(p2 - ray.pos);
+
+        // shear so the ray points along +z
+        let sx = -d.x / d.z;
+        let sy = -d.y / d.z;
+        let sz = 1.0 / d.z;
+        for p in [&mut p0t, &mut p1t, &mut p2t] {
+            p.x += sx * p.z;
+            p.y += sy * p.z;
+        }
+
+        let e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+        let e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+        let e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return IntersectResult::no_intersect();
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return IntersectResult::no_intersect();
+        }
+
+        // scaled distance, compared against the interval before dividing
+        p0t.z *= sz;
+        p1t.z *= sz;
+        p2t.z *= sz;
+        let t_scaled = e0 * p0t.z + e1 * p1t.z + e2 * p2t.z;
+        if det < 0.0 && (t_scaled >= 0.0 || t_scaled < ray.t_max * det) {
+            return IntersectResult::no_intersect();
+        }
+        if det > 0.0 && (t_scaled <= 0.0 || t_scaled > ray.t_max * det) {
+            return IntersectResult::no_intersect();
+        }
+
+        let inv_det = 1.0 / det;
+        let b0 = e0 * inv_det;
+        let b1 = e1 * inv_det;
+        let b2 = e2 * inv_det;
+        let t = t_scaled * inv_det;
+
+        // reject t that could be non-positive after accounting for rounding
+        let max_zt = p0t.z.abs().max(p1t.z.abs()).max(p2t.z.abs());
+        let max_xt = p0t.x.abs().max(p1t.x.abs()).max(p2t.x.abs());
+        let max_yt = p0t.y.abs().max(p1t.y.abs()).max(p2t.y.abs());
+        let delta_z = gamma(3) * max_zt;
+        let delta_x = gamma(5) * (max_xt + max_zt);
+        let delta_y = gamma(5) * (max_yt + max_zt);
+        let delta_e = 2.0 * (gamma(2) * max_xt * max_yt + delta_y * max_xt + delta_x * max_yt);
+        let max_e = e0.abs().max(e1.abs()).max(e2.abs());
+        let delta_t = 3.0 * (gamma(3) * max_e * max_zt + delta_e * max_zt + delta_z * max_e) * inv_det.abs();
+        if t <= delta_t || !ray.contains(t) {
+            return IntersectResult::no_intersect();
+        }
+
+        // the point is rebuilt from the barycentrics, which bounds its error
+        let new_point = b0 * p0 + b1 * p1 + b2 * p2;
+        let abs_sum = (b0 * p0).map(f64::abs) + (b1 * p1).map(f64::abs) + (b2 * p2).map(f64::abs);
+        let error = abs_sum * gamma(7);
+
+        let barycentric = Vector3::new(b0, b1, b2);
+        let [a, b, c] = &self.points;
+        let new_texture = b0 * a.texture + b1 * b.texture + b2 * c.texture;
+        let mut geometric_normal = (p1 - p0).cross(p2 - p0).normalize();
+        let new_normal = b0 * a.normal + b1 * b.normal + b2 * c.normal;
+        let new_normal = if new_normal.magnitude2() > 0.0 { new_normal.normalize() } else { geometric_normal };
+        // the winding may disagree with the vertex normals, which decide the outside
+        if geometric_normal.dot(new_normal) < 0.0 {
+            geometric_normal = -geometric_normal;
+        }
+        let new_color = match (a.color, b.color, c.color) {
+            (Some(ca), Some(cb), Some(cc)) => Some(b0 * ca + b1 * cb + b2 * cc),
+            _ => None,
+        };
+
+        let direction = if ray.dir.dot(geometric_normal) < 0.0 {
+            IntersectDirection::Positive
+        } else {
+            IntersectDirection::Negative
+        };
+        IntersectResult {
+            point: Some(Point {
+                vertex: new_point,
+                texture: new_texture,
+                normal: new_normal,
+                tangent: self.tangent(barycentric, new_normal),
+                color: new_color,
+            }),
+            direction,
+            is_intersect: true,
+            dis: t,
+            object: None,
+            error,
+            geometric_normal,
+            barycentric,
+            uv_sets: Vec::new(),
+        }
+    }
+
+    /// Interpolated vertex tangent, or the face's direction of increasing u when
+    /// the vertices have none, made orthogonal to `normal`
+    fn tangent(&self, barycentric: Vector3<f64>, normal: Vector3<f64>) -> Vector3<f64> {
+        let [a, b, c] = &self.points;
+        let mut t = barycentric.x * a.tangent + barycentric.y * b.tangent + barycentric.z * c.tangent;
+        if t.magnitude2() == 0.0 {
+            t = self.dpdu();
+        }
+        let t = t - normal * normal.dot(t);
+        if t.magnitude2() > 1e-20 { t.normalize() } else { Vector3::zero() }
+    }
+
+    /// Change of position along u, zero when the uvs are degenerate
+    pub fn dpdu(&self) -> Vector3<f64> {
+        let [a, b, c] = &self.points;
+        let duv02 = a.texture - c.texture;
+        let duv12 = b.texture - c.texture;
+        let det = duv02.x * duv12.y - duv02.y * duv12.x;
+        if det.abs() < 1e-12 {
+            return Vector3::zero();
+        }
+
+        ((a.vertex - c.vertex) * duv12.y - (b.vertex - c.vertex) * duv02.y) / det
+    }
+}
+
+pub struct FaceIter<'a> {
+    next: usize,
+    mesh: &'a Mesh,
+}
+
+impl<'a> Iterator for FaceIter<'a> {
+    type Item = Face3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.mesh.faces.len() {
+            return None;
+        }
+        self.next += 1;
+
+        Some(self.mesh.face(self.next - 1))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FaceStruct {
+    pub points: Vec<PointStruct>,
+}
+
+/// Triangle mesh in model space. Meshes are shared by every `Object`
+/// instancing them and carry their own BVH, built on the first intersection.
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Vector3<f64>>,
+    pub textures: Vec<Vector2<f64>>,
+    pub normals: Vec<Vector3<f64>>,
+    pub faces: Vec<FaceStruct>,
+    // faces as the source had them before triangulation, empty when the
+    // loader doesn't keep them. Needed by Catmull-Clark subdivision
+    pub polygons: Vec<FaceStruct>,
+    // polygon each triangle in `faces` was cut from, empty with `polygons`
+    pub face_polygons: Vec<usize>,
+
+    // per vertex attributes, indexed like `vertices` and empty when absent
+    pub colors: Vec<Vector3<f64>>,
+    pub tangents: Vec<Vector3<f64>>,
+    // additional texture coordinate sets, each indexed like `vertices`
+    pub uv_sets: Vec<Vec<Vector2<f64>>>,
+
+    bvh: OnceCell<Bvh>,
+}
+
+impl PointStruct {
+    pub fn new(vertex: i32, texture: i32, normal: i32) -> PointStruct {
+        PointStruct {
+            vertex_index: vertex,
+            texture_index: texture,
+            normal_index: normal,
+        }
+    }
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Mesh::new()
+    }
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh {
+            vertices: Vec::new(),
+            textures: Vec::new(),
+            normals: Vec::new(),
+            faces: Vec::new(),
+            polygons: Vec::new(),
+            face_polygons: Vec::new(),
+
+            colors: Vec::new(),
+            tangents: Vec::new(),
+            uv_sets: Vec::new(),
+
+            bvh: OnceCell::new(),
+        }
+    }
+
+    pub fn from_file(filename: &str) -> Mesh {
+        let contents = fs::read_to_string(filename).unwrap();
+        Mesh::from_obj_str(&contents)
+    }
+
+    pub fn from_obj_str(contents: &str) -> Mesh {
+        Mesh::try_from_obj_str(contents).unwrap()
+    }
+
+    /// Parses an OBJ mesh. Malformed numbers, statements with too few values
+    /// and faces referring to missing vertices are reported with their line.
+    /// Negative indices count back from the last element read
+    pub fn try_from_obj_str(contents: &str) -> Result<Mesh, String> {
+        let mut mesh = Mesh::new();
+        // `v x y z r g b` carries a vertex color, vertices without one are white
+        let mut colors = Vec::new();
+        let mut has_colors = false;
+        for (i, line) in contents.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let keyword = fields.next();
+            let fields: Vec<&str> = fields.collect();
+            let line_error = |e: String| format!("line {}: {}", i + 1, e);
+            match keyword {
+                Some("v") => {
+                    let coords = parse_obj_numbers(&fields, 3).map_err(line_error)?;
+                    mesh.add_vertex(Vector3::new(coords[0], coords[1], coords[2]));
+                    if coords.len() >= 6 {
+                        colors.push(Vector3::new(coords[3], coords[4], coords[5]));
+                        has_colors = true;
+                    } else {
+                        colors.push(Vector3::new(1.0, 1.0, 1.0));
+                    }
+                }
+                Some("vt") => {
+                    let coords = parse_obj_numbers(&fields, 2).map_err(line_error)?;
+                    mesh.add_texture(Vector2::new(coords[0], coords[1]));
+                }
+                Some("vn") => {
+                    let coords = parse_obj_numbers(&fields, 3).map_err(line_error)?;
+                    mesh.add_normal(Vector3::new(coords[0], coords[1], coords[2]));
+                }
+                Some("f") => {
+                    if fields.len() < 3 {
+                        return Err(line_error(String::from("face with less than three points")));
+                    }
+                    let counts = [mesh.vertices.len(), mesh.textures.len(), mesh.normals.len()];
+                    let mut points: Vec<PointStruct> = Vec::new();
+                    for &p in fields.iter() {
+                        let values: Vec<&str> = p.split('/').collect();
+                        if values.len() > 3 || values[0].is_empty() {
+                            return Err(line_error(format!("bad face point `{}`", p)));
+                        }
+                        // missing indices, as in `1//1` or `1`, are stored as 0
+                        let mut indices = [0; 3];
+                        for (k, s) in values.iter().enumerate() {
+                            if !s.is_empty() {
+                                indices[k] = obj_index(s, counts[k]).map_err(line_error)?;
+                            }
+                        }
+                        points.push(PointStruct::new(indices[0], indices[1], indices[2]));
+                    }
+
+                    mesh.add_polygon(FaceStruct {
+                        points,
+                    });
+                }
+                _ => (),
+            }
+        }
+        if has_colors {
+            mesh.colors = colors;
+        }
+
+        mesh.remove_degenerate_faces();
+
+        Ok(mesh)
+    }
+
+    pub fn from_ply(filename: &str) -> Result<Mesh, String> {
+        let data = fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        parse_ply(&data)
+    }
+
+    pub fn from_stl(filename: &str) -> Result<Mesh, String> {
+        let data = fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        parse_stl(&data)
+    }
+
+    /// Loads an OBJ, PLY or STL file. PLY and STL are recognized by their
+    /// content, other files by the extension
+    pub fn load(filename: &str) -> Result<Mesh, String> {
+        let data = fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        if data.starts_with(b"ply") {
+            return parse_ply(&data);
+        }
+        if is_binary_stl(&data) || data.starts_with(b"solid") {
+            return parse_stl(&data);
+        }
+
+        let extension = Path::new(filename).extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("obj") => Mesh::try_from_obj_str(&String::from_utf8_lossy(&data))
+                .map_err(|e| format!("{}: {}", filename, e)),
+            Some("ply") => parse_ply(&data),
+            Some("stl") => parse_stl(&data),
+            _ => Err(format!("{}: unknown mesh format", filename)),
+        }
+    }
+
+    /// Drops zero area triangles, they can't be hit and have no normal.
+    /// Returns the number of faces removed
+    pub fn remove_degenerate_faces(&mut self) -> usize {
+        let keep: Vec<bool> = self.faces.iter().map(|f| !self.is_degenerate(f)).collect();
+        self.retain_faces(&keep)
+    }
+
+    /// Keeps the triangles marked in `keep` along with their polygon indices,
+    /// returns the number of triangles removed
+    pub(crate) fn retain_faces(&mut self, keep: &[bool]) -> usize {
+        let count = self.faces.len();
+        let mut i = 0;
+        self.faces.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+        if !self.face_polygons.is_empty() {
+            let mut i = 0;
+            self.face_polygons.retain(|_| {
+                i += 1;
+                keep[i - 1]
+            });
+        }
+        self.bvh = OnceCell::new();
+
+        count - self.faces.len()
+    }
+
+    pub(crate) fn is_degenerate(&self, face: &FaceStruct) -> bool {
+        let a = self.vertices[face.points[0].vertex_index as usize - 1];
+        let b = self.vertices[face.points[1].vertex_index as usize - 1];
+        let c = self.vertices[face.points[2].vertex_index as usize - 1];
+
+        (b - a).cross(c - a).magnitude2() == 0.0
+    }
+
+    pub fn add_vertex(&mut self, vertex: Vector3<f64>) {
+        self.vertices.push(vertex);
+        self.bvh = OnceCell::new();
+    }
+
+    pub fn add_texture(&mut self, texture: Vector2<f64>) {
+        self.textures.push(texture);
+    }
+
+    pub fn add_normal(&mut self, normal: Vector3<f64>) {
+        self.normals.push(normal);
+    }
+
+    /// Adds a texture coordinate set with one entry per vertex
+    pub fn add_uv_set(&mut self, uvs: Vec<Vector2<f64>>) {
+        self.uv_sets.push(uvs);
+    }
+
+    /// Smooth per vertex tangents, the average direction of increasing u over
+    /// the faces around each vertex
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vector3::zero(); self.vertices.len()];
+        for i in 0..self.faces.len() {
+            let dpdu = self.face(i).dpdu();
+            for p in self.faces[i].points.iter() {
+                tangents[p.vertex_index as usize - 1] += dpdu;
+            }
+        }
+        self.tangents = tangents.into_iter()
+            .map(|t| if t.magnitude2() > 0.0 { t.normalize() } else { t })
+            .collect();
+    }
+
+    pub fn add_face(&mut self, face: FaceStruct) {
+        self.faces.push(face);
+        self.bvh = OnceCell::new();
+    }
+
+    /// Adds a polygon with any number of points, cut into triangles by ear
+    /// clipping. Points referring to vertices not added yet make it a fan
+    pub fn add_polygon(&mut self, polygon: FaceStruct) {
+        let points = &polygon.points;
+        let positions: Option<Vec<Vector3<f64>>> = points.iter()
+            .map(|p| self.vertices.get((p.vertex_index - 1) as usize).copied())
+            .collect();
+        let triangles = match positions {
+            Some(positions) => triangulate(&positions),
+            None => (1..points.len().saturating_sub(1)).map(|i| [0, i, i + 1]).collect(),
+        };
+
+        for t in triangles {
+            self.add_face(FaceStruct {
+                points: t.iter().map(|&i| points[i].clone()).collect(),
+            });
+            self.face_polygons.push(self.polygons.len());
+        }
+        self.polygons.push(polygon);
+    }
+
+    /// Whether edge `k` of triangle `face`, from its point k to the next, is an
+    /// edge of the polygon it was cut from rather than a diagonal. Always true
+    /// for meshes without polygons
+    pub fn is_polygon_edge(&self, face: usize, k: usize) -> bool {
+        let polygon = match self.face_polygons.get(face) {
+            Some(&polygon) => &self.polygons[polygon].points,
+            None => return true,
+        };
+        let a = self.faces[face].points[k].vertex_index;
+        let b = self.faces[face].points[(k + 1) % 3].vertex_index;
+        (0..polygon.len()).any(|i| {
+            let (c, d) = (polygon[i].vertex_index, polygon[(i + 1) % polygon.len()].vertex_index);
+            (a, b) == (c, d) || (a, b) == (d, c)
+        })
+    }
+
+    pub fn face(&self, index: usize) -> Face3 {
+        let f = &self.faces[index];
+        let a = self.vertices[f.points[0].vertex_index as usize - 1];
+        let b = self.vertices[f.points[1].vertex_index as usize - 1];
+        let c = self.vertices[f.points[2].vertex_index as usize - 1];
+        let face_normal = (b - a).cross(c - a).normalize();
+
+        let point = |p: &PointStruct| Point {
+            vertex: self.vertices[p.vertex_index as usize - 1],
+            texture: if p.texture_index > 0 {
+                self.textures[p.texture_index as usize - 1]
+            } else {
+                Vector2::new(0.0, 0.0)
+            },
+            normal: if p.normal_index > 0 {
+                self.normals[p.normal_index as usize - 1].normalize()
+            } else {
+                face_normal
+            },
+            tangent: self.tangents.get(p.vertex_index as usize - 1).copied().unwrap_or_else(Vector3::zero),
+            color: self.colors.get(p.vertex_index as usize - 1).copied(),
+        };
+
+        Face3 {
+            points: [point(&f.points[0]), point(&f.points[1]), point(&f.points[2])],
+        }
+    }
+
+    pub fn faces_iter(&self) -> FaceIter<'_> {
+        FaceIter {
+            next: 0,
+            mesh: self,
+        }
+    }
+
+    fn face_bounds(&self, index: usize) -> Aabb {
+        Aabb::from_points(self.faces[index].points.iter().take(3).map(|p| &self.vertices[p.vertex_index as usize - 1]))
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<Aabb> = (0..self.faces.len()).map(|i| self.face_bounds(i)).collect();
+            Bvh::build(&bounds)
+        })
+    }
+
+    /// Drops the BVH and with it the bounds, both are rebuilt on the next
+    /// query. Needed after moving vertices through the public fields
+    pub fn recompute_bounds(&mut self) {
+        self.bvh = OnceCell::new();
+    }
+
+    /// Uses a tree built earlier over the same faces, as read from a cache
+    pub(crate) fn set_bvh(&mut self, bvh: Bvh) {
+        self.bvh = OnceCell::from(bvh);
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bvh().bounds()
+    }
+
+    /// Nearest hit in model space, the result has no object attached
+    pub fn intersect<'a>(&self, ray: &Ray) -> IntersectResult<'a> {
+        let mut result = IntersectResult::no_intersect();
+        let mut face = 0;
+        self.bvh().intersect(ray, ray.t_max, |i, t_max| {
+            let r = self.face(i).intersect(ray);
+            if r.is_intersect && r.dis < t_max {
+                let t = r.dis;
+                result = r;
+                face = i;
+                Some(t)
+            } else {
+                None
+            }
+        });
+        if result.is_intersect {
+            self.interpolate_uv_sets(face, &mut result);
+        }
+
+        result
+    }
+
+    /// Fills the extra uv sets of a hit on face `index` from its barycentrics
+    fn interpolate_uv_sets(&self, index: usize, result: &mut IntersectResult) {
+        let points = &self.faces[index].points;
+        let b = result.barycentric;
+        result.uv_sets = self.uv_sets.iter().map(|set| {
+            let uv = |k: usize| set[points[k].vertex_index as usize - 1];
+            b.x * uv(0) + b.y * uv(1) + b.z * uv(2)
+        }).collect();
+    }
+
+    /// Whether anything is hit within the ray interval, stops at the first hit
+    pub fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh().any(ray, ray.t_max, |i| self.face(i).intersect(ray).is_intersect)
+    }
+
+    /// Every hit along `ray` in model space, nearest first
+    pub fn intersect_all<'a>(&self, ray: &Ray) -> Vec<IntersectResult<'a>> {
+        let mut result: Vec<IntersectResult<'a>> = Vec::new();
+        self.bvh().intersect(ray, ray.t_max, |i, _| {
+            let mut r = self.face(i).intersect(ray);
+            if r.is_intersect {
+                self.interpolate_uv_sets(i, &mut r);
+                result.push(r);
+            }
+            // never shrink the search, all hits are wanted
+            None
+        });
+        result.sort_by(|a, b| a.dis.partial_cmp(&b.dis).unwrap_or(std::cmp::Ordering::Equal));
+
+        result
+    }
+}
+
+// the numbers of an OBJ statement, at least `min` of them
+fn parse_obj_numbers(fields: &[&str], min: usize) -> Result<Vec<f64>, String> {
+    let numbers = fields.iter()
+        .map(|s| s.parse::<f64>().map_err(|_| format!("bad number `{}`", s)))
+        .collect::<Result<Vec<f64>, String>>()?;
+    if numbers.len() < min {
+        return Err(format!("expected {} numbers, found {}", min, numbers.len()));
+    }
+    Ok(numbers)
+}
+
+// a 1-based OBJ index into `count` elements read so far, negative ones count
+// back from the last
+fn obj_index(s: &str, count: usize) -> Result<i32, String> {
+    let index = s.parse::<i64>().map_err(|_| format!("bad index `{}`", s))?;
+    let index = if index < 0 { count as i64 + index + 1 } else { index };
+    if index < 1 || index > count as i64 {
+        return Err(format!("face refers to missing element {}", s));
+    }
+    Ok(index as i32)
+}
+
+#[cfg(test)]
+mod mesh_test {
+    use super::{Mesh, FaceStruct, PointStruct};
+    use crate::ray::Ray;
+    use crate::object::Object;
+    use cgmath::{InnerSpace, Vector2, Vector3};
+
+    // fan of triangles around the origin in the z = 0 plane, every one shares
+    // the center vertex and an edge with its neighbours
+    fn fan(segments: usize) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.add_vertex(Vector3::new(0.0, 0.0, 0.0));
+        for i in 0..segments {
+            let angle = i as f64 / segments as f64 * std::f64::consts::PI * 2.0;
+            mesh.add_vertex(Vector3::new(angle.cos(), angle.sin(), 0.0));
+        }
+        for i in 0..segments {
+            let next = (i + 1) % segments;
+            mesh.add_face(FaceStruct {
+                points: vec![PointStruct::new(1, 0, 0), PointStruct::new(i as i32 + 2, 0, 0), PointStruct::new(next as i32 + 2, 0, 0)],
+            });
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_edge_and_vertex_hits() {
+        let segments = 7;
+        let mesh = fan(segments);
+
+        // through the shared center vertex, from a few directions
+        for dir in [Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.3, -0.2, -1.0), Vector3::new(-0.7, 0.1, -0.4)] {
+            let ray = Ray::new(-dir * 3.0, dir);
+            assert!(mesh.intersect(&ray).is_intersect, "missed the center vertex along {:?}", dir);
+        }
+
+        // along every shared edge
+        for i in 0..segments {
+            let angle = i as f64 / segments as f64 * std::f64::consts::PI * 2.0;
+            for k in 1..10 {
+                let r = k as f64 / 10.0;
+                let target = Vector3::new(angle.cos() * r, angle.sin() * r, 0.0);
+                let ray = Ray::new(target + Vector3::new(0.1, 0.2, 1.0), Vector3::new(-0.1, -0.2, -1.0));
+                let hit = mesh.intersect(&ray);
+                assert!(hit.is_intersect, "missed edge {} at {}", i, r);
+                assert!((hit.point.unwrap().vertex - target).magnitude() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_barycentric_attributes() {
+        let path = std::env::temp_dir().join(format!("hakaze_mesh_test_{}.obj", std::process::id()));
+        std::fs::write(&path, "v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nv 2 2 0\n\
+            vt 0 0\nvt 1 0\nvt 0 1\nf 1/1 2/2 3/3\nf 1 4 4\n").unwrap();
+        let mut mesh = Mesh::from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        // the second face repeats a vertex and is dropped
+        assert_eq!(mesh.faces.len(), 1);
+        mesh.add_uv_set(vec![Vector2::new(0.0, 0.0), Vector2::new(0.0, 2.0), Vector2::new(4.0, 0.0), Vector2::new(0.0, 0.0)]);
+
+        let ray = Ray::new(Vector3::new(0.25, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = mesh.intersect(&ray);
+        assert!(hit.is_intersect);
+        assert!((hit.barycentric - Vector3::new(0.25, 0.25, 0.5)).magnitude() < 1e-12);
+        let point = hit.point.unwrap();
+        assert!((point.texture - Vector2::new(0.25, 0.5)).magnitude() < 1e-12);
+        assert!((point.color.unwrap() - Vector3::new(0.25, 0.25, 0.5)).magnitude() < 1e-12);
+        assert!((point.tangent - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-12);
+        assert!((hit.uv_sets[0] - Vector2::new(2.0, 0.5)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn test_obj_errors() {
+        // runs of whitespace and negative indices are fine
+        let mesh = Mesh::try_from_obj_str("v 0  0 0\nv 1\t0 0\nv 0 1 0\nf -3 -2 -1\n").unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(mesh.faces[0].points[2].vertex_index, 3);
+
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\n";
+        for (obj, line) in [
+            ("v 1 2\n", 1),
+            ("v 1 2 x\n", 1),
+            ("vt 1\n", 5),
+            ("f 1/x/1 2 3\n", 5),
+            ("f 1 2\n", 5),
+            ("f 1 2 4\n", 5),
+            ("f 1/2 2 3\n", 5),
+            ("f 1/1/1/1 2 3\n", 5),
+        ] {
+            let contents = if line == 1 { obj.to_string() } else { format!("{}{}", vertices, obj) };
+            let err = Mesh::try_from_obj_str(&contents).err().unwrap_or_else(|| panic!("parsed {:?}", obj));
+            assert!(err.starts_with(&format!("line {}:", line)), "{:?}: {}", obj, err);
+        }
+
+        let path = std::env::temp_dir().join(format!("hakaze_mesh_error_test_{}.obj", std::process::id()));
+        std::fs::write(&path, "v 1 2\n").unwrap();
+        let result = Mesh::load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_spawned_rays_leave_surface() {
+        let mut object = Object::from_mesh(std::rc::Rc::new(fan(5)));
+        object.rotate(0.4, 1.1, -0.3).scale_uniform(1234.5).translate(5e3, -2e3, 7e2);
+
+        for i in 0..200 {
+            let x = (i as f64 * 0.37).sin() * 0.5;
+            let y = (i as f64 * 0.61).cos() * 0.5;
+            let target = object.transform.transform_point(Vector3::new(x, y, 0.0));
+            let origin = object.transform.transform_point(Vector3::new(x + 0.3, y - 0.1, 2.0));
+            let ray = Ray::new(origin, (target - origin).normalize());
+            let hit = object.intersect(&ray);
+            assert!(hit.is_intersect);
+
+            // a flat surface can't be hit again from either side
+            let n = hit.geometric_normal;
+            let reflected = ray.dir - 2.0 * ray.dir.dot(n) * n;
+            assert!(!object.intersect(&hit.spawn_ray(reflected)).is_intersect);
+            assert!(!object.intersect(&hit.spawn_ray(ray.dir)).is_intersect);
+        }
+    }
+
+    #[test]
+    fn test_concave_polygon_kept() {
+        // a square with a notch cut into its top edge
+        let mesh = Mesh::from_obj_str("v 0 0 0\nv 3 0 0\nv 3 2 0\nv 2 2 0\nv 1.5 1 0\nv 1 2 0\nv 0 2 0\nf 1 2 3 4 5 6 7\n");
+        assert_eq!(mesh.polygons.len(), 1);
+        assert_eq!(mesh.polygons[0].points.len(), 7);
+        assert_eq!(mesh.faces.len(), 5);
+        assert_eq!(mesh.face_polygons, vec![0; 5]);
+
+        // inside the notch nothing is hit, below it the polygon is
+        let down = Vector3::new(0.0, 0.0, -1.0);
+        assert!(!mesh.intersect(&Ray::new(Vector3::new(1.5, 1.8, 1.0), down)).is_intersect);
+        assert!(mesh.intersect(&Ray::new(Vector3::new(1.5, 0.5, 1.0), down)).is_intersect);
+
+        // seven sides, the other eight triangle edges are diagonals
+        let edges = (0..mesh.faces.len())
+            .flat_map(|f| (0..3).map(move |k| (f, k)))
+            .filter(|&(f, k)| mesh.is_polygon_edge(f, k))
+            .count();
+        assert_eq!(edges, 7);
+    }
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::mesh::{Mesh, FaceStruct, PointStruct};
+use crate::accel::Bvh;
+use crate::util::binio::{BinWriter, BinReader};
+
+const MAGIC: &[u8; 8] = b"HKZMESH\0";
+const VERSION: u32 = 3;
+const FLAG_BVH: u32 = 1;
+
+/// Identifies the contents of a source file. Size and modification time are
+/// compared first, the hash only when the time changed but the size didn't
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceStamp {
+    pub size: u64,
+    // nanoseconds since the unix epoch, 0 when the platform doesn't report it
+    pub modified: u64,
+    pub hash: u64,
+}
+
+/// 64 bit FNV-1a
+fn hash_bytes(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in data.iter() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+fn modified_time(path: &Path) -> Result<u64, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64))
+}
+
+impl SourceStamp {
+    pub fn from_data(data: &[u8], modified: u64) -> SourceStamp {
+        SourceStamp {
+            size: data.len() as u64,
+            modified,
+            hash: hash_bytes(data),
+        }
+    }
+
+    pub fn from_file(filename: &str) -> Result<SourceStamp, String> {
+        let modified = modified_time(Path::new(filename))?;
+        let data = fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        Ok(SourceStamp::from_data(&data, modified))
+    }
+
+    /// Whether the file still has the contents this stamp was taken from
+    pub fn matches(&self, filename: &str) -> bool {
+        let path = Path::new(filename);
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        };
+        if size != self.size {
+            return false;
+        }
+        if self.modified != 0 && modified_time(path) == Ok(self.modified) {
+            return true;
+        }
+        fs::read(path).is_ok_and(|data| hash_bytes(&data) == self.hash)
+    }
+}
+
+fn write_faces(w: &mut BinWriter, faces: &[FaceStruct]) {
+    w.u64(faces.len() as u64);
+    for face in faces.iter() {
+        w.u64(face.points.len() as u64);
+        for p in face.points.iter() {
+            w.u32(p.vertex_index as u32);
+            w.u32(p.texture_index as u32);
+            w.u32(p.normal_index as u32);
+        }
+    }
+}
+
+/// Serializes `mesh` along with the stamp of the file it was loaded from.
+/// The BVH is built first when `with_bvh` is set
+pub fn write_mesh(mesh: &Mesh, source: &SourceStamp, with_bvh: bool) -> Vec<u8> {
+    let mut w = BinWriter::new();
+    w.data.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+    w.u32(if with_bvh { FLAG_BVH } else { 0 });
+    w.u64(source.size);
+    w.u64(source.modified);
+    w.u64(source.hash);
+
+    w.vector3s(&mesh.vertices);
+    w.vector2s(&mesh.textures);
+    w.vector3s(&mesh.normals);
+    w.vector3s(&mesh.colors);
+    w.vector3s(&mesh.tangents);
+    w.u64(mesh.uv_sets.len() as u64);
+    for set in mesh.uv_sets.iter() {
+        w.vector2s(set);
+    }
+    write_faces(&mut w, &mesh.faces);
+    write_faces(&mut w, &mesh.polygons);
+    w.u64(mesh.face_polygons.len() as u64);
+    mesh.face_polygons.iter().for_each(|&i| w.u64(i as u64));
+    if with_bvh {
+        mesh.bvh().write_cache(&mut w);
+    }
+
+    w.data
+}
+
+fn read_header(r: &mut BinReader) -> Result<(SourceStamp, u32), String> {
+    if r.bytes::<8>()? != *MAGIC {
+        return Err(String::from("not a mesh cache"));
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(format!("mesh cache version {} is not supported", version));
+    }
+    let flags = r.u32()?;
+    let stamp = SourceStamp {
+        size: r.u64()?,
+        modified: r.u64()?,
+        hash: r.u64()?,
+    };
+
+    Ok((stamp, flags))
+}
+
+fn read_faces(r: &mut BinReader, mesh: &Mesh) -> Result<Vec<FaceStruct>, String> {
+    let index = |value: u32, len: usize, what: &str| -> Result<i32, String> {
+        if value as usize > len {
+            return Err(format!("mesh cache refers to missing {} {}", what, value));
+        }
+        Ok(value as i32)
+    };
+    let face_count = r.len(8)?;
+    let mut faces = Vec::with_capacity(face_count);
+    for _ in 0..face_count {
+        let count = r.len(12)?;
+        if count < 3 {
+            return Err(String::from("mesh cache face with less than three points"));
+        }
+        let mut points = Vec::with_capacity(count);
+        for _ in 0..count {
+            let vertex = index(r.u32()?, mesh.vertices.len(), "vertex")?;
+            let texture = index(r.u32()?, mesh.textures.len(), "texture coordinate")?;
+            let normal = index(r.u32()?, mesh.normals.len(), "normal")?;
+            if vertex == 0 {
+                return Err(String::from("mesh cache face without vertex"));
+            }
+            points.push(PointStruct::new(vertex, texture, normal));
+        }
+        faces.push(FaceStruct {
+            points,
+        });
+    }
+
+    Ok(faces)
+}
+
+/// Reads the header of a cache file only
+pub fn read_stamp(data: &[u8]) -> Result<SourceStamp, String> {
+    Ok(read_header(&mut BinReader::new(data))?.0)
+}
+
+/// Inverse of `write_mesh`. Indices are checked, so a damaged cache is an
+/// error rather than a panic during rendering
+pub fn read_mesh(data: &[u8]) -> Result<(SourceStamp, Mesh), String> {
+    let mut r = BinReader::new(data);
+    let (stamp, flags) = read_header(&mut r)?;
+
+    let mut mesh = Mesh::new();
+    mesh.vertices = r.vector3s()?;
+    mesh.textures = r.vector2s()?;
+    mesh.normals = r.vector3s()?;
+    mesh.colors = r.vector3s()?;
+    mesh.tangents = r.vector3s()?;
+    let sets = r.len(8)?;
+    for _ in 0..sets {
+        mesh.uv_sets.push(r.vector2s()?);
+    }
+    // per vertex attributes are indexed by vertex, colors and tangents may be absent
+    let vertices = mesh.vertices.len();
+    if mesh.uv_sets.iter().any(|set| set.len() != vertices)
+        || ![0, vertices].contains(&mesh.colors.len())
+        || ![0, vertices].contains(&mesh.tangents.len()) {
+        return Err(String::from("mesh cache vertex attributes don't match the vertices"));
+    }
+
+    mesh.faces = read_faces(&mut r, &mesh)?;
+    mesh.polygons = read_faces(&mut r, &mesh)?;
+    let count = r.len(8)?;
+    for _ in 0..count {
+        let polygon = r.u64()? as usize;
+        if polygon >= mesh.polygons.len() {
+            return Err(format!("mesh cache refers to missing polygon {}", polygon));
+        }
+        mesh.face_polygons.push(polygon);
+    }
+    if count != 0 && count != mesh.faces.len() {
+        return Err(String::from("mesh cache polygon indices don't match the faces"));
+    }
+    if flags & FLAG_BVH != 0 {
+        let bvh = Bvh::read_cache(&mut r, mesh.faces.len())?;
+        mesh.set_bvh(bvh);
+    }
+
+    Ok((stamp, mesh))
+}
+
+/// Loads meshes through a binary copy written next to the source, as
+/// `<source>.hkzmesh`. The copy is used while the source is unchanged and
+/// rewritten otherwise
+pub struct MeshCache {
+    // store the built BVH as well, saves building it on load
+    pub with_bvh: bool,
+}
+
+impl Default for MeshCache {
+    fn default() -> Self {
+        MeshCache::new()
+    }
+}
+
+impl MeshCache {
+    pub fn new() -> MeshCache {
+        MeshCache {
+            with_bvh: true,
+        }
+    }
+
+    pub fn set_with_bvh(&mut self, value: bool) -> &mut Self {
+        self.with_bvh = value;
+
+        self
+    }
+
+    pub fn cache_path(filename: &str) -> PathBuf {
+        let mut path = filename.to_string();
+        path.push_str(".hkzmesh");
+        PathBuf::from(path)
+    }
+
+    /// Reads the cache of `filename` when it is still valid, otherwise parses
+    /// the source with `Mesh::load` and writes the cache. Failing to write it,
+    /// as in a read only directory, is not an error
+    pub fn load(&self, filename: &str) -> Result<Mesh, String> {
+        let cache_path = MeshCache::cache_path(filename);
+        if let Ok(data) = fs::read(&cache_path) {
+            if let Ok((stamp, mesh)) = read_mesh(&data) {
+                if stamp.matches(filename) {
+                    return Ok(mesh);
+                }
+            }
+        }
+
+        let modified = modified_time(Path::new(filename))?;
+        let data = fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let stamp = SourceStamp::from_data(&data, modified);
+        let mesh = Mesh::load(filename)?;
+        let _ = fs::write(&cache_path, write_mesh(&mesh, &stamp, self.with_bvh));
+
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod mesh_cache_test {
+    use super::{MeshCache, read_mesh, write_mesh, SourceStamp, VERSION};
+    use crate::ray::Ray;
+    use cgmath::{Vector2, Vector3};
+
+    #[test]
+    fn test_round_trip_and_invalidation() {
+        let dir = std::env::temp_dir().join(format!("hakaze_mesh_cache_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("quad.obj");
+        let filename = source.to_str().unwrap();
+        std::fs::write(&source, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1 4/1/1\n").unwrap();
+        let cache_path = MeshCache::cache_path(filename);
+        let _ = std::fs::remove_file(&cache_path);
+
+        let mesh = MeshCache::new().load(filename).unwrap();
+        assert!(cache_path.exists());
+        let data = std::fs::read(&cache_path).unwrap();
+        let (stamp, cached) = read_mesh(&data).unwrap();
+        assert_eq!(stamp, SourceStamp::from_file(filename).unwrap());
+        assert_eq!(cached.vertices, mesh.vertices);
+        assert_eq!(cached.faces.len(), 2);
+        let ray = Ray::new(Vector3::new(0.25, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(cached.intersect(&ray).dis, mesh.intersect(&ray).dis);
+
+        // a truncated cache is rejected, not half read
+        assert!(read_mesh(&data[..data.len() - 1]).is_err());
+        assert!(read_mesh(&write_mesh(&mesh, &stamp, false)).is_ok());
+
+        // editing the source makes the cache stale
+        std::fs::write(&source, "v 0 0 0\nv 2 0 0\nv 0 2 0\nf 1 2 3\n").unwrap();
+        let mesh = MeshCache::new().load(filename).unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        let (_, cached) = read_mesh(&std::fs::read(&cache_path).unwrap()).unwrap();
+        assert_eq!(cached.vertices[1], Vector3::new(2.0, 0.0, 0.0));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bad_cache_falls_back_to_source() {
+        let dir = std::env::temp_dir().join(format!("hakaze_mesh_cache_fallback_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("triangle.obj");
+        let filename = source.to_str().unwrap();
+        std::fs::write(&source, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let cache_path = MeshCache::cache_path(filename);
+        let _ = std::fs::remove_file(&cache_path);
+        MeshCache::new().load(filename).unwrap();
+        let good = std::fs::read(&cache_path).unwrap();
+
+        // a valid cache of another mesh, stamped for another file
+        let other = crate::object::Mesh::from_obj_str("v 0 0 0\nv 5 0 0\nv 0 5 0\nv 5 5 0\nf 1 2 3\nf 2 4 3\n");
+        let other_stamp = SourceStamp::from_data(b"something else", 1);
+        let mut newer = good.clone();
+        newer[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        // a uv set shorter than the vertices, written with the right stamp
+        let mut short = crate::object::Mesh::from_obj_str("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        short.add_uv_set(vec![Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0)]);
+        let short = write_mesh(&short, &SourceStamp::from_file(filename).unwrap(), true);
+        assert!(read_mesh(&short).is_err());
+        let broken = [
+            good[..good.len() / 2].to_vec(),
+            write_mesh(&other, &other_stamp, true),
+            newer,
+            short,
+        ];
+
+        for cache in broken.iter() {
+            std::fs::write(&cache_path, cache).unwrap();
+            let mesh = MeshCache::new().load(filename).unwrap();
+            assert_eq!(mesh.faces.len(), 1);
+            assert_eq!(mesh.vertices[1], Vector3::new(1.0, 0.0, 0.0));
+            // and the cache is written again
+            assert_eq!(std::fs::read(&cache_path).unwrap(), good);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+use super::mesh::Mesh;
+
+impl Mesh {
+    fn face_normal(&self, index: usize) -> Vector3<f64> {
+        let p = &self.faces[index].points;
+        let a = self.vertices[p[0].vertex_index as usize - 1];
+        let b = self.vertices[p[1].vertex_index as usize - 1];
+        let c = self.vertices[p[2].vertex_index as usize - 1];
+        let n = (b - a).cross(c - a);
+
+        if n.magnitude2() > 0.0 { n.normalize() } else { n }
+    }
+
+    /// Angle of the face at its corner `k`
+    fn corner_angle(&self, index: usize, k: usize) -> f64 {
+        let p = &self.faces[index].points;
+        let v = |i: usize| self.vertices[p[i % 3].vertex_index as usize - 1];
+        let (e1, e2) = (v(k + 1) - v(k), v(k + 2) - v(k));
+        if e1.magnitude2() == 0.0 || e2.magnitude2() == 0.0 {
+            return 0.0;
+        }
+
+        e1.angle(e2).0
+    }
+
+    /// The polygons keep no normals once they are recomputed for the triangles
+    fn clear_polygon_normals(&mut self) {
+        self.polygons.iter_mut()
+            .flat_map(|f| f.points.iter_mut())
+            .for_each(|p| p.normal_index = 0);
+    }
+
+    /// One normal per face, replacing any normals the mesh had
+    pub fn compute_flat_normals(&mut self) {
+        self.clear_polygon_normals();
+        self.normals = (0..self.faces.len()).map(|i| self.face_normal(i)).collect();
+        for (i, face) in self.faces.iter_mut().enumerate() {
+            face.points.iter_mut().for_each(|p| p.normal_index = i as i32 + 1);
+        }
+    }
+
+    /// Smooth normals weighted by the angle of each face at the vertex.
+    /// Faces meeting at more than `crease_angle` (radians) keep a hard edge:
+    /// a corner only averages the faces around its vertex whose normals are
+    /// within the crease angle of its own face. Replaces any normals the mesh had
+    pub fn compute_smooth_normals(&mut self, crease_angle: f64) {
+        self.clear_polygon_normals();
+        let face_normals: Vec<Vector3<f64>> = (0..self.faces.len()).map(|i| self.face_normal(i)).collect();
+        let mut corners: Vec<Vec<(usize, f64)>> = vec![Vec::new(); self.vertices.len()];
+        for (i, face) in self.faces.iter().enumerate() {
+            for (k, p) in face.points.iter().enumerate() {
+                corners[p.vertex_index as usize - 1].push((i, self.corner_angle(i, k)));
+            }
+        }
+
+        let cos_crease = crease_angle.cos();
+        let mut normals = Vec::new();
+        // corners ending up with the same normal share it
+        let mut shared: HashMap<[u64; 3], i32> = HashMap::new();
+        for i in 0..self.faces.len() {
+            for k in 0..3 {
+                let vertex = self.faces[i].points[k].vertex_index;
+                let n: Vector3<f64> = corners[vertex as usize - 1].iter()
+                    .filter(|&&(j, _)| face_normals[j].dot(face_normals[i]) >= cos_crease - 1e-9)
+                    .map(|&(j, angle)| face_normals[j] * angle)
+                    .sum();
+                // faces with no area fall back to the geometric normal in `face`
+                let index = if n.magnitude2() > 0.0 {
+                    let n = n.normalize();
+                    *shared.entry([n.x.to_bits(), n.y.to_bits(), n.z.to_bits()]).or_insert_with(|| {
+                        normals.push(n);
+                        normals.len() as i32
+                    })
+                } else {
+                    0
+                };
+                self.faces[i].points[k].normal_index = index;
+            }
+        }
+        self.normals = normals;
+    }
+
+    /// Merges vertices closer than `tolerance`, then drops vertices no face
+    /// uses. Merged vertices keep the colors, tangents and uv sets of the
+    /// first of them. Returns the number of vertices removed
+    pub fn weld_vertices(&mut self, tolerance: f64) -> usize {
+        let count = self.vertices.len();
+        // vertices are hashed into cells of the tolerance size, a match can
+        // only be in the same or a neighbouring cell
+        let cell_size = tolerance.max(1e-12);
+        let cell = |v: Vector3<f64>| [
+            (v.x / cell_size).floor() as i64,
+            (v.y / cell_size).floor() as i64,
+            (v.z / cell_size).floor() as i64,
+        ];
+        let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut representative = vec![0; count];
+        for (i, &v) in self.vertices.iter().enumerate() {
+            let c = cell(v);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbours = match grid.get(&[c[0] + dx, c[1] + dy, c[2] + dz]) {
+                            Some(neighbours) => neighbours,
+                            None => continue,
+                        };
+                        if let Some(&j) = neighbours.iter().find(|&&j| (self.vertices[j] - v).magnitude() <= tolerance) {
+                            found = Some(j);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+            representative[i] = match found {
+                Some(j) => j,
+                None => {
+                    grid.entry(c).or_default().push(i);
+                    i
+                },
+            };
+        }
+
+        self.merge_vertices(&representative);
+
+        count - self.vertices.len()
+    }
+
+    /// Moves every corner on vertex i to `representative[i]`, then drops the
+    /// vertices no face uses. Per vertex attributes follow the vertices kept
+    pub(crate) fn merge_vertices(&mut self, representative: &[usize]) {
+        let count = self.vertices.len();
+        // new indices for the representatives still used, in their old order
+        let mut used = vec![false; count];
+        for face in self.faces.iter().chain(self.polygons.iter()) {
+            face.points.iter().for_each(|p| used[representative[p.vertex_index as usize - 1]] = true);
+        }
+        let mut new_index = vec![0; count];
+        let mut kept = Vec::new();
+        for i in 0..count {
+            if used[i] {
+                kept.push(i);
+                new_index[i] = kept.len() as i32;
+            }
+        }
+        for face in self.faces.iter_mut().chain(self.polygons.iter_mut()) {
+            for p in face.points.iter_mut() {
+                p.vertex_index = new_index[representative[p.vertex_index as usize - 1]];
+            }
+        }
+
+        let select = |values: &[Vector3<f64>]| -> Vec<Vector3<f64>> {
+            if values.len() == count { kept.iter().map(|&i| values[i]).collect() } else { Vec::new() }
+        };
+        self.vertices = select(&self.vertices);
+        self.colors = select(&self.colors);
+        self.tangents = select(&self.tangents);
+        self.uv_sets = self.uv_sets.iter()
+            .filter(|set| set.len() == count)
+            .map(|set| kept.iter().map(|&i| set[i]).collect())
+            .collect();
+        self.recompute_bounds();
+    }
+
+    /// Drops faces over the same three vertices as an earlier face, whatever
+    /// their winding. Returns the number of faces removed
+    pub fn remove_duplicate_faces(&mut self) -> usize {
+        let mut seen = HashSet::new();
+        let keep: Vec<bool> = self.faces.iter().map(|face| {
+            let mut key: Vec<i32> = face.points.iter().map(|p| p.vertex_index).collect();
+            key.sort_unstable();
+            seen.insert(key)
+        }).collect();
+
+        self.retain_faces(&keep)
+    }
+}
+
+#[cfg(test)]
+mod mesh_processing_test {
+    use crate::object::{Mesh, Object};
+    use std::rc::Rc;
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    // two sides of a unit cube meeting along the y axis, each with its own
+    // copies of the shared vertices, plus a repeated triangle
+    fn corner() -> Mesh {
+        let mut obj = String::new();
+        let quads = [
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 1.0], [0.0, 0.0, 1.0]],
+        ];
+        for (q, quad) in quads.iter().enumerate() {
+            for v in quad.iter() {
+                obj.push_str(&format!("v {} {} {}\n", v[0], v[1], v[2]));
+            }
+            let i = q * 4;
+            obj.push_str(&format!("f {} {} {}\nf {} {} {}\n", i + 1, i + 2, i + 3, i + 1, i + 3, i + 4));
+        }
+        obj.push_str("f 3 2 1\n");
+
+        Mesh::from_obj_str(&obj)
+    }
+
+    #[test]
+    fn test_weld_and_clean() {
+        let mut mesh = corner();
+        let stats = mesh.stats();
+        assert_eq!(stats.duplicate_faces, 1);
+        assert_eq!(stats.boundary_edges, 6);
+        assert_eq!(stats.non_manifold_edges, 1);
+
+        mesh.vertices[4] += Vector3::new(1e-7, 0.0, 0.0);
+        assert_eq!(mesh.weld_vertices(1e-6), 2);
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.remove_duplicate_faces(), 1);
+        assert_eq!(mesh.remove_degenerate_faces(), 0);
+
+        let stats = mesh.stats();
+        assert_eq!(stats.faces, 4);
+        assert_eq!(stats.boundary_edges, 6);
+        assert_eq!(stats.non_manifold_edges, 0);
+        assert_eq!(stats.bounds.max, Vector3::new(1.0, 1.0, 1.0));
+
+        // refreshing the bounds of an instance doesn't copy a shared mesh
+        let shared = Rc::new(mesh);
+        let mut instance = Object::from_mesh(shared.clone());
+        instance.recompute_bounds();
+        assert!(Rc::ptr_eq(&instance.mesh, &shared));
+    }
+
+    #[test]
+    fn test_normals_with_crease() {
+        let mut mesh = corner();
+        mesh.remove_duplicate_faces();
+        mesh.weld_vertices(1e-6);
+
+        // the faces meet at 90 degrees, a 60 degree crease keeps the edge hard
+        mesh.compute_smooth_normals(60f64.to_radians());
+        assert_eq!(mesh.normals.len(), 2);
+        assert_eq!(mesh.face(0).points[0].normal, Vector3::new(0.0, 0.0, 1.0));
+
+        mesh.compute_smooth_normals(120f64.to_radians());
+        let shared = mesh.face(0).points[0].normal;
+        assert!((shared - Vector3::new(1.0, 0.0, 1.0).normalize()).magnitude() < 1e-12);
+        // away from the edge the normals stay those of the faces
+        assert_eq!(mesh.face(0).points[1].normal, Vector3::new(0.0, 0.0, 1.0));
+
+        mesh.compute_flat_normals();
+        assert_eq!(mesh.normals.len(), 4);
+        assert_eq!(mesh.face(2).points[0].normal, Vector3::new(1.0, 0.0, 0.0));
+    }
+}
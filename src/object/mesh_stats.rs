@@ -0,0 +1,72 @@
+use std::collections::{HashMap, HashSet};
+
+use super::mesh::Mesh;
+use crate::accel::Aabb;
+
+/// Counts describing the state of a mesh, from `Mesh::stats`. Edges are
+/// identified by their vertex indices, so meshes should be welded first
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshStats {
+    pub vertices: usize,
+    pub normals: usize,
+    pub textures: usize,
+    pub faces: usize,
+    // polygons of the source, 0 when the loader only kept triangles
+    pub polygons: usize,
+
+    pub degenerate_faces: usize,
+    pub duplicate_faces: usize,
+    // vertices no face refers to
+    pub unreferenced_vertices: usize,
+    // edges of a single face, where the surface is open
+    pub boundary_edges: usize,
+    // edges shared by more than two faces
+    pub non_manifold_edges: usize,
+
+    pub bounds: Aabb,
+}
+
+impl MeshStats {
+    /// Whether the mesh encloses a volume, needed for meshes holding a medium
+    pub fn is_closed(&self) -> bool {
+        self.faces > 0 && self.boundary_edges == 0 && self.non_manifold_edges == 0
+    }
+}
+
+impl Mesh {
+    pub fn stats(&self) -> MeshStats {
+        let mut edges: HashMap<(i32, i32), usize> = HashMap::new();
+        let mut faces = HashSet::new();
+        let mut referenced = vec![false; self.vertices.len()];
+        let mut duplicate_faces = 0;
+        for face in self.faces.iter() {
+            let indices: Vec<i32> = face.points.iter().map(|p| p.vertex_index).collect();
+            for k in 0..indices.len() {
+                let (a, b) = (indices[k], indices[(k + 1) % indices.len()]);
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                referenced[a as usize - 1] = true;
+            }
+            let mut key = indices;
+            key.sort_unstable();
+            if !faces.insert(key) {
+                duplicate_faces += 1;
+            }
+        }
+
+        MeshStats {
+            vertices: self.vertices.len(),
+            normals: self.normals.len(),
+            textures: self.textures.len(),
+            faces: self.faces.len(),
+            polygons: self.polygons.len(),
+
+            degenerate_faces: self.faces.iter().filter(|f| self.is_degenerate(f)).count(),
+            duplicate_faces,
+            unreferenced_vertices: referenced.iter().filter(|&&r| !r).count(),
+            boundary_edges: edges.values().filter(|&&n| n == 1).count(),
+            non_manifold_edges: edges.values().filter(|&&n| n > 2).count(),
+
+            bounds: Aabb::from_points(self.faces.iter().flat_map(|f| f.points.iter()).map(|p| &self.vertices[p.vertex_index as usize - 1])),
+        }
+    }
+}
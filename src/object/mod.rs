@@ -1,5 +1,24 @@
+#[allow(clippy::module_inception)]
 pub mod object;
+pub mod mesh;
+pub mod primitive;
+pub mod ply;
+pub mod stl;
+pub mod mesh_cache;
+pub mod mesh_processing;
+pub mod mesh_stats;
+pub mod subdivision;
+pub mod simplification;
+pub mod lod;
+pub mod triangulate;
 
 pub use object::Object;
 pub use object::IntersectResult;
-pub use object::IntersectDirection;
\ No newline at end of file
+pub use object::IntersectDirection;
+pub use mesh::Mesh;
+pub use mesh_cache::MeshCache;
+pub use mesh_stats::MeshStats;
+pub use subdivision::{Subdivision, SubdivisionScheme};
+pub use simplification::Simplification;
+pub use lod::Lod;
+pub use primitive::Primitive;
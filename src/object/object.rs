@@ -1,32 +1,21 @@
-use std::fs;
+use std::rc::Rc;
 
 use cgmath::prelude::*;
-use cgmath::{Vector3, Vector2, Matrix4, Vector4, Rad, Matrix2, Matrix3};
-use std::convert::Infallible;
-use std::cmp::Ordering;
-
-use crate::ray::ray::Ray;
+use cgmath::{Vector2, Vector3, Matrix4, Quaternion};
+
+use super::mesh::{Mesh, Point};
+use super::mesh_cache::MeshCache;
+use super::mesh_stats::MeshStats;
+use super::subdivision::Subdivision;
+use super::simplification::Simplification;
+use super::lod::Lod;
+use super::primitive::Primitive;
+use crate::ray::{Ray, offset_ray_origin};
+use crate::accel::Aabb;
+use crate::transform::{Transform, AnimatedTransform};
 use crate::material::{Material, NaiveMaterial};
-
-#[derive(Clone, Debug)]
-pub struct PointStruct {
-    pub vertex_index: i32,
-    pub texture_index: i32,
-    pub normal_index: i32,
-}
-
-#[derive(Debug, Clone)]
-pub struct Point {
-    pub vertex: Vector3<f64>,
-    pub texture: Vector2<f64>,
-    pub normal: Vector3<f64>,
-}
-
-// #[derive(Debug)]
-pub struct Face3<'a> {
-    pub points: [Point; 3],
-    pub object: &'a Object,
-}
+use crate::medium::HomogeneousMedium;
+use crate::camera::Camera;
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum IntersectDirection {
@@ -40,7 +29,15 @@ pub struct IntersectResult<'a> {
     pub direction: IntersectDirection,
     pub is_intersect: bool,
     pub dis: f64,
-    pub object: Option<&'a Object>,
+    pub object: Option<&'a dyn Primitive>,
+    // bound on the absolute rounding error of `point.vertex`
+    pub error: Vector3<f64>,
+    // normal of the actual surface, the shading normal in `point` may be interpolated
+    pub geometric_normal: Vector3<f64>,
+    // weights of the triangle corners at the hit, zero for other primitives
+    pub barycentric: Vector3<f64>,
+    // extra texture coordinate sets of the mesh at the hit
+    pub uv_sets: Vec<Vector2<f64>>,
 }
 
 impl<'a> IntersectResult<'a> {
@@ -51,389 +48,368 @@ impl<'a> IntersectResult<'a> {
             is_intersect: false,
             dis: 0.0,
             object: None,
+            error: Vector3::zero(),
+            geometric_normal: Vector3::zero(),
+            barycentric: Vector3::zero(),
+            uv_sets: Vec::new(),
         }
     }
-}
 
-impl<'a> Face3<'a> {
-    fn calc_xy(&self, point: &Vector3<f64>) -> (f64, f64) {
-        let p1 = &self.points[0];
-        let p2 = &self.points[1];
-        let p3 = &self.points[2];
-
-        let ab = p2.vertex - p1.vertex;
-        let ac = p3.vertex - p1.vertex;
-        let ap = point - p1.vertex;
-
-        let mat = Matrix2::new(
-            ab.x, ab.y,
-            ac.x,ac.y
-        ).invert().unwrap();
-        let result = mat * Vector2::new(ap.x, ap.y);
-
-        (result.x, result.y)
-    }
-
-    fn interpolate(&self, p: &Vector3<f64>) -> Vector2<f64> {
-        let a = &self.points[0].vertex;
-        let b = &self.points[1].vertex;
-        let c = &self.points[2].vertex;
-
-        let ab = b - a;
-        let ac = c - a;
-        let z = ac.cross(ab);
-
-        let t = Matrix3::new(
-            ab.x, ab.y, ab.z,
-            ac.x, ac.y, ac.z,
-            z.x, z.y, z.z,
-        ).invert().unwrap();
-
-        let new_coord = t * (p - a);
-        let w2 = new_coord.x;
-        let w3 = new_coord.y;
-        let w1 = 1.0 - w2 - w3;
-        //
-        // let pa = p1 - point;
-        // let pb = p2 - point;
-        // let pc = p3 - point;
-        //
-        // let da = p1.distance(*point);
-        // let db = p2.distance(*point);
-        // let dc = p3.distance(*point);
-        //
-        // let sa = 0.5 * db * dc * (1.0 - (pb.dot(pc).powf(2.0))).sqrt();
-        // let sb = 0.5 * da * dc * (1.0 - (pa.dot(pc).powf(2.0))).sqrt();
-        // let sc = 0.5 * da * db * (1.0 - (pa.dot(pb).powf(2.0))).sqrt();
-        //
-        // let w1 = sa / (sa + sb + sc);
-        // let w2 = sb / (sa + sb + sc);
-        // let w3 = sc / (sa + sb + sc);
-
-        // let w1 = ((p2.y - p3.y) * (point.x - p3.x) + (p3.x - p2.x) * (point.y - p2.x) * (point.y - p3.y))
-        //     / ((p2.y - p3.y) * (p1.x - p3.x) + (p3.x - p2.x) * (p1.y - p3.y));
-        // let w2 = ((p3.y - p1.y) * (point.x - p3.x) + (p1.x - p3.x) * (point.y - p3.y))
-        //     / ((p2.y - p3.y) * (p1.x - p3.x) + (p3.x - p2.x) * (p1.y - p3.y));
-        // let w3 = 1.0 - w1 - w2;
-        // println!("{:?}, {:?}, {:?}", w1, w2, w3);
-
-        w1 * self.points[0].texture + w2 * self.points[1].texture + w3 * self.points[2].texture
-    }
-
-    pub fn intersect(&self, ray: &Ray) -> IntersectResult<'a> {
-        let p1 = &self.points[0];
-        let p2 = &self.points[1];
-        let p3 = &self.points[2];
-
-        let t1 = p1.vertex - p3.vertex;
-        let t2 = p2.vertex - p3.vertex;
-        let normal = t1.cross(t2);
-
-        let normal_x_d = normal.dot(ray.dir.clone());
-        if normal_x_d.abs() < 1e-6 {
-            // parallel
-            return IntersectResult::no_intersect();
-        }
-
-        let d = -normal.dot(p1.vertex.clone());
-
-        let t = -(normal.dot(ray.pos.clone()) + d) / (normal.dot(ray.dir.clone()));
-        if t < 1e-6 {
-            return IntersectResult::no_intersect();
-        }
-        let new_point = ray.pos + t * ray.dir;
-
-        // check if new point is in the triangle
-        let mut flag = (p2.vertex - p1.vertex).cross(new_point - p2.vertex).dot(normal) > 0.0;
-        flag = flag && (p3.vertex - p2.vertex).cross(new_point - p3.vertex).dot(normal) > 0.0;
-        flag = flag && (p1.vertex - p3.vertex).cross(new_point - p1.vertex).dot(normal) > 0.0;
-        if !flag {
-            return IntersectResult::no_intersect();
-        }
-
-        // let (x, y) = self.calc_xy(&new_point);
-        // let new_texture = -(x + y) * p1.texture + x * p2.texture + y * p3.texture;
-        // let new_texture = p1.texture.clone();
-        let new_texture = self.interpolate(&new_point);
-        // println!("{:?}", new_texture);
-        let new_normal = p1.normal.clone();
-
-        let direction = if ray.dir.dot(p1.normal.clone()) < 0.0 {
-            IntersectDirection::Positive
-        } else {
-            IntersectDirection::Negative
-        };
-        IntersectResult {
-            point: Some(Point {
-                vertex: new_point,
-                texture: new_texture,
-                normal: new_normal,
-            }),
-            direction,
-            is_intersect: true,
-            dis: t,
-            object: Some(&self.object),
-        }
+    /// Origin for a ray leaving the hit towards `dir`, pushed off the surface
+    /// by the error bound of the hit point, see `offset_ray_origin`
+    pub fn spawn_origin(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        let point = self.point.as_ref().unwrap();
+        offset_ray_origin(point.vertex, self.error, self.geometric_normal, dir)
     }
-}
-
-pub struct FaceIter<'a> {
-    next: usize,
-    object: &'a Object,
-    model_matrix: Matrix4<f64>,
-    normal_matrix: Matrix4<f64>,
-    // scale_factor: f64,
-}
 
-impl<'a> Iterator for FaceIter<'a> {
-    type Item = Face3<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.next == self.object.faces.len() {
-            return None;
-        }
-        let f = &self.object.faces[self.next];
-        self.next += 1;
-
-        let mut points: Vec<Point> = Vec::new();
-        for p in f.points.iter().take(3) {
-            let texture = &self.object.textures[p.texture_index as usize - 1];
-            let vertex = &self.object.vertices[p.vertex_index as usize - 1];
-            let new_vertex = Vector4::new(vertex[0], vertex[1], vertex[2], 1.0);
-            let new_vertex: Vector4<f64> = self.model_matrix * new_vertex;
-            let normal = &self.object.normals[p.normal_index as usize - 1];
-            let new_normal = Vector4::new(normal[0], normal[1], normal[2], 1.0);
-            let new_normal: Vector4<f64> = self.normal_matrix * new_normal;
-
-            points.push(Point {
-                vertex: Vector3::new(new_vertex[0], new_vertex[1], new_vertex[2]),
-                texture: texture.clone(),
-                normal: Vector3::new(new_normal[0], new_normal[1], new_normal[2]).normalize(),
-            });
+    /// Ray leaving the hit towards `dir`. The origin is already clear of the
+    /// surface, so the ray accepts hits from t = 0
+    pub fn spawn_ray(&self, dir: Vector3<f64>) -> Ray {
+        Ray {
+            t_min: 0.0,
+            ..Ray::new(self.spawn_origin(dir), dir)
         }
-
-        Some(Face3 {
-            points: [points[0].clone(), points[1].clone(), points[2].clone()],
-            object: &self.object,
-        })
     }
 }
 
-#[derive(Debug)]
-pub struct FaceStruct {
-    pub points: Vec<PointStruct>,
-}
-
-// #[derive(Debug)]
+/// An instance of a mesh placed in the scene with its own transform and
+/// material, many objects can share the same `Mesh`
 pub struct Object {
-    pub vertices: Vec<Vector3<f64>>,
-    pub textures: Vec<Vector2<f64>>,
-    pub normals: Vec<Vector3<f64>>,
-    pub faces: Vec<FaceStruct>,
+    pub mesh: Rc<Mesh>,
 
-    pub rotate: Vector3<f64>,
-    pub translate: Vector3<f64>,
-    pub scale: Vector3<f64>,
+    // model to world, including the transforms of the scene graph nodes above the object
+    pub transform: Transform,
+    // keyframed model to world transform, replaces `transform` when not empty
+    pub motion: AnimatedTransform,
 
     pub material: Box<dyn Material>,
+    // medium filling the inside of a closed mesh
+    pub interior: Option<HomogeneousMedium>,
+
+    // simplified versions of the mesh, most detailed first, `select_lod`
+    // makes one of them `mesh`
+    pub lods: Vec<Lod>,
 }
 
-impl PointStruct {
-    pub fn new(vertex: i32, texture: i32, normal: i32) -> PointStruct {
-        PointStruct {
-            vertex_index: vertex,
-            texture_index: texture,
-            normal_index: normal,
-        }
+impl Default for Object {
+    fn default() -> Self {
+        Object::new()
     }
 }
 
 impl Object {
     pub fn new() -> Object {
+        Object::from_mesh(Rc::new(Mesh::new()))
+    }
+
+    pub fn from_mesh(mesh: Rc<Mesh>) -> Object {
         Object {
-            vertices: Vec::new(),
-            textures: Vec::new(),
-            normals: Vec::new(),
-            faces: Vec::new(),
+            mesh,
 
-            rotate: Vector3::new(0.0, 0.0, 0.0),
-            translate: Vector3::new(0.0, 0.0, 0.0),
-            scale: Vector3::new(1.0, 1.0, 1.0),
+            transform: Transform::identity(),
+            motion: AnimatedTransform::new(),
 
             material: Box::new(NaiveMaterial::default()) as Box<dyn Material>,
+            interior: None,
+
+            lods: Vec::new(),
         }
     }
 
     pub fn get_model_matrix(&self) -> Matrix4<f64> {
-        let rot_x: Matrix4<f64> = Matrix4::from_angle_x(Rad(self.rotate[0]));
-        let rot_y: Matrix4<f64> = Matrix4::from_angle_y(Rad(self.rotate[1]));
-        let rot_z: Matrix4<f64> = Matrix4::from_angle_z(Rad(self.rotate[2]));
-        let translate: Matrix4<f64> = Matrix4::from_translation(self.translate.clone());
-        let scale: Matrix4<f64> = Matrix4::from_nonuniform_scale(self.scale[0], self.scale[1], self.scale[2]);
+        self.transform.matrix
+    }
 
-        // scale * rot_z * rot_y * rot_x * translate
-        translate * rot_x * rot_y * rot_z * scale
+    /// Model to world transform at `time`
+    pub fn transform_at(&self, time: f64) -> Transform {
+        if self.motion.is_empty() {
+            self.transform
+        } else {
+            self.motion.interpolate(time)
+        }
     }
 
-    pub fn get_rotate_matrix(&self) -> Matrix4<f64> {
-        let rot_x: Matrix4<f64> = Matrix4::from_angle_x(Rad(self.rotate[0]));
-        let rot_y: Matrix4<f64> = Matrix4::from_angle_y(Rad(self.rotate[1]));
-        let rot_z: Matrix4<f64> = Matrix4::from_angle_z(Rad(self.rotate[2]));
+    /// Maps a world space position at `time` back into the object's model space
+    pub fn world_to_object(&self, p: Vector3<f64>, time: f64) -> Vector3<f64> {
+        self.transform_at(time).inverse().transform_point(p)
+    }
 
-        rot_x * rot_y * rot_z
-        // rot_z * rot_y * rot_x
+    /// Adds a keyframe of the whole model to world transform, once an object
+    /// has keyframes its static `transform` is no longer used
+    pub fn add_keyframe(&mut self, time: f64, transform: Transform) -> &mut Object {
+        self.motion.add_keyframe(time, transform);
+
+        self
     }
 
-    pub fn scale_uniform(&mut self, value: f64) -> &mut Object {
-        self.scale[0] = value;
-        self.scale[1] = value;
-        self.scale[2] = value;
+    pub fn set_transform(&mut self, transform: Transform) -> &mut Object {
+        self.transform = transform;
 
         self
     }
 
-    pub fn scale(&mut self, x: f64, y: f64, z: f64) {
-        self.scale[0] = x;
-        self.scale[1] = y;
-        self.scale[2] = z;
+    /// Applies `transform` in world space, after the current transform
+    pub fn apply_transform(&mut self, transform: Transform) -> &mut Object {
+        self.transform = transform * self.transform;
+        self.motion.premultiply(transform);
+
+        self
     }
 
-    pub fn rotate_x(&mut self, value: f64) {
-        self.rotate[0] = value;
+    /// Applies `transform` in model space, before the current transform
+    pub fn apply_local_transform(&mut self, transform: Transform) -> &mut Object {
+        self.transform = self.transform * transform;
+        self.motion.postmultiply(transform);
+
+        self
+    }
+
+    // Scales and rotations accumulate in model space, so the object turns
+    // and grows around its own origin wherever it was moved to.
+    // Translations accumulate in world space.
+
+    pub fn scale_uniform(&mut self, value: f64) -> &mut Object {
+        self.apply_local_transform(Transform::uniform_scale(value))
+    }
+
+    pub fn scale(&mut self, x: f64, y: f64, z: f64) -> &mut Object {
+        self.apply_local_transform(Transform::scale(x, y, z))
     }
 
-    pub fn rotate_y(&mut self, value: f64) {
-        self.rotate[1] = value;
+    pub fn rotate_x(&mut self, value: f64) -> &mut Object {
+        self.apply_local_transform(Transform::rotate_x(value))
     }
 
-    pub fn rotate_z(&mut self, value: f64) {
-        self.rotate[2] = value;
+    pub fn rotate_y(&mut self, value: f64) -> &mut Object {
+        self.apply_local_transform(Transform::rotate_y(value))
     }
 
-    pub fn rotate(&mut self, x: f64, y: f64, z: f64) {
-        self.rotate[0] = x;
-        self.rotate[1] = y;
-        self.rotate[2] = z;
+    pub fn rotate_z(&mut self, value: f64) -> &mut Object {
+        self.apply_local_transform(Transform::rotate_z(value))
+    }
+
+    /// Euler angles applied in X, Y, Z order around the model's axes
+    pub fn rotate(&mut self, x: f64, y: f64, z: f64) -> &mut Object {
+        self.rotate_x(x).rotate_y(y).rotate_z(z)
+    }
+
+    pub fn rotate_axis(&mut self, axis: Vector3<f64>, angle: f64) -> &mut Object {
+        self.apply_local_transform(Transform::axis_angle(axis, angle))
+    }
+
+    pub fn rotate_quaternion(&mut self, q: Quaternion<f64>) -> &mut Object {
+        self.apply_local_transform(Transform::rotation(q))
     }
 
     pub fn translate_x(&mut self, value: f64) -> &mut Object {
-        self.translate[0] = value;
+        self.translate(value, 0.0, 0.0)
+    }
+
+    pub fn translate_y(&mut self, value: f64) -> &mut Object {
+        self.translate(0.0, value, 0.0)
+    }
+
+    pub fn translate_z(&mut self, value: f64) -> &mut Object {
+        self.translate(0.0, 0.0, value)
+    }
+
+    pub fn translate(&mut self, x: f64, y: f64, z: f64) -> &mut Object {
+        self.apply_transform(Transform::translation(Vector3::new(x, y, z)))
+    }
+
+    /// Replaces the transform with one placing the object at `eye`, its -z axis towards `target`
+    pub fn look_at(&mut self, eye: Vector3<f64>, target: Vector3<f64>, up: Vector3<f64>) -> &mut Object {
+        self.transform = Transform::look_at(eye, target, up);
 
         self
     }
 
-    pub fn translate_y(&mut self, value: f64) -> &mut Object {
-        self.translate[1] = value;
+    pub fn from_file(filename: &str) -> Object {
+        Object::from_mesh(Rc::new(Mesh::from_file(filename)))
+    }
+
+    pub fn from_ply(filename: &str) -> Result<Object, String> {
+        Ok(Object::from_mesh(Rc::new(Mesh::from_ply(filename)?)))
+    }
+
+    pub fn from_stl(filename: &str) -> Result<Object, String> {
+        Ok(Object::from_mesh(Rc::new(Mesh::from_stl(filename)?)))
+    }
+
+    /// Loads any mesh format known to `Mesh::load`
+    pub fn load(filename: &str) -> Result<Object, String> {
+        Ok(Object::from_mesh(Rc::new(Mesh::load(filename)?)))
+    }
+
+    /// Loads the mesh through its binary cache, see `MeshCache`
+    pub fn load_cached(filename: &str) -> Result<Object, String> {
+        Ok(Object::from_mesh(Rc::new(MeshCache::new().load(filename)?)))
+    }
+
+    /// Mesh of the object for editing. A mesh shared with other objects is
+    /// copied first, so the others keep the original. The edits below go
+    /// through here; reach an object already in a `Scene` through
+    /// `Scene::object_mut` so the scene's BVH picks up the new bounds
+    pub fn mesh_mut(&mut self) -> &mut Mesh {
+        Rc::make_mut(&mut self.mesh)
+    }
+
+    /// See `mesh_mut` for objects in a scene
+    pub fn flat_normals(&mut self) -> &mut Object {
+        self.mesh_mut().compute_flat_normals();
 
         self
     }
 
-    pub fn translate_z(&mut self, value: f64) -> &mut Object {
-        self.translate[2] = value;
+    /// See `Mesh::compute_smooth_normals`, the crease angle is in radians.
+    /// See `mesh_mut` for objects in a scene
+    pub fn smooth_normals(&mut self, crease_angle: f64) -> &mut Object {
+        self.mesh_mut().compute_smooth_normals(crease_angle);
 
         self
     }
 
-    pub fn translate(&mut self, x: f64, y: f64, z: f64) -> &mut Object {
-        self.translate[0] = x;
-        self.translate[1] = y;
-        self.translate[2] = z;
+    /// See `Mesh::weld_vertices` and `mesh_mut` for objects in a scene
+    pub fn weld_vertices(&mut self, tolerance: f64) -> &mut Object {
+        self.mesh_mut().weld_vertices(tolerance);
 
         self
     }
 
-    pub fn from_file(filename: &str) -> Object {
-        let contents = fs::read_to_string(filename).unwrap();
-
-        let mut object = Object::new();
-        for line in contents.lines() {
-            if line.starts_with("v ") {
-                let coords: Vec<f64> = line.split(" ")
-                    .skip(1)
-                    .map(|s| s.parse::<f64>().unwrap())
-                    .collect();
-                let v = Vector3::new(coords[0], coords[1], coords[2]);
-                object.add_vertex(v);
-            } else if line.starts_with("vt ") {
-                let coords: Vec<f64> = line.split(" ")
-                    .skip(1)
-                    .map(|s| s.parse::<f64>().unwrap())
-                    .collect();
-                let v = Vector2::new(coords[0], coords[1]);
-                object.add_texture(v);
-            } else if line.starts_with("vn ") {
-                let coords: Vec<f64> = line.split(" ")
-                    .skip(1)
-                    .map(|s| s.parse::<f64>().unwrap())
-                    .collect();
-                let v = Vector3::new(coords[0], coords[1], coords[2]);
-                object.add_normal(v);
-            } else if line.starts_with("f ") {
-                let points_str: Vec<&str> = line.split(" ")
-                    .skip(1)
-                    .collect();
-                let mut points: Vec<PointStruct> = Vec::new();
-                for &p in points_str.iter() {
-                    let values: Vec<i32> = p.split("/").map(|s| s.parse::<i32>().unwrap()).collect();
-                    points.push(PointStruct::new(values[0], values[1], values[2]));
-                }
-
-                // make triangles
-                for i in 1..points.len() - 1 {
-                    let p1 = points[0].clone();
-                    let p2 = points[i].clone();
-                    let p3 = points[i + 1].clone();
-                    let face = FaceStruct {
-                        points: vec![p1, p2, p3],
-                    };
-
-                    object.add_face(face);
-                }
-            }
+    /// Removes degenerate and duplicate triangles, see `mesh_mut` for
+    /// objects in a scene
+    pub fn clean(&mut self) -> &mut Object {
+        let mesh = self.mesh_mut();
+        mesh.remove_degenerate_faces();
+        mesh.remove_duplicate_faces();
+
+        self
+    }
+
+    /// Drops the mesh's BVH after its vertices were moved. A mesh shared with
+    /// other objects can't have been edited through this one and is left
+    /// alone rather than copied
+    pub fn recompute_bounds(&mut self) -> &mut Object {
+        if let Some(mesh) = Rc::get_mut(&mut self.mesh) {
+            mesh.recompute_bounds();
         }
 
-        object
+        self
+    }
+
+    /// Replaces the mesh by its subdivided copy, objects sharing the old
+    /// mesh keep it
+    pub fn subdivide(&mut self, subdivision: &Subdivision) -> &mut Object {
+        self.mesh = Rc::new(subdivision.apply(&self.mesh));
+
+        self
+    }
+
+    /// Replaces the mesh by its simplified copy, objects sharing the old
+    /// mesh keep it
+    pub fn simplify(&mut self, simplification: &Simplification) -> &mut Object {
+        self.mesh = Rc::new(simplification.apply(&self.mesh));
+
+        self
+    }
+
+    /// Adds a level of detail, kept sorted from the most detailed
+    pub fn add_lod(&mut self, mesh: Rc<Mesh>, min_pixels: f64) -> &mut Object {
+        self.lods.push(Lod::new(mesh, min_pixels));
+        self.lods.sort_by(|a, b| b.min_pixels.total_cmp(&a.min_pixels));
+
+        self
     }
 
-    pub fn add_vertex(&mut self, vertex: Vector3<f64>) {
-        self.vertices.push(vertex);
+    /// Replaces the levels of detail by the current mesh, used from
+    /// `full_pixels` up, and `count` simplified ones. Each level has a quarter
+    /// of the triangles of the one before and is used down to half its size
+    pub fn generate_lods(&mut self, count: usize, full_pixels: f64) -> &mut Object {
+        let mut mesh = self.mesh.clone();
+        self.lods = vec![Lod::new(mesh.clone(), full_pixels)];
+        for level in 1..=count {
+            let target = (mesh.faces.len() / 4).max(1);
+            mesh = Rc::new(Simplification::new().set_target_faces(target).apply(&mesh));
+            let min_pixels = if level == count { 0.0 } else { full_pixels / (1 << level) as f64 };
+            self.lods.push(Lod::new(mesh.clone(), min_pixels));
+        }
+
+        self
     }
 
-    pub fn add_texture(&mut self, texture: Vector2<f64>) {
-        self.textures.push(texture);
+    /// Makes `mesh` the first level of detail the object is big enough for,
+    /// judged by its bounding sphere placed as at `time`, e.g. the middle of
+    /// the shutter, seen from `camera` in an image `height` pixels high.
+    /// Returns the index of the level, None without levels
+    pub fn select_lod(&mut self, camera: &dyn Camera, height: usize, time: f64) -> Option<usize> {
+        let finest = self.lods.first()?;
+        let bounds = self.transform_at(time).transform_aabb(&finest.mesh.bounds());
+        let pixels = camera.projected_size(bounds.centroid(), bounds.diagonal().magnitude() / 2.0, height);
+        let index = self.lods.iter().position(|lod| pixels >= lod.min_pixels).unwrap_or(self.lods.len() - 1);
+        self.mesh = self.lods[index].mesh.clone();
+
+        Some(index)
     }
 
-    pub fn add_normal(&mut self, normal: Vector3<f64>) {
-        self.normals.push(normal);
+    pub fn mesh_stats(&self) -> MeshStats {
+        self.mesh.stats()
     }
 
-    pub fn add_face(&mut self, face: FaceStruct) {
-        self.faces.push(face);
+    /// Intersects the mesh in model space, `ray` is carried there without
+    /// renormalizing its direction so distances stay the same in both spaces
+    pub fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        let transform = self.transform_at(ray.time);
+        let local_ray = transform.inverse().transform_ray(ray);
+
+        let mut result = self.mesh.intersect(&local_ray);
+        if result.is_intersect {
+            self.hit_to_world(&transform, &mut result);
+        }
+
+        result
     }
 
-    pub fn faces_iter(&self) -> FaceIter {
-        FaceIter {
-            next: 0,
-            object: &self,
-            model_matrix: self.get_model_matrix(),
-            normal_matrix: self.get_rotate_matrix(),
+    /// Moves a model space hit into world space, the error bound grows by the
+    /// rounding of the transform
+    fn hit_to_world<'a>(&'a self, transform: &Transform, result: &mut IntersectResult<'a>) {
+        let point = result.point.as_mut().unwrap();
+        let (vertex, error) = transform.transform_point_with_error(point.vertex, result.error);
+        point.vertex = vertex;
+        point.normal = transform.transform_normal(point.normal).normalize();
+        if point.tangent != Vector3::zero() {
+            point.tangent = transform.transform_vector(point.tangent).normalize();
         }
+        result.error = error;
+        result.geometric_normal = transform.transform_normal(result.geometric_normal).normalize();
+        result.object = Some(self);
+    }
+
+    /// Whether the mesh is hit within the ray interval, see `Mesh::occluded`
+    pub fn occluded(&self, ray: &Ray) -> bool {
+        let local_ray = self.transform_at(ray.time).inverse().transform_ray(ray);
+        self.mesh.occluded(&local_ray)
     }
 
-    pub fn intersect(&self, ray: &Ray) -> IntersectResult {
-        let mut min_dis = f64::INFINITY;
-        let mut result: Option<IntersectResult> = None;
-        for face in self.faces_iter() {
-            let r = face.intersect(&ray);
-            if r.is_intersect {
-                if r.dis < min_dis {
-                    min_dis = r.dis;
-                    result = Some(r);
-                }
-            }
+    /// Every hit along `ray`, nearest first, see `Object::intersect`
+    pub fn intersect_all(&self, ray: &Ray) -> Vec<IntersectResult<'_>> {
+        let transform = self.transform_at(ray.time);
+        let local_ray = transform.inverse().transform_ray(ray);
+
+        let mut result = self.mesh.intersect_all(&local_ray);
+        for r in result.iter_mut() {
+            self.hit_to_world(&transform, r);
         }
 
-        result.unwrap_or(IntersectResult::no_intersect())
+        result
+    }
+
+    /// World space bounding box, covering the whole motion of animated objects
+    pub fn bounds(&self) -> Aabb {
+        if self.motion.is_empty() {
+            self.transform.transform_aabb(&self.mesh.bounds())
+        } else {
+            self.motion.motion_bounds(&self.mesh.bounds())
+        }
     }
 
     pub fn set_material(&mut self, material: Box<dyn Material>) -> &mut Object {
@@ -441,4 +417,37 @@ impl Object {
 
         self
     }
-}
\ No newline at end of file
+
+    pub fn set_interior(&mut self, medium: HomogeneousMedium) -> &mut Object {
+        self.interior = Some(medium);
+
+        self
+    }
+
+}
+
+impl Primitive for Object {
+    fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        Object::intersect(self, ray)
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        Object::occluded(self, ray)
+    }
+
+    fn bounds(&self) -> Aabb {
+        Object::bounds(self)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.as_ref()
+    }
+
+    fn interior(&self) -> Option<&HomogeneousMedium> {
+        self.interior.as_ref()
+    }
+
+    fn world_to_object(&self, p: Vector3<f64>, time: f64) -> Vector3<f64> {
+        Object::world_to_object(self, p, time)
+    }
+}
@@ -0,0 +1,319 @@
+use std::str::SplitAsciiWhitespace;
+
+use cgmath::{Vector2, Vector3};
+
+use super::mesh::{Mesh, FaceStruct, PointStruct};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<ScalarType, String> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(format!("unknown ply type `{}`", name)),
+        })
+    }
+
+    /// Scale bringing integer colors to [0, 1]
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::U8 => 1.0 / 255.0,
+            ScalarType::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PropertyType {
+    Scalar(ScalarType),
+    // count type, item type
+    List(ScalarType, ScalarType),
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Values of the body, either whitespace separated text or packed binary
+struct BodyReader<'a> {
+    format: Format,
+    data: &'a [u8],
+    pos: usize,
+    tokens: SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> BodyReader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self.data.get(self.pos..self.pos + N).ok_or("unexpected end of ply data")?;
+        self.pos += N;
+        let mut result = [0; N];
+        result.copy_from_slice(bytes);
+        Ok(result)
+    }
+
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.tokens.next().ok_or("unexpected end of ply data")?;
+            return token.parse::<f64>().map_err(|e| format!("bad ply value `{}`: {}", token, e));
+        }
+
+        let le = self.format == Format::BinaryLittleEndian;
+        macro_rules! read_as {
+            ($t:ty, $n:expr) => {{
+                let bytes = self.take::<$n>()?;
+                (if le { <$t>::from_le_bytes(bytes) } else { <$t>::from_be_bytes(bytes) }) as f64
+            }};
+        }
+        Ok(match ty {
+            ScalarType::I8 => read_as!(i8, 1),
+            ScalarType::U8 => read_as!(u8, 1),
+            ScalarType::I16 => read_as!(i16, 2),
+            ScalarType::U16 => read_as!(u16, 2),
+            ScalarType::I32 => read_as!(i32, 4),
+            ScalarType::U32 => read_as!(u32, 4),
+            ScalarType::F32 => read_as!(f32, 4),
+            ScalarType::F64 => read_as!(f64, 8),
+        })
+    }
+
+    fn read_list(&mut self, count_ty: ScalarType, item_ty: ScalarType) -> Result<Vec<f64>, String> {
+        let count = self.read(count_ty)? as usize;
+        (0..count).map(|_| self.read(item_ty)).collect()
+    }
+}
+
+fn parse_header(text: &str) -> Result<(Format, Vec<Element>), String> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in text.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| format!("bad ply element count `{}`", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => {
+                let element = elements.last_mut().ok_or("ply property outside of an element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: PropertyType::List(ScalarType::parse(count_ty)?, ScalarType::parse(item_ty)?),
+                });
+            },
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or("ply property outside of an element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: PropertyType::Scalar(ScalarType::parse(ty)?),
+                });
+            },
+            _ => {},
+        }
+    }
+
+    Ok((format.ok_or("ply header has no format")?, elements))
+}
+
+/// Reads an ASCII or binary PLY file. Vertices may carry normals, texture
+/// coordinates and colors, polygons are kept and split into triangles
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, String> {
+    if !data.starts_with(b"ply") {
+        return Err(String::from("not a ply file"));
+    }
+    let end = b"end_header";
+    let header_end = data.windows(end.len()).position(|w| w == end).ok_or("ply header is not terminated")?;
+    // the body starts after the line break following `end_header`
+    let mut body_start = header_end + end.len();
+    if data.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    if data.get(body_start) == Some(&b'\n') {
+        body_start += 1;
+    }
+
+    let header = std::str::from_utf8(&data[..header_end]).map_err(|_| "ply header is not text")?;
+    let (format, elements) = parse_header(header)?;
+    let body = &data[body_start..];
+    let text = if format == Format::Ascii {
+        std::str::from_utf8(body).map_err(|_| "ascii ply body is not text")?
+    } else {
+        ""
+    };
+    let mut reader = BodyReader {
+        format,
+        data: body,
+        pos: 0,
+        tokens: text.split_ascii_whitespace(),
+    };
+
+    let mut mesh = Mesh::new();
+    let mut normals = Vec::new();
+    let mut textures = Vec::new();
+    let mut colors = Vec::new();
+    let mut polygons: Vec<Vec<usize>> = Vec::new();
+    for element in elements.iter() {
+        for _ in 0..element.count {
+            let mut position = Vector3::new(0.0, 0.0, 0.0);
+            let mut normal = Vector3::new(0.0, 0.0, 0.0);
+            let mut uv = Vector2::new(0.0, 0.0);
+            let mut color = Vector3::new(1.0, 1.0, 1.0);
+            for property in element.properties.iter() {
+                match property.ty {
+                    PropertyType::Scalar(ty) => {
+                        let value = reader.read(ty)?;
+                        if element.name != "vertex" {
+                            continue;
+                        }
+                        match property.name.as_str() {
+                            "x" => position.x = value,
+                            "y" => position.y = value,
+                            "z" => position.z = value,
+                            "nx" => normal.x = value,
+                            "ny" => normal.y = value,
+                            "nz" => normal.z = value,
+                            "u" | "s" | "texture_u" => uv.x = value,
+                            "v" | "t" | "texture_v" => uv.y = value,
+                            "red" | "r" => color.x = value * ty.color_scale(),
+                            "green" | "g" => color.y = value * ty.color_scale(),
+                            "blue" | "b" => color.z = value * ty.color_scale(),
+                            _ => {},
+                        }
+                    },
+                    PropertyType::List(count_ty, item_ty) => {
+                        let values = reader.read_list(count_ty, item_ty)?;
+                        if element.name == "face" && (property.name == "vertex_indices" || property.name == "vertex_index") {
+                            polygons.push(values.into_iter().map(|v| v as usize).collect());
+                        }
+                    },
+                }
+            }
+
+            if element.name == "vertex" {
+                mesh.add_vertex(position);
+                normals.push(normal);
+                textures.push(uv);
+                colors.push(color);
+            }
+        }
+    }
+
+    let vertex = elements.iter().find(|e| e.name == "vertex");
+    let has = |names: &[&str]| vertex.is_some_and(|e| e.properties.iter().any(|p| names.contains(&p.name.as_str())));
+    let has_normals = has(&["nx"]);
+    let has_textures = has(&["u", "s", "texture_u"]);
+    if has_normals {
+        mesh.normals = normals;
+    }
+    if has_textures {
+        mesh.textures = textures;
+    }
+    if has(&["red", "r"]) {
+        mesh.colors = colors;
+    }
+
+    // normals and texture coordinates are indexed like the vertices
+    let point = |i: usize| {
+        let index = i as i32 + 1;
+        PointStruct::new(index, if has_textures { index } else { 0 }, if has_normals { index } else { 0 })
+    };
+    for polygon in polygons.iter() {
+        if let Some(&i) = polygon.iter().find(|&&i| i >= mesh.vertices.len()) {
+            return Err(format!("ply face refers to missing vertex {}", i));
+        }
+        if polygon.len() >= 3 {
+            mesh.add_polygon(FaceStruct {
+                points: polygon.iter().map(|&i| point(i)).collect(),
+            });
+        }
+    }
+    mesh.remove_degenerate_faces();
+
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod ply_test {
+    use super::parse_ply;
+    use cgmath::{InnerSpace, Vector3};
+
+    #[test]
+    fn test_ascii_quad() {
+        let data = "ply\nformat ascii 1.0\ncomment quad\nelement vertex 4\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 255 0 0\n1 1 0 0 0 255\n0 1 0 0 0 255\n4 0 1 2 3\n";
+        let mesh = parse_ply(data.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.colors[2], Vector3::new(0.0, 0.0, 1.0));
+        assert!(mesh.normals.is_empty());
+    }
+
+    #[test]
+    fn test_binary_with_normals() {
+        let mut data = b"ply\nformat binary_big_endian 1.0\nelement vertex 3\n\
+            property double x\nproperty double y\nproperty double z\n\
+            property float nx\nproperty float ny\nproperty float nz\n\
+            element face 1\nproperty list uchar uint vertex_index\nend_header\n".to_vec();
+        for p in [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]] {
+            for v in p.iter() {
+                data.extend_from_slice(&f64::to_be_bytes(*v));
+            }
+            for v in [0.0f32, 0.0, 1.0] {
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        data.push(3);
+        for i in 0u32..3 {
+            data.extend_from_slice(&i.to_be_bytes());
+        }
+        let mesh = parse_ply(&data).unwrap();
+
+        assert_eq!(mesh.vertices[1], Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(mesh.faces[0].points[2].normal_index, 3);
+        assert!((mesh.face(0).points[0].normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+
+        data.truncate(data.len() - 2);
+        assert!(parse_ply(&data).is_err());
+    }
+}
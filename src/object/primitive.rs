@@ -0,0 +1,33 @@
+use cgmath::Vector3;
+
+use super::object::IntersectResult;
+use crate::ray::Ray;
+use crate::accel::Aabb;
+use crate::material::Material;
+use crate::medium::HomogeneousMedium;
+
+/// Anything the scene can intersect and shade: meshes, CSG solids, ...
+pub trait Primitive {
+    fn intersect(&self, ray: &Ray) -> IntersectResult<'_>;
+
+    /// Any hit within the ray interval, primitives with a cheaper test than
+    /// finding the nearest hit should override it
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.intersect(ray).is_intersect
+    }
+
+    /// World space bounding box
+    fn bounds(&self) -> Aabb;
+
+    fn material(&self) -> &dyn Material;
+
+    /// Medium filling the inside of a closed surface
+    fn interior(&self) -> Option<&HomogeneousMedium> {
+        None
+    }
+
+    /// Position in the primitive's own space, used as texture coordinate
+    fn world_to_object(&self, p: Vector3<f64>, _time: f64) -> Vector3<f64> {
+        p
+    }
+}
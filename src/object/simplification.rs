@@ -0,0 +1,386 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::ops::AddAssign;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Vector3};
+
+use super::mesh::{Mesh, FaceStruct};
+
+// weight of the planes holding boundary edges in place
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// Sum of squared distances to a set of planes, the symmetric 4x4 matrix of
+/// Garland and Heckbert stored as its upper triangle
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric {
+    m: [f64; 10],
+}
+
+impl Quadric {
+    /// Plane through `p` with unit normal `n`
+    fn plane(n: Vector3<f64>, p: Vector3<f64>, weight: f64) -> Quadric {
+        let d = -n.dot(p);
+        let (a, b, c) = (n.x, n.y, n.z);
+        Quadric {
+            m: [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|v| v * weight),
+        }
+    }
+
+    fn error(&self, p: Vector3<f64>) -> f64 {
+        let m = &self.m;
+        let (x, y, z) = (p.x, p.y, p.z);
+        m[0] * x * x + 2.0 * m[1] * x * y + 2.0 * m[2] * x * z + 2.0 * m[3] * x
+            + m[4] * y * y + 2.0 * m[5] * y * z + 2.0 * m[6] * y
+            + m[7] * z * z + 2.0 * m[8] * z
+            + m[9]
+    }
+
+    /// Point of least error, None when the planes don't pin one down
+    fn minimum(&self) -> Option<Vector3<f64>> {
+        let m = &self.m;
+        let a = Matrix3::new(m[0], m[1], m[2], m[1], m[4], m[5], m[2], m[5], m[7]);
+        a.invert().map(|inv| inv * Vector3::new(-m[3], -m[6], -m[8]))
+    }
+}
+
+impl AddAssign for Quadric {
+    fn add_assign(&mut self, other: Quadric) {
+        for i in 0..10 {
+            self.m[i] += other.m[i];
+        }
+    }
+}
+
+/// Collapse of the edge between `u` and `v` into `target`, stale when either
+/// vertex changed since it was queued
+struct Collapse {
+    cost: f64,
+    u: usize,
+    v: usize,
+    versions: (usize, usize),
+    target: Vector3<f64>,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // cheapest first out of the max heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Decimation by quadric error metrics: edges are collapsed cheapest first
+/// until the mesh is down to `target_faces` triangles or the next collapse
+/// would move the surface by more than `max_error`. Collapses that flip
+/// faces or pinch the surface are skipped. The source polygons are dropped
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simplification {
+    pub target_faces: Option<usize>,
+    pub max_error: Option<f64>,
+    // keep open edges where they are
+    pub preserve_boundary: bool,
+}
+
+impl Default for Simplification {
+    fn default() -> Self {
+        Simplification::new()
+    }
+}
+
+struct State {
+    positions: Vec<Vector3<f64>>,
+    faces: Vec<[usize; 3]>,
+    face_alive: Vec<bool>,
+    vertex_faces: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<usize>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl State {
+    fn neighbours(&self, v: usize) -> HashSet<usize> {
+        self.vertex_faces[v].iter()
+            .flat_map(|&f| self.faces[f].iter().copied())
+            .filter(|&w| w != v)
+            .collect()
+    }
+
+    fn push(&mut self, u: usize, v: usize) {
+        let mut q = self.quadrics[u];
+        q += self.quadrics[v];
+        let (pu, pv) = (self.positions[u], self.positions[v]);
+        let middle = (pu + pv) / 2.0;
+        // the optimum is only trusted near the edge, far away it comes from
+        // a nearly singular matrix
+        let mut candidates = vec![pu, pv, middle];
+        if let Some(p) = q.minimum().filter(|p| (p - middle).magnitude() <= (pu - pv).magnitude()) {
+            candidates.push(p);
+        }
+        let (cost, target) = candidates.into_iter()
+            .map(|p| (q.error(p).max(0.0), p))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+
+        self.heap.push(Collapse {
+            cost,
+            u,
+            v,
+            versions: (self.versions[u], self.versions[v]),
+            target,
+        });
+    }
+
+    /// Whether collapsing `u` and `v` into `target` keeps the surface
+    /// manifold and no remaining face turns over
+    fn can_collapse(&self, u: usize, v: usize, target: Vector3<f64>) -> bool {
+        let shared = self.vertex_faces[u].iter().filter(|f| self.faces[**f].contains(&v)).count();
+        let common = self.neighbours(u).intersection(&self.neighbours(v)).count();
+        if common != shared {
+            return false;
+        }
+
+        for &w in [u, v].iter() {
+            for &f in self.vertex_faces[w].iter() {
+                let face = self.faces[f];
+                if face.contains(&u) && face.contains(&v) {
+                    continue;
+                }
+                let corner = |i: usize| self.positions[face[i]];
+                let moved = |i: usize| if face[i] == w { target } else { corner(i) };
+                let before = (corner(1) - corner(0)).cross(corner(2) - corner(0));
+                let after = (moved(1) - moved(0)).cross(moved(2) - moved(0));
+                if after.magnitude2() == 0.0 || before.dot(after) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn collapse(&mut self, u: usize, v: usize, target: Vector3<f64>) -> usize {
+        let mut removed = 0;
+        for f in std::mem::take(&mut self.vertex_faces[v]) {
+            if self.faces[f].contains(&u) {
+                self.face_alive[f] = false;
+                removed += 1;
+            } else {
+                self.faces[f].iter_mut().filter(|w| **w == v).for_each(|w| *w = u);
+                self.vertex_faces[u].push(f);
+            }
+        }
+        let alive = &self.face_alive;
+        self.vertex_faces[u].retain(|&f| alive[f]);
+        for w in self.neighbours(u) {
+            let alive = &self.face_alive;
+            self.vertex_faces[w].retain(|&f| alive[f]);
+        }
+
+        self.positions[u] = target;
+        let q = self.quadrics[v];
+        self.quadrics[u] += q;
+        self.versions[u] += 1;
+        self.versions[v] += 1;
+        for w in self.neighbours(u) {
+            self.push(u, w);
+        }
+
+        removed
+    }
+}
+
+impl Simplification {
+    pub fn new() -> Simplification {
+        Simplification {
+            target_faces: None,
+            max_error: None,
+            preserve_boundary: true,
+        }
+    }
+
+    pub fn set_target_faces(&mut self, count: usize) -> &mut Self {
+        self.target_faces = Some(count);
+
+        self
+    }
+
+    /// Largest distance, in model units, the surface may move
+    pub fn set_max_error(&mut self, error: f64) -> &mut Self {
+        self.max_error = Some(error);
+
+        self
+    }
+
+    pub fn set_preserve_boundary(&mut self, value: bool) -> &mut Self {
+        self.preserve_boundary = value;
+
+        self
+    }
+
+    /// Simplified copy of `mesh`. Corners keep their texture coordinates and
+    /// normals, vertices their colors and tangents
+    pub fn apply(&self, mesh: &Mesh) -> Mesh {
+        if self.target_faces.is_none() && self.max_error.is_none() {
+            return mesh.clone();
+        }
+
+        let n = mesh.vertices.len();
+        let mut state = State {
+            positions: mesh.vertices.clone(),
+            faces: mesh.faces.iter()
+                .map(|f| [0, 1, 2].map(|k| f.points[k].vertex_index as usize - 1))
+                .collect(),
+            face_alive: vec![true; mesh.faces.len()],
+            vertex_faces: vec![Vec::new(); n],
+            quadrics: vec![Quadric::default(); n],
+            versions: vec![0; n],
+            heap: BinaryHeap::new(),
+        };
+
+        let mut edges: Vec<((usize, usize), usize)> = Vec::new();
+        for f in 0..state.faces.len() {
+            let face = state.faces[f];
+            let p = face.map(|v| state.positions[v]);
+            let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            if normal.magnitude2() > 0.0 {
+                let plane = Quadric::plane(normal.normalize(), p[0], 1.0);
+                face.iter().for_each(|&v| state.quadrics[v] += plane);
+            }
+            for k in 0..3 {
+                state.vertex_faces[face[k]].push(f);
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                edges.push(((a.min(b), a.max(b)), f));
+            }
+        }
+        edges.sort_unstable();
+
+        for (i, &(e, f)) in edges.iter().enumerate() {
+            if i > 0 && edges[i - 1].0 == e {
+                continue;
+            }
+            let single = edges.get(i + 1).is_none_or(|next| next.0 != e);
+            if single && self.preserve_boundary {
+                // plane through the open edge, upright on its face
+                let p = state.faces[f].map(|v| state.positions[v]);
+                let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+                let side = (state.positions[e.1] - state.positions[e.0]).cross(normal);
+                if side.magnitude2() > 0.0 {
+                    let plane = Quadric::plane(side.normalize(), state.positions[e.0], BOUNDARY_WEIGHT);
+                    state.quadrics[e.0] += plane;
+                    state.quadrics[e.1] += plane;
+                }
+            }
+        }
+        for i in 0..edges.len() {
+            if i == 0 || edges[i - 1].0 != edges[i].0 {
+                let (u, v) = edges[i].0;
+                state.push(u, v);
+            }
+        }
+
+        let mut face_count = mesh.faces.len();
+        let max_cost = self.max_error.map_or(f64::INFINITY, |e| e * e);
+        while let Some(c) = state.heap.pop() {
+            if self.target_faces.is_some_and(|target| face_count <= target) || c.cost > max_cost {
+                break;
+            }
+            if c.versions != (state.versions[c.u], state.versions[c.v])
+                || !state.can_collapse(c.u, c.v, c.target) {
+                continue;
+            }
+            face_count -= state.collapse(c.u, c.v, c.target);
+        }
+
+        let mut result = mesh.clone();
+        result.vertices = state.positions;
+        result.faces = mesh.faces.iter().zip(state.faces.iter())
+            .zip(state.face_alive.iter())
+            .filter(|(_, &alive)| alive)
+            .map(|((face, indices), _)| FaceStruct {
+                points: face.points.iter().zip(indices.iter())
+                    .map(|(p, &v)| {
+                        let mut p = p.clone();
+                        p.vertex_index = v as i32 + 1;
+                        p
+                    })
+                    .collect(),
+            })
+            .collect();
+        result.polygons.clear();
+        result.face_polygons.clear();
+        let identity: Vec<usize> = (0..n).collect();
+        result.merge_vertices(&identity);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod simplification_test {
+    use super::Simplification;
+    use crate::object::{Mesh, Subdivision, SubdivisionScheme};
+    use cgmath::prelude::*;
+
+    fn area(mesh: &Mesh) -> f64 {
+        mesh.faces_iter()
+            .map(|f| (f.points[1].vertex - f.points[0].vertex).cross(f.points[2].vertex - f.points[0].vertex).magnitude() / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_flat_grid_collapses() {
+        let mut obj = String::new();
+        for y in 0..=10 {
+            for x in 0..=10 {
+                obj.push_str(&format!("v {} {} 0\n", x, y));
+            }
+        }
+        for y in 0..10 {
+            for x in 0..10 {
+                let i = y * 11 + x + 1;
+                obj.push_str(&format!("f {} {} {} {}\n", i, i + 1, i + 12, i + 11));
+            }
+        }
+        let grid = Mesh::from_obj_str(&obj);
+        assert_eq!(grid.faces.len(), 200);
+
+        let mesh = Simplification::new().set_max_error(1e-9).apply(&grid);
+        assert!(mesh.faces.len() < 10);
+        assert!(mesh.vertices.iter().all(|v| v.z == 0.0));
+        assert!((area(&mesh) - 100.0).abs() < 1e-9);
+        assert_eq!(mesh.stats().boundary_edges, mesh.stats().faces + 2);
+    }
+
+    #[test]
+    fn test_target_faces_keeps_closed_surface() {
+        let cube = Mesh::from_obj_str("v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+            v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+            f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n");
+        let sphere = Subdivision::new(SubdivisionScheme::CatmullClark, 3).apply(&cube);
+        assert_eq!(sphere.faces.len(), 768);
+
+        let mesh = Simplification::new().set_target_faces(100).apply(&sphere);
+        let stats = mesh.stats();
+        assert!(stats.faces <= 100 && stats.faces >= 90);
+        assert!(stats.is_closed());
+        assert_eq!(stats.vertices, stats.faces / 2 + 2);
+        // the shape stays about the same size
+        let extent = stats.bounds.diagonal();
+        let original = sphere.stats().bounds.diagonal();
+        assert!((extent - original).magnitude() < 0.1 * original.magnitude());
+    }
+}
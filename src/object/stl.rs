@@ -0,0 +1,93 @@
+use cgmath::Vector3;
+
+use super::mesh::{Mesh, FaceStruct, PointStruct};
+
+/// Binary files have an 80 byte header, a triangle count and 50 bytes per
+/// triangle. The header may start with `solid` too, so the size decides
+pub fn is_binary_stl(data: &[u8]) -> bool {
+    if data.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    data.len() == 84 + count * 50
+}
+
+/// Reads an ASCII or binary STL file. Triangles don't share vertices in STL,
+/// every face gets three of its own. Facet normals are often left zero by
+/// exporters, normals follow the winding instead
+pub fn parse_stl(data: &[u8]) -> Result<Mesh, String> {
+    let mut mesh = Mesh::new();
+    if is_binary_stl(data) {
+        let count = (data.len() - 84) / 50;
+        for i in 0..count {
+            let facet = &data[84 + i * 50..84 + (i + 1) * 50];
+            let float = |k: usize| f32::from_le_bytes([facet[k], facet[k + 1], facet[k + 2], facet[k + 3]]) as f64;
+            // skip the normal, then three vertices
+            for v in 0..3 {
+                let offset = 12 + v * 12;
+                mesh.add_vertex(Vector3::new(float(offset), float(offset + 4), float(offset + 8)));
+            }
+        }
+    } else {
+        let text = std::str::from_utf8(data).map_err(|_| "stl is neither binary nor text")?;
+        if !text.trim_start().starts_with("solid") {
+            return Err(String::from("not a stl file"));
+        }
+        let mut tokens = text.split_ascii_whitespace();
+        while let Some(token) = tokens.next() {
+            if token == "vertex" {
+                let mut coord = || -> Result<f64, String> {
+                    let t = tokens.next().ok_or("unexpected end of stl")?;
+                    t.parse::<f64>().map_err(|e| format!("bad stl value `{}`: {}", t, e))
+                };
+                let v = Vector3::new(coord()?, coord()?, coord()?);
+                mesh.add_vertex(v);
+            }
+        }
+        if !mesh.vertices.len().is_multiple_of(3) {
+            return Err(String::from("stl facet without three vertices"));
+        }
+    }
+
+    for i in 0..mesh.vertices.len() / 3 {
+        let index = i as i32 * 3;
+        mesh.add_face(FaceStruct {
+            points: (1..=3).map(|k| PointStruct::new(index + k, 0, 0)).collect(),
+        });
+    }
+    mesh.remove_degenerate_faces();
+
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod stl_test {
+    use super::{parse_stl, is_binary_stl};
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_ascii_and_binary() {
+        let text = "solid tri\n facet normal 0 0 0\n  outer loop\n   vertex 0 0 0\n   vertex 1 0 0\n   vertex 0 1 0\n  endloop\n endfacet\nendsolid tri\n";
+        let mesh = parse_stl(text.as_bytes()).unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(mesh.face(0).points[0].normal, Vector3::new(0.0, 0.0, 1.0));
+
+        // binary header starting with `solid`, as some exporters write it
+        let mut data = b"solid binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for z in [0.0f32, 1.0] {
+            data.extend_from_slice(&[0; 12]);
+            for v in [[0.0f32, 0.0, z], [1.0, 0.0, z], [0.0, 1.0, z]] {
+                for c in v.iter() {
+                    data.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+        assert!(is_binary_stl(&data));
+        let mesh = parse_stl(&data).unwrap();
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.vertices[4], Vector3::new(1.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+
+use super::mesh::{Mesh, FaceStruct, PointStruct};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubdivisionScheme {
+    // triangles, each split into four
+    Loop,
+    // any polygons, each split into quads
+    CatmullClark,
+}
+
+/// Subdivision surface settings, applied with `apply` or `Object::subdivide`.
+/// Creases follow the semi-sharp rules of DeRose et al.: an edge of sharpness
+/// s is refined with the crease rules for s levels, a fraction blends the
+/// smooth and crease rules. Boundary edges are always infinitely sharp
+pub struct Subdivision {
+    pub scheme: SubdivisionScheme,
+    pub levels: usize,
+    // sharpness of crease edges, keyed by their 1 based vertex indices with the smaller first
+    pub creases: HashMap<(i32, i32), f64>,
+    // edges whose faces meet at more than this angle become infinitely sharp
+    pub crease_angle: Option<f64>,
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn average<T: Copy + std::iter::Sum<T> + std::ops::Div<f64, Output = T>>(values: impl Iterator<Item = T>) -> T {
+    let values: Vec<T> = values.collect();
+    let count = values.len() as f64;
+    values.into_iter().sum::<T>() / count
+}
+
+/// Polygon mesh between subdivision steps, indices start from 0
+struct Control {
+    positions: Vec<Vector3<f64>>,
+    // per vertex, empty when absent
+    colors: Vec<Vector3<f64>>,
+    faces: Vec<Vec<usize>>,
+    // texture coordinates at the corners of each face, empty when absent
+    uvs: Vec<Vec<Vector2<f64>>>,
+    sharpness: HashMap<(usize, usize), f64>,
+}
+
+struct Topology {
+    edge_faces: HashMap<(usize, usize), Vec<usize>>,
+    vertex_edges: Vec<Vec<(usize, usize)>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl Control {
+    fn topology(&self) -> Topology {
+        let mut topology = Topology {
+            edge_faces: HashMap::new(),
+            vertex_edges: vec![Vec::new(); self.positions.len()],
+            vertex_faces: vec![Vec::new(); self.positions.len()],
+        };
+        for (f, face) in self.faces.iter().enumerate() {
+            for (i, &v) in face.iter().enumerate() {
+                let e = edge_key(v, face[(i + 1) % face.len()]);
+                let faces = topology.edge_faces.entry(e).or_default();
+                if faces.is_empty() {
+                    topology.vertex_edges[e.0].push(e);
+                    topology.vertex_edges[e.1].push(e);
+                }
+                faces.push(f);
+                topology.vertex_faces[v].push(f);
+            }
+        }
+
+        topology
+    }
+
+    /// Boundary and non-manifold edges are infinitely sharp
+    fn edge_sharpness(&self, topology: &Topology, e: (usize, usize)) -> f64 {
+        if topology.edge_faces[&e].len() != 2 {
+            return f64::INFINITY;
+        }
+        self.sharpness.get(&e).copied().unwrap_or(0.0)
+    }
+
+    fn edge_point(&self, topology: &Topology, e: (usize, usize), smooth: impl Fn() -> Vector3<f64>) -> Vector3<f64> {
+        let middle = (self.positions[e.0] + self.positions[e.1]) / 2.0;
+        let s = self.edge_sharpness(topology, e);
+        if s >= 1.0 {
+            middle
+        } else {
+            smooth().lerp(middle, s)
+        }
+    }
+
+    /// Moves `v` to `smooth` unless crease edges meet at it: two make it
+    /// follow the crease, more or a single face pin it in place
+    fn vertex_point(&self, topology: &Topology, v: usize, smooth: impl Fn() -> Vector3<f64>) -> Vector3<f64> {
+        let p = self.positions[v];
+        if topology.vertex_faces[v].is_empty() {
+            return p;
+        }
+        let sharp: Vec<(usize, f64)> = topology.vertex_edges[v].iter()
+            .map(|&e| (if e.0 == v { e.1 } else { e.0 }, self.edge_sharpness(topology, e)))
+            .filter(|&(_, s)| s > 0.0)
+            .collect();
+        let crease = if topology.vertex_faces[v].len() == 1 || sharp.len() > 2 {
+            p
+        } else if sharp.len() == 2 {
+            (self.positions[sharp[0].0] + p * 6.0 + self.positions[sharp[1].0]) / 8.0
+        } else {
+            return smooth();
+        };
+
+        let s = average(sharp.iter().map(|&(_, s)| s));
+        if s >= 1.0 {
+            crease
+        } else {
+            smooth().lerp(crease, s)
+        }
+    }
+
+    /// Sharpness of the two halves of each crease edge, one level softer
+    fn split_creases(&self, edge_points: &HashMap<(usize, usize), usize>) -> HashMap<(usize, usize), f64> {
+        let mut result = HashMap::new();
+        for (&e, &s) in self.sharpness.iter() {
+            if let Some(&middle) = edge_points.get(&e) {
+                if s > 1.0 {
+                    result.insert(edge_key(e.0, middle), s - 1.0);
+                    result.insert(edge_key(middle, e.1), s - 1.0);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// New vertices go after the old ones, one per edge in the order the
+    /// faces first use them
+    fn number_edges(&self, first: usize) -> HashMap<(usize, usize), usize> {
+        let mut edge_points = HashMap::new();
+        for face in self.faces.iter() {
+            for i in 0..face.len() {
+                let e = edge_key(face[i], face[(i + 1) % face.len()]);
+                let next = first + edge_points.len();
+                edge_points.entry(e).or_insert(next);
+            }
+        }
+
+        edge_points
+    }
+
+    /// Linear colors of the edge points, numbered as in `number_edges`
+    fn edge_colors(&self, edge_points: &HashMap<(usize, usize), usize>, colors: &mut Vec<Vector3<f64>>) {
+        if self.colors.is_empty() {
+            return;
+        }
+        colors.resize(colors.len() + edge_points.len(), Vector3::zero());
+        for (&e, &i) in edge_points.iter() {
+            colors[i] = (self.colors[e.0] + self.colors[e.1]) / 2.0;
+        }
+    }
+
+    fn loop_step(&self) -> Control {
+        let topology = self.topology();
+        let n = self.positions.len();
+        let edge_points = self.number_edges(n);
+
+        let mut positions: Vec<Vector3<f64>> = (0..n).map(|v| self.vertex_point(&topology, v, || {
+            let neighbours: Vec<Vector3<f64>> = topology.vertex_edges[v].iter()
+                .map(|&e| self.positions[if e.0 == v { e.1 } else { e.0 }])
+                .collect();
+            let k = neighbours.len() as f64;
+            let beta = if neighbours.len() == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * k) };
+            self.positions[v] * (1.0 - k * beta) + neighbours.into_iter().sum::<Vector3<f64>>() * beta
+        })).collect();
+        positions.resize(n + edge_points.len(), Vector3::zero());
+        for (&e, &i) in edge_points.iter() {
+            positions[i] = self.edge_point(&topology, e, || {
+                // the corners facing the edge in its two triangles
+                let opposite: Vector3<f64> = topology.edge_faces[&e].iter()
+                    .map(|&f| self.faces[f].iter().find(|&&v| v != e.0 && v != e.1).map_or(Vector3::zero(), |&v| self.positions[v]))
+                    .sum();
+                (self.positions[e.0] + self.positions[e.1]) * (3.0 / 8.0) + opposite / 8.0
+            });
+        }
+
+        let mut colors = self.colors.clone();
+        self.edge_colors(&edge_points, &mut colors);
+
+        let mut faces = Vec::new();
+        let mut uvs = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let e = |i: usize| edge_points[&edge_key(face[i], face[(i + 1) % 3])];
+            faces.push(vec![face[0], e(0), e(2)]);
+            faces.push(vec![face[1], e(1), e(0)]);
+            faces.push(vec![face[2], e(2), e(1)]);
+            faces.push(vec![e(0), e(1), e(2)]);
+            if !self.uvs.is_empty() {
+                let uv = &self.uvs[f];
+                let m = |i: usize| (uv[i] + uv[(i + 1) % 3]) / 2.0;
+                uvs.push(vec![uv[0], m(0), m(2)]);
+                uvs.push(vec![uv[1], m(1), m(0)]);
+                uvs.push(vec![uv[2], m(2), m(1)]);
+                uvs.push(vec![m(0), m(1), m(2)]);
+            }
+        }
+
+        Control {
+            positions,
+            colors,
+            faces,
+            uvs,
+            sharpness: self.split_creases(&edge_points),
+        }
+    }
+
+    fn catmull_clark_step(&self) -> Control {
+        let topology = self.topology();
+        let n = self.positions.len();
+        let face_points: Vec<Vector3<f64>> = self.faces.iter()
+            .map(|face| average(face.iter().map(|&v| self.positions[v])))
+            .collect();
+        // old vertices, then face points, then edge points
+        let edge_points = self.number_edges(n + self.faces.len());
+
+        let mut positions: Vec<Vector3<f64>> = (0..n).map(|v| self.vertex_point(&topology, v, || {
+            let k = topology.vertex_edges[v].len() as f64;
+            let q = average(topology.vertex_faces[v].iter().map(|&f| face_points[f]));
+            let r = average(topology.vertex_edges[v].iter().map(|&e| (self.positions[e.0] + self.positions[e.1]) / 2.0));
+            (q + r * 2.0 + self.positions[v] * (k - 3.0)) / k
+        })).collect();
+        positions.extend_from_slice(&face_points);
+        positions.resize(n + self.faces.len() + edge_points.len(), Vector3::zero());
+        for (&e, &i) in edge_points.iter() {
+            positions[i] = self.edge_point(&topology, e, || {
+                let faces: Vector3<f64> = topology.edge_faces[&e].iter().map(|&f| face_points[f]).sum();
+                (self.positions[e.0] + self.positions[e.1] + faces) / 4.0
+            });
+        }
+
+        let mut colors = self.colors.clone();
+        if !colors.is_empty() {
+            colors.extend(self.faces.iter().map(|face| average(face.iter().map(|&v| self.colors[v]))));
+        }
+        self.edge_colors(&edge_points, &mut colors);
+
+        let mut faces = Vec::new();
+        let mut uvs = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let k = face.len();
+            let e = |i: usize| edge_points[&edge_key(face[i % k], face[(i + 1) % k])];
+            for (i, &v) in face.iter().enumerate() {
+                faces.push(vec![v, e(i), n + f, e(i + k - 1)]);
+            }
+            if !self.uvs.is_empty() {
+                let uv = &self.uvs[f];
+                let m = |i: usize| (uv[i % k] + uv[(i + 1) % k]) / 2.0;
+                let center = average(uv.iter().copied());
+                for (i, &corner) in uv.iter().enumerate() {
+                    uvs.push(vec![corner, m(i), center, m(i + k - 1)]);
+                }
+            }
+        }
+
+        Control {
+            positions,
+            colors,
+            faces,
+            uvs,
+            sharpness: self.split_creases(&edge_points),
+        }
+    }
+}
+
+impl Subdivision {
+    pub fn new(scheme: SubdivisionScheme, levels: usize) -> Subdivision {
+        Subdivision {
+            scheme,
+            levels,
+            creases: HashMap::new(),
+            crease_angle: None,
+        }
+    }
+
+    pub fn set_levels(&mut self, levels: usize) -> &mut Self {
+        self.levels = levels;
+
+        self
+    }
+
+    /// Marks the edge between vertices `a` and `b` (1 based, as in the mesh
+    /// faces) as a crease, `f64::INFINITY` keeps it sharp at every level
+    pub fn add_crease(&mut self, a: i32, b: i32, sharpness: f64) -> &mut Self {
+        self.creases.insert((a.min(b), a.max(b)), sharpness);
+
+        self
+    }
+
+    /// Angle in radians
+    pub fn set_crease_angle(&mut self, angle: f64) -> &mut Self {
+        self.crease_angle = Some(angle);
+
+        self
+    }
+
+    fn control(&self, mesh: &Mesh) -> Control {
+        // Catmull-Clark works best on the quads the source had
+        let source = if self.scheme == SubdivisionScheme::CatmullClark && !mesh.polygons.is_empty() {
+            &mesh.polygons
+        } else {
+            &mesh.faces
+        };
+        let has_uvs = !mesh.textures.is_empty()
+            && source.iter().all(|f| f.points.iter().all(|p| p.texture_index > 0));
+
+        let mut control = Control {
+            positions: mesh.vertices.clone(),
+            colors: mesh.colors.clone(),
+            faces: source.iter()
+                .map(|f| f.points.iter().map(|p| p.vertex_index as usize - 1).collect())
+                .collect(),
+            uvs: if has_uvs {
+                source.iter()
+                    .map(|f| f.points.iter().map(|p| mesh.textures[p.texture_index as usize - 1]).collect())
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            sharpness: self.creases.iter()
+                .map(|(&(a, b), &s)| (edge_key(a as usize - 1, b as usize - 1), s))
+                .collect(),
+        };
+
+        if let Some(angle) = self.crease_angle {
+            // Newell normals, which also work for polygons that aren't flat
+            let normals: Vec<Vector3<f64>> = control.faces.iter().map(|face| {
+                let n: Vector3<f64> = (0..face.len())
+                    .map(|i| control.positions[face[i]].cross(control.positions[face[(i + 1) % face.len()]]))
+                    .sum();
+                if n.magnitude2() > 0.0 { n.normalize() } else { n }
+            }).collect();
+            let topology = control.topology();
+            for (&e, faces) in topology.edge_faces.iter() {
+                if faces.len() == 2 && normals[faces[0]].dot(normals[faces[1]]) < angle.cos() {
+                    control.sharpness.insert(e, f64::INFINITY);
+                }
+            }
+        }
+
+        control
+    }
+
+    /// Subdivided copy of `mesh`. Texture coordinates and vertex colors are
+    /// interpolated linearly, normals are recomputed, smooth up to the crease
+    /// angle. Extra uv sets are dropped
+    pub fn apply(&self, mesh: &Mesh) -> Mesh {
+        if self.levels == 0 {
+            return mesh.clone();
+        }
+
+        let mut control = self.control(mesh);
+        for _ in 0..self.levels {
+            control = match self.scheme {
+                SubdivisionScheme::Loop => control.loop_step(),
+                SubdivisionScheme::CatmullClark => control.catmull_clark_step(),
+            };
+        }
+
+        let Control { positions, colors, faces, uvs, .. } = control;
+        let mut result = Mesh::new();
+        result.vertices = positions;
+        result.colors = colors;
+        // corners sharing a texture coordinate share the entry
+        let mut textures: HashMap<[u64; 2], i32> = HashMap::new();
+        for (f, face) in faces.iter().enumerate() {
+            let points: Vec<PointStruct> = face.iter().enumerate().map(|(i, &v)| {
+                let texture = match uvs.get(f) {
+                    Some(uv) => {
+                        let uv = uv[i];
+                        *textures.entry([uv.x.to_bits(), uv.y.to_bits()]).or_insert_with(|| {
+                            result.textures.push(uv);
+                            result.textures.len() as i32
+                        })
+                    },
+                    None => 0,
+                };
+                PointStruct::new(v as i32 + 1, texture, 0)
+            }).collect();
+            result.add_polygon(FaceStruct {
+                points,
+            });
+        }
+        result.remove_degenerate_faces();
+        result.compute_smooth_normals(self.crease_angle.unwrap_or(PI));
+        if !mesh.tangents.is_empty() {
+            result.compute_tangents();
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod subdivision_test {
+    use super::{Subdivision, SubdivisionScheme};
+    use crate::object::Mesh;
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    fn cube() -> Mesh {
+        Mesh::from_obj_str("v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+            v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+            f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n")
+    }
+
+    #[test]
+    fn test_catmull_clark_cube() {
+        let mesh = Subdivision::new(SubdivisionScheme::CatmullClark, 1).apply(&cube());
+        assert_eq!(mesh.vertices.len(), 26);
+        assert_eq!(mesh.polygons.len(), 24);
+        assert_eq!(mesh.faces.len(), 48);
+        let expected = Vector3::new(5.0, 5.0, 5.0) / 9.0;
+        assert!((mesh.vertices[6] - expected).magnitude() < 1e-12);
+
+        // with every edge sharp the surface stays on the cube
+        let mut subdivision = Subdivision::new(SubdivisionScheme::CatmullClark, 2);
+        subdivision.set_crease_angle(80f64.to_radians());
+        let mesh = subdivision.apply(&cube());
+        assert_eq!(mesh.polygons.len(), 96);
+        for v in mesh.vertices.iter() {
+            assert!((v.x.abs().max(v.y.abs()).max(v.z.abs()) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_loop_tetrahedron_and_crease() {
+        let tetrahedron = Mesh::from_obj_str("v 1 1 1\nv 1 -1 -1\nv -1 1 -1\nv -1 -1 1\n\
+            f 1 2 3\nf 1 4 2\nf 1 3 4\nf 2 4 3\n");
+        let mesh = Subdivision::new(SubdivisionScheme::Loop, 1).apply(&tetrahedron);
+        assert_eq!(mesh.faces.len(), 16);
+        assert!((mesh.vertices[0] - Vector3::new(0.25, 0.25, 0.25)).magnitude() < 1e-12);
+        // edge points sit at half the distance of the edge middles
+        assert!(mesh.vertices[4..].iter().all(|v| (v.magnitude() - 0.5).abs() < 1e-12));
+
+        // creases around the first face keep its outline in the plane of the
+        // face, along with the points inside whose neighbours are all there
+        let mut subdivision = Subdivision::new(SubdivisionScheme::Loop, 2);
+        subdivision.add_crease(1, 2, f64::INFINITY)
+            .add_crease(2, 3, f64::INFINITY)
+            .add_crease(3, 1, f64::INFINITY);
+        let mesh = subdivision.apply(&tetrahedron);
+        let in_plane = mesh.vertices.iter()
+            .filter(|v| (v.x + v.y - v.z - 1.0).abs() < 1e-12)
+            .count();
+        assert_eq!(in_plane, 15);
+    }
+}
@@ -0,0 +1,93 @@
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+/// Normal of a polygon by Newell's method, its length is twice the area.
+/// Works for polygons that are concave or not quite flat
+pub fn polygon_normal(points: &[Vector3<f64>]) -> Vector3<f64> {
+    (0..points.len())
+        .map(|i| points[i].cross(points[(i + 1) % points.len()]))
+        .sum()
+}
+
+fn is_inside(p: Vector3<f64>, a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>, normal: Vector3<f64>) -> bool {
+    (b - a).cross(p - a).dot(normal) >= 0.0
+        && (c - b).cross(p - b).dot(normal) >= 0.0
+        && (a - c).cross(p - c).dot(normal) >= 0.0
+}
+
+/// Splits a simple polygon into triangles by ear clipping, returned as
+/// indices into `points` wound like the polygon. Convex polygons get the fan
+/// from the first point. When no ear can be found, as for self intersecting
+/// or degenerate polygons, the rest is split into a fan
+pub fn triangulate(points: &[Vector3<f64>]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    let normal = polygon_normal(points);
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&j| {
+            let (a, b, c) = (remaining[j], remaining[(j + 1) % m], remaining[(j + 2) % m]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            // the corner must turn the same way as the polygon
+            if (pb - pa).cross(pc - pb).dot(normal) <= 0.0 {
+                return false;
+            }
+            // and no other corner may be inside the ear, corners at the same
+            // place as one of the ear's, as in polygons touching themselves, are fine
+            !remaining.iter()
+                .filter(|&&k| k != a && k != b && k != c)
+                .map(|&k| points[k])
+                .any(|p| p != pa && p != pb && p != pc && is_inside(p, pa, pb, pc, normal))
+        });
+        match ear {
+            Some(j) => {
+                triangles.push([remaining[j], remaining[(j + 1) % m], remaining[(j + 2) % m]]);
+                remaining.remove((j + 1) % m);
+            },
+            None => break,
+        }
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod triangulate_test {
+    use super::triangulate;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_convex_and_concave() {
+        let square: Vec<Vector3<f64>> = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].iter()
+            .map(|p| Vector3::new(p[0], p[1], 0.0))
+            .collect();
+        assert_eq!(triangulate(&square), vec![[0, 1, 2], [0, 2, 3]]);
+
+        // an arrow head, the fan from the first point would cover the notch
+        let arrow: Vec<Vector3<f64>> = [[0.0, 0.0], [2.0, 1.0], [0.0, 2.0], [1.0, 1.0]].iter()
+            .map(|p| Vector3::new(0.0, p[0], p[1]))
+            .collect();
+        let triangles = triangulate(&arrow);
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().all(|t| t.contains(&3)));
+
+        // an L shape wound clockwise
+        let l: Vec<Vector3<f64>> = [[0.0, 0.0], [0.0, 2.0], [1.0, 2.0], [1.0, 1.0], [2.0, 1.0], [2.0, 0.0]].iter()
+            .map(|p| Vector3::new(p[0], p[1], 0.0))
+            .collect();
+        let triangles = triangulate(&l);
+        assert_eq!(triangles.len(), 4);
+        let area: f64 = triangles.iter()
+            .map(|t| ((l[t[1]] - l[t[0]]).x * (l[t[2]] - l[t[0]]).y - (l[t[1]] - l[t[0]]).y * (l[t[2]] - l[t[0]]).x) / 2.0)
+            .sum();
+        assert_eq!(area, -3.0);
+    }
+}
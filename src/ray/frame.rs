@@ -0,0 +1,12 @@
+use cgmath::Vector3;
+
+/// Builds two tangents completing `n` into an orthonormal basis
+pub fn coordinate_system(n: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let t = if n.x.abs() > n.y.abs() {
+        Vector3::new(-n.z, 0.0, n.x) / (n.x * n.x + n.z * n.z).sqrt()
+    } else {
+        Vector3::new(0.0, n.z, -n.y) / (n.y * n.y + n.z * n.z).sqrt()
+    };
+
+    (t, n.cross(t))
+}
@@ -1,3 +1,8 @@
+#[allow(clippy::module_inception)]
 pub mod ray;
+pub mod offset;
+pub mod frame;
 
-pub use ray::Ray;
\ No newline at end of file
+pub use ray::Ray;
+pub use offset::{gamma, offset_ray_origin};
+pub use frame::coordinate_system;
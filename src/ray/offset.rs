@@ -0,0 +1,67 @@
+use cgmath::{InnerSpace, Vector3};
+
+/// Bound on the relative error of `n` consecutive floating point operations
+pub fn gamma(n: i32) -> f64 {
+    let e = f64::EPSILON * 0.5 * n as f64;
+    e / (1.0 - e)
+}
+
+/// Moves `p` along the geometric normal `n` just far enough that the box of
+/// its rounding error `error` lies behind it, on the side `dir` points to.
+/// A ray leaving from the result can't hit the surface it starts on again
+pub fn offset_ray_origin(p: Vector3<f64>, error: Vector3<f64>, n: Vector3<f64>, dir: Vector3<f64>) -> Vector3<f64> {
+    let d = n.x.abs() * error.x + n.y.abs() * error.y + n.z.abs() * error.z;
+    let mut offset = n * d;
+    if dir.dot(n) < 0.0 {
+        offset = -offset;
+    }
+    let mut po = p + offset;
+
+    // round away from p so the offset is not lost to rounding
+    for i in 0..3 {
+        if offset[i] > 0.0 {
+            po[i] = next_up(po[i]);
+        } else if offset[i] < 0.0 {
+            po[i] = next_down(po[i]);
+        }
+    }
+
+    po
+}
+
+fn next_up(v: f64) -> f64 {
+    if v.is_infinite() && v > 0.0 {
+        return v;
+    }
+    if v == 0.0 {
+        return f64::from_bits(1);
+    }
+    let bits = v.to_bits();
+    if v > 0.0 { f64::from_bits(bits + 1) } else { f64::from_bits(bits - 1) }
+}
+
+fn next_down(v: f64) -> f64 {
+    -next_up(-v)
+}
+
+#[cfg(test)]
+mod offset_test {
+    use super::{next_up, next_down, offset_ray_origin};
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_offset_side() {
+        let p = Vector3::new(0.5, 1.0, -2.0);
+        let error = Vector3::new(1e-9, 1e-9, 1e-9);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+
+        let up = offset_ray_origin(p, error, n, Vector3::new(0.3, 0.5, 0.0));
+        let down = offset_ray_origin(p, error, n, Vector3::new(0.3, -0.5, 0.0));
+        assert!(up.y - p.y >= 1e-9);
+        assert!(p.y - down.y >= 1e-9);
+        assert_eq!(up.x, p.x);
+
+        assert!(next_up(1.0) > 1.0);
+        assert!(next_down(0.0) < 0.0);
+    }
+}
@@ -1,17 +1,50 @@
-use cgmath::prelude::*;
 use cgmath::Vector3;
 
-#[derive(Debug, PartialEq)]
+/// Default start of the ray interval. Rays leaving a surface should come from
+/// `IntersectResult::spawn_ray` instead, which moves the origin off the surface
+pub const RAY_EPSILON: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     pub pos: Vector3<f64>,
     pub dir: Vector3<f64>,
+    // moment within the shutter interval, used by animated objects
+    pub time: f64,
+    // only hits with t in [t_min, t_max] count
+    pub t_min: f64,
+    pub t_max: f64,
 }
 
 impl Ray {
+    pub fn new(pos: Vector3<f64>, dir: Vector3<f64>) -> Ray {
+        Ray {
+            pos,
+            dir,
+            time: 0.0,
+            t_min: RAY_EPSILON,
+            t_max: f64::INFINITY,
+        }
+    }
+
     pub fn new_nz() -> Ray {
+        Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0))
+    }
+
+    pub fn with_time(self, time: f64) -> Ray {
         Ray {
-            pos: Vector3::new(0.0, 0.0, 0.0),
-            dir: Vector3::new(0.0, 0.0, -1.0),
+            time,
+            ..self
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_t_max(self, t_max: f64) -> Ray {
+        Ray {
+            t_max,
+            ..self
+        }
+    }
+
+    pub fn contains(&self, t: f64) -> bool {
+        t >= self.t_min && t <= self.t_max
+    }
+}
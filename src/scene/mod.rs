@@ -1,3 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod scene;
+pub mod scene_node;
 
-pub use scene::Scene;
\ No newline at end of file
+pub use scene::Scene;
+pub use scene_node::SceneNode;
@@ -1,41 +1,232 @@
+use std::cell::OnceCell;
+
 use crate::ray::Ray;
-use crate::object::{IntersectResult, Object};
+use crate::accel::{Aabb, Bvh};
+use crate::object::{IntersectResult, Object, Primitive};
 use crate::light::Light;
+use crate::medium::{HomogeneousMedium, GridVolume};
+use super::scene_node::SceneNode;
+use crate::animation::Timeline;
+use crate::camera::Camera;
 
 pub struct Scene {
-    pub objects: Vec<Object>,
+    // objects and shapes are only reachable through methods dropping the BVH
+    objects: Vec<Object>,
+    // primitives other than meshes, e.g. CSG solids
+    shapes: Vec<Box<dyn Primitive>>,
     pub lights: Vec<Box<dyn Light>>,
+    // medium filling the space outside of all objects
+    pub fog: Option<HomogeneousMedium>,
+    pub volumes: Vec<GridVolume>,
+    pub timeline: Timeline,
+
+    // top level BVH over the objects' world bounds, built on the first intersection
+    bvh: OnceCell<Bvh>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
 }
 
 impl Scene {
     pub fn new() -> Scene {
         Scene {
             objects: Vec::new(),
+            shapes: Vec::new(),
             lights: Vec::new(),
+            fog: None,
+            volumes: Vec::new(),
+            timeline: Timeline::new(),
+
+            bvh: OnceCell::new(),
         }
     }
 
     pub fn add_object(&mut self, obj: Object) {
         self.objects.push(obj);
+        self.bvh = OnceCell::new();
+    }
+
+    pub fn add_shape(&mut self, shape: Box<dyn Primitive>) {
+        self.shapes.push(shape);
+        self.bvh = OnceCell::new();
+    }
+
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// Object for editing, the BVH is rebuilt on the next intersection
+    pub fn object_mut(&mut self, index: usize) -> Option<&mut Object> {
+        self.invalidate();
+        self.objects.get_mut(index)
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Primitive>] {
+        &self.shapes
+    }
+
+    /// Adds every object below `node`, each placed by the transforms of all its ancestors
+    pub fn add_node(&mut self, node: SceneNode) {
+        for obj in node.flatten() {
+            self.add_object(obj);
+        }
+    }
+
+    /// Drops the acceleration structure so it is rebuilt, `object_mut` and
+    /// the methods moving objects call it
+    pub fn invalidate(&mut self) {
+        self.bvh = OnceCell::new();
+    }
+
+    /// Picks the level of detail of every object for `camera` rendering an
+    /// image `height` pixels high, with objects placed as at `time`, see
+    /// `Object::select_lod`. `render_sequence` calls it for every frame,
+    /// callers rendering a single image have to call it before tracing
+    pub fn select_lods(&mut self, camera: &dyn Camera, height: usize, time: f64) {
+        for obj in self.objects.iter_mut() {
+            obj.select_lod(camera, height, time);
+        }
+        self.invalidate();
+    }
+
+    /// Poses the animated objects and lights as they are at `time` in seconds.
+    /// Returns the number of tracks skipped: those whose object or light is
+    /// gone, and object tracks on objects moved by `motion` keyframes, which
+    /// take precedence over the transform the tracks set
+    pub fn set_time(&mut self, time: f64) -> usize {
+        let mut skipped = 0;
+        for (index, tracks) in self.timeline.objects.iter_mut() {
+            match self.objects.get_mut(*index) {
+                Some(obj) if obj.motion.is_empty() => tracks.apply(obj, time),
+                _ => skipped += 1,
+            }
+        }
+        for (index, tracks) in self.timeline.lights.iter() {
+            match self.lights.get_mut(*index) {
+                Some(light) => tracks.apply(light.as_mut(), time),
+                None => skipped += 1,
+            }
+        }
+        if !self.timeline.objects.is_empty() {
+            self.invalidate();
+        }
+
+        skipped
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<Aabb> = self.objects.iter().map(|o| o.bounds())
+                .chain(self.shapes.iter().map(|s| s.bounds()))
+                .collect();
+            Bvh::build(&bounds)
+        })
     }
 
     pub fn add_light(&mut self, light: Box<dyn Light>) {
         self.lights.push(light);
     }
 
-    pub fn intersect(&self, ray: &Ray) -> IntersectResult {
-        let mut min_dis = f64::INFINITY;
-        let mut result: Option<IntersectResult> = None;
-        for obj in self.objects.iter() {
-            let r = obj.intersect(&ray);
-            if r.is_intersect {
-                if r.dis < min_dis {
-                    min_dis = r.dis;
-                    result = Some(r);
-                }
+    pub fn set_fog(&mut self, fog: HomogeneousMedium) {
+        self.fog = Some(fog);
+    }
+
+    pub fn add_volume(&mut self, volume: GridVolume) {
+        self.volumes.push(volume);
+    }
+
+    /// Objects come first, then shapes, the order the BVH indexes them
+    fn primitive(&self, index: usize) -> &dyn Primitive {
+        if index < self.objects.len() {
+            &self.objects[index]
+        } else {
+            self.shapes[index - self.objects.len()].as_ref()
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        let mut result = IntersectResult::no_intersect();
+        self.bvh().intersect(ray, ray.t_max, |i, t_max| {
+            let r = self.primitive(i).intersect(ray);
+            if r.is_intersect && r.dis < t_max {
+                let t = r.dis;
+                result = r;
+                Some(t)
+            } else {
+                None
             }
+        });
+
+        result
+    }
+
+    /// Whether anything blocks `ray` within its interval. Stops at the first
+    /// hit, so it is cheaper than `intersect` for shadow rays
+    pub fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh().any(ray, ray.t_max, |i| self.primitive(i).occluded(ray))
+    }
+}
+
+#[cfg(test)]
+mod scene_test {
+    use super::Scene;
+    use crate::object::{Object, Mesh};
+    use crate::light::{Light, PointLight};
+    use crate::ray::Ray;
+    use crate::transform::Transform;
+    use cgmath::Vector3;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_edits_rebuild_bvh() {
+        let mut scene = Scene::new();
+        let wall = Mesh::from_obj_str("v -1 -1 -5\nv 1 -1 -5\nv 0 1 -5\nf 1 2 3\n");
+        scene.add_object(Object::from_mesh(Rc::new(wall)));
+        assert!(scene.intersect(&Ray::new_nz()).is_intersect);
+
+        scene.object_mut(0).unwrap().set_transform(Transform::translation(Vector3::new(10.0, 0.0, 0.0)));
+        assert!(!scene.intersect(&Ray::new_nz()).is_intersect);
+        assert!(scene.object_mut(1).is_none());
+    }
+
+    #[test]
+    fn test_hits_do_not_depend_on_scale() {
+        // the same wall modelled a millionth and a million times too big
+        for size in [1e-6, 1e6] {
+            let mut scene = Scene::new();
+            let wall = Mesh::from_obj_str(&format!("v {0} {0} {1}\nv {2} {0} {1}\nv 0 {2} {1}\nf 1 2 3\n", -size, -5.0 * size, size));
+            let mut object = Object::from_mesh(Rc::new(wall));
+            object.set_transform(Transform::scale(1.0 / size, 1.0 / size, 1.0 / size));
+            scene.add_object(object);
+
+            let hit = scene.intersect(&Ray::new_nz());
+            assert!(hit.is_intersect);
+            assert!((hit.dis - 5.0).abs() < 1e-9);
+            assert!(!scene.occluded(&Ray::new_nz().with_t_max(4.9)));
+            // a hit just in front of the origin is still found
+            assert!(scene.intersect(&Ray::new(Vector3::new(0.0, 0.0, -4.99), Vector3::new(0.0, 0.0, -1.0))).is_intersect);
         }
+    }
 
-        result.unwrap_or(IntersectResult::no_intersect())
+    #[test]
+    fn test_occluded_within_ray_interval() {
+        let mut scene = Scene::new();
+        let wall = Mesh::from_obj_str("v -1 -1 -5\nv 1 -1 -5\nv 0 1 -5\nf 1 2 3\n");
+        scene.add_object(Object::from_mesh(Rc::new(wall)));
+
+        assert!(scene.occluded(&Ray::new_nz()));
+        assert!(!scene.occluded(&Ray::new_nz().with_t_max(4.0)));
+
+        // shadow rays end at the light, the wall only shadows lights behind it
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let near = PointLight::new(Vector3::new(0.0, 0.0, -3.0), white, 0.5, 1.0, 1.0);
+        let far = PointLight::new(Vector3::new(0.0, 0.0, -8.0), white, 0.5, 1.0, 1.0);
+        assert_eq!(near.get_ray(origin).t_max, 3.0);
+        assert!(!scene.occluded(&near.get_ray(origin)));
+        assert!(scene.occluded(&far.get_ray(origin)));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,87 @@
+use crate::object::Object;
+use crate::transform::Transform;
+
+/// A node of the scene graph. Its transform applies to its own objects and,
+/// composed with theirs, to everything in its children.
+pub struct SceneNode {
+    pub transform: Transform,
+    pub objects: Vec<Object>,
+    pub children: Vec<SceneNode>,
+}
+
+impl Default for SceneNode {
+    fn default() -> Self {
+        SceneNode::new()
+    }
+}
+
+impl SceneNode {
+    pub fn new() -> SceneNode {
+        SceneNode {
+            transform: Transform::identity(),
+            objects: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) -> &mut SceneNode {
+        self.transform = transform;
+
+        self
+    }
+
+    pub fn add_object(&mut self, obj: Object) -> &mut SceneNode {
+        self.objects.push(obj);
+
+        self
+    }
+
+    pub fn add_child(&mut self, child: SceneNode) -> &mut SceneNode {
+        self.children.push(child);
+
+        self
+    }
+
+    /// All objects of the subtree with the world transform of their parents applied
+    pub fn flatten(self) -> Vec<Object> {
+        let mut result = Vec::new();
+        self.flatten_into(Transform::identity(), &mut result);
+
+        result
+    }
+
+    fn flatten_into(self, parent: Transform, result: &mut Vec<Object>) {
+        let world = parent * self.transform;
+        for mut obj in self.objects {
+            obj.apply_transform(world);
+            result.push(obj);
+        }
+        for child in self.children {
+            child.flatten_into(world, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod scene_node_test {
+    use super::SceneNode;
+    use crate::object::Object;
+    use crate::transform::Transform;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_flatten_composes_transforms() {
+        let mut child = SceneNode::new();
+        child.set_transform(Transform::translation(Vector3::new(0.0, 1.0, 0.0)));
+        child.add_object(Object::new());
+
+        let mut root = SceneNode::new();
+        root.set_transform(Transform::uniform_scale(2.0));
+        root.add_child(child);
+
+        let objects = root.flatten();
+        assert_eq!(objects.len(), 1);
+        let expected = Transform::uniform_scale(2.0) * Transform::translation(Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(objects[0].transform, expected);
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod sdf;
+pub mod sdf_object;
+
+pub use sdf::Sdf;
+pub use sdf_object::SdfObject;
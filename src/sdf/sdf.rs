@@ -0,0 +1,218 @@
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3, Quaternion};
+
+use crate::accel::Aabb;
+
+fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+/// Signed distance expression, negative inside. Primitives are centered at
+/// the origin, use `translate` and `rotate` to place them.
+pub enum Sdf {
+    Sphere(f64),
+    /// half extents along each axis
+    Cuboid(Vector3<f64>),
+    /// major radius in the xz plane, minor radius of the tube
+    Torus(f64, f64),
+    /// segment end points and radius
+    Capsule(Vector3<f64>, Vector3<f64>, f64),
+
+    Translate(Vector3<f64>, Box<Sdf>),
+    Rotate(Quaternion<f64>, Box<Sdf>),
+    Scale(f64, Box<Sdf>),
+
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    /// the first one with the second one cut away
+    Subtraction(Box<Sdf>, Box<Sdf>),
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f64),
+    SmoothSubtraction(Box<Sdf>, Box<Sdf>, f64),
+
+    /// infinite copies every `period`, a zero component leaves that axis alone
+    Repeat(Vector3<f64>, Box<Sdf>),
+    /// rotation around y growing by the given radians per unit of height.
+    /// Distances are no longer exact, lower the `SdfObject` step scale
+    Twist(f64, Box<Sdf>),
+}
+
+impl Sdf {
+    pub fn sphere(radius: f64) -> Sdf {
+        Sdf::Sphere(radius)
+    }
+
+    pub fn cuboid(half_extents: Vector3<f64>) -> Sdf {
+        Sdf::Cuboid(half_extents)
+    }
+
+    pub fn torus(major: f64, minor: f64) -> Sdf {
+        Sdf::Torus(major, minor)
+    }
+
+    pub fn capsule(a: Vector3<f64>, b: Vector3<f64>, radius: f64) -> Sdf {
+        Sdf::Capsule(a, b, radius)
+    }
+
+    pub fn translate(self, offset: Vector3<f64>) -> Sdf {
+        Sdf::Translate(offset, Box::new(self))
+    }
+
+    pub fn rotate(self, rotation: Quaternion<f64>) -> Sdf {
+        Sdf::Rotate(rotation.normalize(), Box::new(self))
+    }
+
+    pub fn scale(self, factor: f64) -> Sdf {
+        Sdf::Scale(factor, Box::new(self))
+    }
+
+    pub fn union(self, other: Sdf) -> Sdf {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Sdf) -> Sdf {
+        Sdf::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Sdf) -> Sdf {
+        Sdf::Subtraction(Box::new(self), Box::new(other))
+    }
+
+    /// Union blending the surfaces over a distance of about `k`
+    pub fn smooth_union(self, other: Sdf, k: f64) -> Sdf {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn smooth_subtract(self, other: Sdf, k: f64) -> Sdf {
+        Sdf::SmoothSubtraction(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn repeat(self, period: Vector3<f64>) -> Sdf {
+        Sdf::Repeat(period, Box::new(self))
+    }
+
+    pub fn twist(self, amount: f64) -> Sdf {
+        Sdf::Twist(amount, Box::new(self))
+    }
+
+    pub fn distance(&self, p: Vector3<f64>) -> f64 {
+        match self {
+            Sdf::Sphere(r) => p.magnitude() - r,
+            Sdf::Cuboid(h) => {
+                let q = Vector3::new(p.x.abs() - h.x, p.y.abs() - h.y, p.z.abs() - h.z);
+                let outside = Vector3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).magnitude();
+                outside + q.x.max(q.y).max(q.z).min(0.0)
+            },
+            Sdf::Torus(major, minor) => {
+                let q = Vector2::new(Vector2::new(p.x, p.z).magnitude() - major, p.y);
+                q.magnitude() - minor
+            },
+            Sdf::Capsule(a, b, r) => {
+                let pa = p - a;
+                let ba = b - a;
+                let h = (pa.dot(ba) / ba.magnitude2()).clamp(0.0, 1.0);
+                (pa - ba * h).magnitude() - r
+            },
+            Sdf::Translate(offset, s) => s.distance(p - offset),
+            Sdf::Rotate(q, s) => s.distance(q.conjugate().rotate_vector(p)),
+            Sdf::Scale(factor, s) => s.distance(p / *factor) * factor,
+            Sdf::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Sdf::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            Sdf::Subtraction(a, b) => a.distance(p).max(-b.distance(p)),
+            Sdf::SmoothUnion(a, b, k) => smooth_min(a.distance(p), b.distance(p), *k),
+            Sdf::SmoothSubtraction(a, b, k) => -smooth_min(-a.distance(p), b.distance(p), *k),
+            Sdf::Repeat(period, s) => {
+                let mut q = p;
+                for k in 0..3 {
+                    if period[k] > 0.0 {
+                        q[k] = p[k] - period[k] * (p[k] / period[k]).round();
+                    }
+                }
+                s.distance(q)
+            },
+            Sdf::Twist(amount, s) => {
+                let (sin, cos) = (amount * p.y).sin_cos();
+                s.distance(Vector3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z))
+            },
+        }
+    }
+
+    /// Box around the surface, infinite when it is repeated
+    pub fn bounds(&self) -> Aabb {
+        let infinite = Aabb::new(
+            Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        );
+        let around = |r: f64| Aabb::new(Vector3::new(-r, -r, -r), Vector3::new(r, r, r));
+
+        match self {
+            Sdf::Sphere(r) => around(*r),
+            Sdf::Cuboid(h) => Aabb::new(-*h, *h),
+            Sdf::Torus(major, minor) => Aabb::new(Vector3::new(-major - minor, -minor, -major - minor), Vector3::new(major + minor, *minor, major + minor)),
+            Sdf::Capsule(a, b, r) => {
+                let mut result = Aabb::from_points([*a, *b].iter());
+                result.min -= Vector3::new(*r, *r, *r);
+                result.max += Vector3::new(*r, *r, *r);
+                result
+            },
+            Sdf::Translate(offset, s) => {
+                let b = s.bounds();
+                Aabb::new(b.min + offset, b.max + offset)
+            },
+            Sdf::Rotate(q, s) => {
+                let b = s.bounds();
+                if b.is_empty() || b.min.x.is_infinite() || b.max.x.is_infinite() {
+                    return b;
+                }
+                Aabb::from_points(b.corners().iter().map(|c| q.rotate_vector(*c)).collect::<Vec<_>>().iter())
+            },
+            Sdf::Scale(factor, s) => {
+                let b = s.bounds();
+                Aabb::new(b.min * *factor, b.max * *factor)
+            },
+            Sdf::Union(a, b) => a.bounds().union(&b.bounds()),
+            Sdf::SmoothUnion(a, b, k) => {
+                let mut result = a.bounds().union(&b.bounds());
+                result.min -= Vector3::new(*k, *k, *k);
+                result.max += Vector3::new(*k, *k, *k);
+                result
+            },
+            Sdf::Intersection(a, _) | Sdf::Subtraction(a, _) | Sdf::SmoothSubtraction(a, _, _) => a.bounds(),
+            Sdf::Repeat(..) => infinite,
+            Sdf::Twist(_, s) => {
+                // any rotation around y stays within the xz radius
+                let b = s.bounds();
+                let r = b.min.x.abs().max(b.max.x.abs()).hypot(b.min.z.abs().max(b.max.z.abs()));
+                Aabb::new(Vector3::new(-r, b.min.y, -r), Vector3::new(r, b.max.y, r))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod sdf_test {
+    use super::Sdf;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_primitives() {
+        let p = Vector3::new(0.0, 3.0, 0.0);
+        assert!((Sdf::sphere(1.0).distance(p) - 2.0).abs() < 1e-12);
+        assert!((Sdf::cuboid(Vector3::new(1.0, 1.0, 1.0)).distance(p) - 2.0).abs() < 1e-12);
+        assert!((Sdf::torus(2.0, 0.5).distance(Vector3::new(2.0, 0.0, 0.0)) + 0.5).abs() < 1e-12);
+        assert!((Sdf::sphere(1.0).translate(Vector3::new(0.0, 3.0, 0.0)).distance(p) + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_smooth_union_blends() {
+        let a = Sdf::sphere(1.0).translate(Vector3::new(-1.0, 0.0, 0.0));
+        let b = Sdf::sphere(1.0).translate(Vector3::new(1.0, 0.0, 0.0));
+        let p = Vector3::new(0.0, 1.0, 0.0);
+        let hard = a.distance(p).min(b.distance(p));
+        assert!(a.smooth_union(b, 0.5).distance(p) < hard);
+    }
+}
@@ -0,0 +1,185 @@
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+
+use super::sdf::Sdf;
+use crate::ray::Ray;
+use crate::accel::Aabb;
+use crate::material::{Material, NaiveMaterial};
+use crate::medium::HomogeneousMedium;
+use crate::object::{IntersectResult, IntersectDirection, Primitive};
+use crate::object::mesh::Point;
+
+/// Surface of a signed distance field, intersected by sphere tracing
+pub struct SdfObject {
+    pub sdf: Sdf,
+    pub material: Box<dyn Material>,
+    pub interior: Option<HomogeneousMedium>,
+
+    // distance to the surface counted as a hit
+    pub epsilon: f64,
+    pub max_steps: u32,
+    // rays give up past this distance, needed for repeated fields
+    pub max_distance: f64,
+    // fraction of the distance bound actually stepped, below 1 for inexact fields like twists
+    pub step_scale: f64,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Sdf) -> SdfObject {
+        SdfObject {
+            sdf,
+            material: Box::new(NaiveMaterial::default()) as Box<dyn Material>,
+            interior: None,
+
+            epsilon: 1e-4,
+            max_steps: 256,
+            max_distance: 100.0,
+            step_scale: 1.0,
+        }
+    }
+
+    pub fn set_material(&mut self, material: Box<dyn Material>) -> &mut SdfObject {
+        self.material = material;
+
+        self
+    }
+
+    pub fn set_interior(&mut self, medium: HomogeneousMedium) -> &mut SdfObject {
+        self.interior = Some(medium);
+
+        self
+    }
+
+    pub fn set_step_scale(&mut self, value: f64) -> &mut SdfObject {
+        self.step_scale = value;
+
+        self
+    }
+
+    /// Outward normal from the gradient, four evaluations on a tetrahedron
+    pub fn normal(&self, p: Vector3<f64>) -> Vector3<f64> {
+        let h = self.epsilon;
+        let k = [
+            Vector3::new(1.0, -1.0, -1.0),
+            Vector3::new(-1.0, -1.0, 1.0),
+            Vector3::new(-1.0, 1.0, -1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        ];
+        let gradient = k.iter().fold(Vector3::new(0.0, 0.0, 0.0), |acc, k| acc + k * self.sdf.distance(p + k * h));
+
+        gradient.normalize()
+    }
+}
+
+impl Primitive for SdfObject {
+    fn intersect(&self, ray: &Ray) -> IntersectResult<'_> {
+        let inv_dir = Vector3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        let t_max = (self.max_distance / ray.dir.magnitude()).min(ray.t_max);
+        let (t_enter, t_exit) = match self.bounds().intersect(ray, inv_dir, t_max) {
+            Some(range) => range,
+            None => return IntersectResult::no_intersect(),
+        };
+
+        // the march covers the part of the ray interval within the bounds
+        let t_start = t_enter.max(ray.t_min);
+        if t_start > t_exit {
+            return IntersectResult::no_intersect();
+        }
+
+        // which side the ray travels on, a ray leaving the surface decides by its direction
+        let start = ray.pos + t_start * ray.dir;
+        let d0 = self.sdf.distance(start);
+        let on_surface = d0.abs() < self.epsilon;
+        let inside = if on_surface { ray.dir.dot(self.normal(start)) < 0.0 } else { d0 < 0.0 };
+        let sign = if inside { -1.0 } else { 1.0 };
+
+        let speed = ray.dir.magnitude();
+        let mut t = t_start;
+        let mut leaving = on_surface;
+        for _ in 0..self.max_steps {
+            if t > t_exit {
+                break;
+            }
+            let p = ray.pos + t * ray.dir;
+            let d = sign * self.sdf.distance(p);
+            if d >= self.epsilon {
+                leaving = false;
+            } else if !leaving {
+                let normal = self.normal(p);
+                return IntersectResult {
+                    point: Some(Point {
+                        vertex: p,
+                        texture: Vector2::new(0.0, 0.0),
+                        normal,
+                        tangent: Vector3::zero(),
+                        color: None,
+                    }),
+                    direction: if inside { IntersectDirection::Negative } else { IntersectDirection::Positive },
+                    is_intersect: true,
+                    dis: t,
+                    object: Some(self),
+                    // the march stops anywhere within epsilon of the surface
+                    error: Vector3::new(self.epsilon, self.epsilon, self.epsilon),
+                    geometric_normal: normal,
+                    barycentric: Vector3::zero(),
+                    uv_sets: Vec::new(),
+                };
+            }
+            t += d.abs().max(self.epsilon) * self.step_scale / speed;
+        }
+
+        IntersectResult::no_intersect()
+    }
+
+    fn bounds(&self) -> Aabb {
+        let mut b = self.sdf.bounds();
+        // room for the hit tolerance
+        b.min -= Vector3::new(self.epsilon, self.epsilon, self.epsilon) * 2.0;
+        b.max += Vector3::new(self.epsilon, self.epsilon, self.epsilon) * 2.0;
+        b
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.as_ref()
+    }
+
+    fn interior(&self) -> Option<&HomogeneousMedium> {
+        self.interior.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod sdf_object_test {
+    use super::SdfObject;
+    use crate::sdf::Sdf;
+    use crate::object::{IntersectDirection, Primitive};
+    use crate::ray::Ray;
+    use cgmath::{Vector3, InnerSpace};
+
+    #[test]
+    fn test_sphere_tracing() {
+        let object = SdfObject::new(Sdf::sphere(1.0).translate(Vector3::new(0.0, 0.0, -5.0)));
+        let hit = object.intersect(&Ray::new_nz());
+        assert!(hit.is_intersect);
+        assert_eq!(hit.direction, IntersectDirection::Positive);
+        assert!((hit.dis - 4.0).abs() < 1e-3);
+
+        // continuing from the hit point the ray is inside and leaves on the far side
+        let inner = Ray {
+            pos: hit.point.unwrap().vertex,
+            ..Ray::new_nz()
+        };
+        let exit = object.intersect(&inner);
+        assert_eq!(exit.direction, IntersectDirection::Negative);
+        assert!((exit.dis - 2.0).abs() < 1e-3);
+        assert!((exit.point.unwrap().normal - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-3);
+
+        // the ray interval is honored at both ends
+        assert!(!object.intersect(&Ray::new_nz().with_t_max(3.9)).is_intersect);
+        let late = object.intersect(&Ray { t_min: 4.1, ..Ray::new_nz() });
+        assert!(late.is_intersect);
+        assert_eq!(late.direction, IntersectDirection::Negative);
+        assert!((late.dis - 6.0).abs() < 1e-3);
+        assert!(!object.intersect(&Ray { t_min: 6.1, ..Ray::new_nz() }).is_intersect);
+    }
+}
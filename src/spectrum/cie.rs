@@ -0,0 +1,69 @@
+use std::sync::OnceLock;
+
+use cgmath::Vector3;
+
+/// Range of wavelengths (in nm) sampled by the spectral renderer
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 720.0;
+
+fn lobe(lambda: f64, mu: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if lambda < mu { sigma_low } else { sigma_high };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 color matching functions, using the multi-lobe fit of Wyman et al. 2013
+pub fn color_matching(lambda: f64) -> Vector3<f64> {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+
+    Vector3::new(x, y, z)
+}
+
+/// Integrals of the matching functions over the sampled range
+fn cmf_integral() -> Vector3<f64> {
+    static INTEGRAL: OnceLock<Vector3<f64>> = OnceLock::new();
+    *INTEGRAL.get_or_init(|| {
+        let steps = 3400;
+        let dl = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let mut sum = Vector3::new(0.0, 0.0, 0.0);
+        for i in 0..steps {
+            sum += color_matching(LAMBDA_MIN + (i as f64 + 0.5) * dl) * dl;
+        }
+        sum
+    })
+}
+
+/// Picks a wavelength uniformly in the visible range, returns it with its pdf
+pub fn sample_wavelength(u: f64) -> (f64, f64) {
+    let lambda = LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN);
+
+    (lambda, 1.0 / (LAMBDA_MAX - LAMBDA_MIN))
+}
+
+/// Monte Carlo estimate of the XYZ color of a radiance sample taken at `lambda`
+pub fn wavelength_to_xyz(value: f64, lambda: f64, pdf: f64) -> Vector3<f64> {
+    color_matching(lambda) * (value / (pdf * cmf_integral().y))
+}
+
+fn xyz_to_linear_srgb(xyz: Vector3<f64>) -> Vector3<f64> {
+    Vector3::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+/// Converts XYZ to linear sRGB. The result is white balanced so that a flat
+/// spectrum of 1, which is what a white RGB color uplifts to, maps back to white.
+pub fn xyz_to_rgb(xyz: Vector3<f64>) -> Vector3<f64> {
+    let white = xyz_to_linear_srgb(cmf_integral() / cmf_integral().y);
+    let rgb = xyz_to_linear_srgb(xyz);
+
+    Vector3::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+}
@@ -0,0 +1,68 @@
+/// Wavelength dependent index of refraction
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    /// n = a + b / λ², λ in micrometers
+    Cauchy {
+        a: f64,
+        b: f64,
+    },
+    /// n² = 1 + Σ b_i λ² / (λ² - c_i), λ in micrometers
+    Sellmeier {
+        b: [f64; 3],
+        c: [f64; 3],
+    },
+}
+
+impl Dispersion {
+    /// Borosilicate crown glass
+    pub fn bk7() -> Dispersion {
+        Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    /// Dense flint glass, strongly dispersive
+    pub fn sf11() -> Dispersion {
+        Dispersion::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        }
+    }
+
+    pub fn water() -> Dispersion {
+        Dispersion::Cauchy {
+            a: 1.3199,
+            b: 0.00653,
+        }
+    }
+
+    /// Index of refraction at `lambda` nm
+    pub fn ior(&self, lambda: f64) -> f64 {
+        let l = lambda / 1000.0;
+        let l2 = l * l;
+        match *self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod dispersion_test {
+    use super::Dispersion;
+
+    #[test]
+    fn test_bk7() {
+        // sodium d line
+        assert!((Dispersion::bk7().ior(587.6) - 1.5168).abs() < 1e-3);
+        // blue bends more than red
+        assert!(Dispersion::sf11().ior(450.0) > Dispersion::sf11().ior(650.0));
+    }
+}
@@ -0,0 +1,10 @@
+pub mod cie;
+pub mod uplift;
+pub mod dispersion;
+#[allow(clippy::module_inception)]
+pub mod spectrum;
+
+pub use cie::{LAMBDA_MIN, LAMBDA_MAX, sample_wavelength, wavelength_to_xyz, xyz_to_rgb};
+pub use uplift::rgb_to_spectrum;
+pub use dispersion::Dispersion;
+pub use spectrum::Spectrum;
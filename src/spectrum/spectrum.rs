@@ -0,0 +1,51 @@
+use cgmath::Vector3;
+
+use super::uplift::rgb_to_spectrum;
+
+/// Emission spectrum of a light
+#[derive(Clone, Debug)]
+pub enum Spectrum {
+    /// a smooth spectrum matching an RGB color
+    Rgb(Vector3<f64>),
+    /// black body radiator at a temperature in kelvin, normalized to 1 at its peak
+    Blackbody(f64),
+    /// (wavelength in nm, value) pairs sorted by wavelength, linearly interpolated
+    Sampled(Vec<(f64, f64)>),
+}
+
+fn planck(lambda: f64, temperature: f64) -> f64 {
+    const C: f64 = 299792458.0;
+    const H: f64 = 6.62606957e-34;
+    const KB: f64 = 1.3806488e-23;
+
+    let l = lambda * 1e-9;
+    (2.0 * H * C * C) / (l.powi(5) * ((H * C / (l * KB * temperature)).exp() - 1.0))
+}
+
+impl Spectrum {
+    pub fn evaluate(&self, lambda: f64) -> f64 {
+        match *self {
+            Spectrum::Rgb(rgb) => rgb_to_spectrum(rgb, lambda),
+            Spectrum::Blackbody(temperature) => {
+                // Wien's displacement law gives the peak
+                let peak = 2.8977721e-3 / temperature * 1e9;
+                planck(lambda, temperature) / planck(peak, temperature)
+            },
+            Spectrum::Sampled(ref samples) => {
+                if samples.is_empty() {
+                    return 0.0;
+                }
+                if lambda <= samples[0].0 {
+                    return samples[0].1;
+                }
+                for w in samples.windows(2) {
+                    if lambda <= w[1].0 {
+                        let t = (lambda - w[0].0) / (w[1].0 - w[0].0);
+                        return w[0].1 + t * (w[1].1 - w[0].1);
+                    }
+                }
+                samples[samples.len() - 1].1
+            },
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use cgmath::Vector3;
+
+use super::cie::{LAMBDA_MIN, LAMBDA_MAX};
+
+// Basis spectra of Smits, "An RGB to Spectrum Conversion for Reflectances" (1999),
+// 10 bins spanning the sampled range
+
+const WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// Value at `lambda` (nm) of a smooth spectrum whose color is `rgb`
+pub fn rgb_to_spectrum(rgb: Vector3<f64>, lambda: f64) -> f64 {
+    let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+    let i = ((t * 10.0) as isize).clamp(0, 9) as usize;
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+
+    if r <= g && r <= b {
+        let mut v = r * WHITE[i];
+        if g <= b {
+            v += (g - r) * CYAN[i] + (b - g) * BLUE[i];
+        } else {
+            v += (b - r) * CYAN[i] + (g - b) * GREEN[i];
+        }
+        v
+    } else if g <= r && g <= b {
+        let mut v = g * WHITE[i];
+        if r <= b {
+            v += (r - g) * MAGENTA[i] + (b - r) * BLUE[i];
+        } else {
+            v += (b - g) * MAGENTA[i] + (r - b) * RED[i];
+        }
+        v
+    } else {
+        let mut v = b * WHITE[i];
+        if r <= g {
+            v += (r - b) * YELLOW[i] + (g - r) * GREEN[i];
+        } else {
+            v += (g - b) * YELLOW[i] + (r - g) * RED[i];
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod uplift_test {
+    use super::rgb_to_spectrum;
+    use crate::spectrum::{sample_wavelength, wavelength_to_xyz, xyz_to_rgb};
+    use cgmath::Vector3;
+
+    fn round_trip(rgb: Vector3<f64>) -> Vector3<f64> {
+        let n = 2000;
+        let mut xyz = Vector3::new(0.0, 0.0, 0.0);
+        for i in 0..n {
+            let (lambda, pdf) = sample_wavelength((i as f64 + 0.5) / n as f64);
+            xyz += wavelength_to_xyz(rgb_to_spectrum(rgb, lambda), lambda, pdf);
+        }
+        xyz_to_rgb(xyz / n as f64)
+    }
+
+    #[test]
+    fn test_white_round_trip() {
+        let rgb = round_trip(Vector3::new(1.0, 1.0, 1.0));
+        for i in 0..3 {
+            assert!((rgb[i] - 1.0).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_red_stays_red() {
+        let rgb = round_trip(Vector3::new(1.0, 0.0, 0.0));
+        assert!(rgb.x > 0.5);
+        assert!(rgb.x > 4.0 * rgb.y.abs());
+        assert!(rgb.x > 4.0 * rgb.z.abs());
+    }
+}
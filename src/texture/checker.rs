@@ -0,0 +1,71 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue};
+
+/// Alternates between two values on a regular lattice of cells
+pub struct CheckerTexture<T> {
+    pub a: T,
+    pub b: T,
+    // cells per unit length
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> CheckerTexture<T> {
+    pub fn new(a: T, b: T) -> CheckerTexture<T> {
+        CheckerTexture {
+            a,
+            b,
+            scale: 1.0,
+            space: TextureSpace::Uv,
+        }
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+}
+
+impl<T: TextureValue> Texture<T> for CheckerTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let c = self.space.coordinate(p) * self.scale;
+        let sum = c.x.floor() as i64 + c.y.floor() as i64 + c.z.floor() as i64;
+
+        if sum.rem_euclid(2) == 0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+#[cfg(test)]
+mod checker_test {
+    use super::CheckerTexture;
+    use crate::texture::{Texture, TexturePoint, TextureSpace};
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_checker_uv() {
+        let mut checker = CheckerTexture::new(0.0, 1.0);
+        checker.set_scale(10.0);
+        assert_eq!(checker.evaluate(&TexturePoint::from_uv(0.05, 0.05)), 0.0);
+        assert_eq!(checker.evaluate(&TexturePoint::from_uv(0.15, 0.05)), 1.0);
+        assert_eq!(checker.evaluate(&TexturePoint::from_uv(0.15, 0.15)), 0.0);
+    }
+
+    #[test]
+    fn test_checker_world_negative() {
+        let mut checker = CheckerTexture::new(0.0, 1.0);
+        checker.set_space(TextureSpace::World);
+        let mut p = TexturePoint::from_uv(0.0, 0.0);
+        p.world = Vector3::new(-0.5, 0.5, 0.5);
+        assert_eq!(checker.evaluate(&p), 1.0);
+    }
+}
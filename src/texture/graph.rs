@@ -0,0 +1,329 @@
+use cgmath::Vector3;
+use serde_json::{Map, Value};
+
+use crate::texture::{
+    Texture, TexturePoint, TextureValue, TextureSpace, CheckerTexture, GridTexture, NoiseTexture, NoiseKind,
+    VoronoiTexture, VoronoiFeature, MarbleTexture, WoodTexture, VertexColorTexture, ImageTexture,
+};
+use crate::texture::nodes::{Multiply, Mix, Remap, ColorRamp, Invert, Gray};
+
+// Builds shading graphs from a JSON description, e.g.
+//
+// {
+//     "type": "multiply",
+//     "a": "images/TexturesCom_Metal_Threadplate3_1K_albedo.tif",
+//     "b": { "type": "remap", "input": "images/TexturesCom_Metal_Threadplate3_1K_ao.tif", "to": [0.3, 1.0] }
+// }
+//
+// A number or an [r, g, b] array is a constant, a string is an image file.
+// Images repeat outside [0, 1] and are filtered, see `ImageTexture`; scalar
+// graphs read their luma.
+// Procedural textures take a "space" of "uv", "uv1", "uv2", ..., "object" or "world".
+
+/// Values a graph can be built for
+pub trait GraphValue: TextureValue + 'static {
+    fn parse_constant(value: &Value) -> Option<Self>;
+
+    fn image(path: &str) -> Result<Box<dyn Texture<Self>>, String>;
+
+    /// Adapts a scalar texture, e.g. a remap, to this value type
+    fn from_scalar(input: Box<dyn Texture<f64>>) -> Box<dyn Texture<Self>>;
+
+    fn vertex_color(fallback: Vector3<f64>) -> Box<dyn Texture<Self>>;
+}
+
+impl GraphValue for f64 {
+    fn parse_constant(value: &Value) -> Option<Self> {
+        value.as_f64()
+    }
+
+    fn image(path: &str) -> Result<Box<dyn Texture<Self>>, String> {
+        Ok(Box::new(ImageTexture::try_from_file_gray(path)?))
+    }
+
+    fn from_scalar(input: Box<dyn Texture<f64>>) -> Box<dyn Texture<Self>> {
+        input
+    }
+
+    fn vertex_color(fallback: Vector3<f64>) -> Box<dyn Texture<Self>> {
+        Box::new(VertexColorTexture::new(fallback))
+    }
+}
+
+impl GraphValue for Vector3<f64> {
+    fn parse_constant(value: &Value) -> Option<Self> {
+        if let Some(v) = value.as_f64() {
+            return Some(Vector3::new(v, v, v));
+        }
+        let arr = value.as_array()?;
+        if arr.len() != 3 {
+            return None;
+        }
+
+        Some(Vector3::new(arr[0].as_f64()?, arr[1].as_f64()?, arr[2].as_f64()?))
+    }
+
+    fn image(path: &str) -> Result<Box<dyn Texture<Self>>, String> {
+        Ok(Box::new(ImageTexture::try_from_file(path)?))
+    }
+
+    fn from_scalar(input: Box<dyn Texture<f64>>) -> Box<dyn Texture<Self>> {
+        Box::new(Gray { input })
+    }
+
+    fn vertex_color(fallback: Vector3<f64>) -> Box<dyn Texture<Self>> {
+        Box::new(VertexColorTexture::new(fallback))
+    }
+}
+
+fn field<'a>(node: &'a Map<String, Value>, name: &str) -> Result<&'a Value, String> {
+    node.get(name).ok_or(format!("texture node is missing \"{}\"", name))
+}
+
+fn number(node: &Map<String, Value>, name: &str, default: f64) -> Result<f64, String> {
+    match node.get(name) {
+        None => Ok(default),
+        Some(v) => v.as_f64().ok_or(format!("\"{}\" should be a number", name)),
+    }
+}
+
+fn constant<T: GraphValue>(node: &Map<String, Value>, name: &str) -> Result<T, String> {
+    T::parse_constant(field(node, name)?).ok_or(format!("\"{}\" should be a constant", name))
+}
+
+fn range(node: &Map<String, Value>, name: &str) -> Result<(f64, f64), String> {
+    match node.get(name) {
+        None => Ok((0.0, 1.0)),
+        Some(v) => {
+            let pair = v.as_array()
+                .filter(|a| a.len() == 2)
+                .and_then(|a| Some((a[0].as_f64()?, a[1].as_f64()?)));
+            pair.ok_or(format!("\"{}\" should be a [min, max] pair", name))
+        }
+    }
+}
+
+fn space(node: &Map<String, Value>, default: TextureSpace) -> Result<TextureSpace, String> {
+    match node.get("space").and_then(|v| v.as_str()) {
+        None => Ok(default),
+        Some("uv") => Ok(TextureSpace::Uv),
+        Some("object") => Ok(TextureSpace::Object),
+        Some("world") => Ok(TextureSpace::World),
+        // the other texture coordinate sets, "uv1" is the first after `uv`
+        Some(s) => match s.strip_prefix("uv").and_then(|i| i.parse().ok()) {
+            Some(index) => Ok(TextureSpace::UvSet(index)),
+            None => Err(format!("unknown texture space \"{}\"", s)),
+        },
+    }
+}
+
+fn seed(node: &Map<String, Value>) -> u32 {
+    node.get("seed").and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+fn noise_kind(node: &Map<String, Value>) -> Result<NoiseKind, String> {
+    let octaves = node.get("octaves").and_then(|v| v.as_u64()).unwrap_or(6) as u32;
+    let lacunarity = number(node, "lacunarity", 2.0)?;
+    let gain = number(node, "gain", 0.5)?;
+
+    match node.get("kind").and_then(|v| v.as_str()).unwrap_or("perlin") {
+        "perlin" => Ok(NoiseKind::Perlin),
+        "simplex" => Ok(NoiseKind::Simplex),
+        "fbm" => Ok(NoiseKind::Fbm { octaves, lacunarity, gain }),
+        "turbulence" => Ok(NoiseKind::Turbulence { octaves, lacunarity, gain }),
+        s => Err(format!("unknown noise kind \"{}\"", s)),
+    }
+}
+
+/// Parses a texture (or a whole graph) producing values of type `T`
+pub fn parse_texture<T: GraphValue>(value: &Value) -> Result<Box<dyn Texture<T>>, String> {
+    if let Some(c) = T::parse_constant(value) {
+        return Ok(Box::new(ConstantTexture(c)));
+    }
+    if let Some(path) = value.as_str() {
+        return T::image(path);
+    }
+    let node = value.as_object().ok_or(format!("cannot parse texture from {}", value))?;
+    let node_type = field(node, "type")?.as_str().ok_or("texture \"type\" should be a string")?;
+
+    let texture: Box<dyn Texture<T>> = match node_type {
+        "constant" => Box::new(ConstantTexture(constant::<T>(node, "value")?)),
+        "image" => {
+            let path = field(node, "file")?.as_str().ok_or("\"file\" should be a string")?;
+            T::image(path)?
+        },
+        "vertex_color" => {
+            let fallback = match node.get("fallback") {
+                None => Vector3::new(1.0, 1.0, 1.0),
+                Some(v) => Vector3::parse_constant(v).ok_or("\"fallback\" should be a color")?,
+            };
+            T::vertex_color(fallback)
+        },
+        "multiply" => Box::new(Multiply {
+            a: parse_texture(field(node, "a")?)?,
+            b: parse_texture(field(node, "b")?)?,
+        }),
+        "mix" => Box::new(Mix {
+            a: parse_texture(field(node, "a")?)?,
+            b: parse_texture(field(node, "b")?)?,
+            factor: parse_texture(field(node, "factor")?)?,
+        }),
+        "invert" => Box::new(Invert {
+            input: parse_texture(field(node, "input")?)?,
+        }),
+        "remap" => {
+            let (from_min, from_max) = range(node, "from")?;
+            let (to_min, to_max) = range(node, "to")?;
+            let remap = Remap {
+                input: parse_texture(field(node, "input")?)?,
+                from_min,
+                from_max,
+                to_min,
+                to_max,
+                clamp: node.get("clamp").and_then(|v| v.as_bool()).unwrap_or(true),
+            };
+            T::from_scalar(Box::new(remap))
+        },
+        "color_ramp" => {
+            let stops_value = field(node, "stops")?.as_array().ok_or("\"stops\" should be an array")?;
+            let mut stops = Vec::new();
+            for stop in stops_value.iter() {
+                let pair = stop.as_array()
+                    .filter(|a| a.len() == 2)
+                    .and_then(|a| Some((a[0].as_f64()?, T::parse_constant(&a[1])?)));
+                stops.push(pair.ok_or("a color ramp stop should be [position, value]")?);
+            }
+            if stops.is_empty() {
+                return Err(String::from("color ramp needs at least one stop"));
+            }
+            let input: Box<dyn Texture<f64>> = parse_texture(field(node, "input")?)?;
+            Box::new(ColorRamp::new(input, stops))
+        },
+        "checker" => {
+            let mut t = CheckerTexture::new(constant::<T>(node, "a")?, constant::<T>(node, "b")?);
+            t.set_scale(number(node, "scale", 1.0)?).set_space(space(node, TextureSpace::Uv)?);
+            Box::new(t)
+        },
+        "grid" => {
+            let mut t = GridTexture::new(constant::<T>(node, "line")?, constant::<T>(node, "background")?);
+            t.set_scale(number(node, "scale", 1.0)?)
+                .set_line_width(number(node, "line_width", 0.05)?)
+                .set_space(space(node, TextureSpace::Uv)?);
+            Box::new(t)
+        },
+        "noise" => {
+            let mut t = NoiseTexture::new(constant::<T>(node, "low")?, constant::<T>(node, "high")?, noise_kind(node)?);
+            t.set_seed(seed(node))
+                .set_scale(number(node, "scale", 1.0)?)
+                .set_space(space(node, TextureSpace::Uv)?);
+            Box::new(t)
+        },
+        "voronoi" => {
+            let feature = match node.get("feature").and_then(|v| v.as_str()).unwrap_or("f1") {
+                "f1" => VoronoiFeature::F1,
+                "f2" => VoronoiFeature::F2,
+                "f2-f1" => VoronoiFeature::F2MinusF1,
+                s => return Err(format!("unknown voronoi feature \"{}\"", s)),
+            };
+            let mut t = VoronoiTexture::new(constant::<T>(node, "low")?, constant::<T>(node, "high")?, feature);
+            t.set_seed(seed(node))
+                .set_scale(number(node, "scale", 1.0)?)
+                .set_space(space(node, TextureSpace::Uv)?);
+            Box::new(t)
+        },
+        "marble" => {
+            let mut t = MarbleTexture::new(constant::<T>(node, "a")?, constant::<T>(node, "b")?);
+            t.frequency = number(node, "frequency", t.frequency)?;
+            t.distortion = number(node, "distortion", t.distortion)?;
+            t.set_seed(seed(node))
+                .set_scale(number(node, "scale", 1.0)?)
+                .set_space(space(node, TextureSpace::Object)?);
+            Box::new(t)
+        },
+        "wood" => {
+            let mut t = WoodTexture::new(constant::<T>(node, "a")?, constant::<T>(node, "b")?);
+            t.ring_frequency = number(node, "ring_frequency", t.ring_frequency)?;
+            t.distortion = number(node, "distortion", t.distortion)?;
+            t.set_seed(seed(node))
+                .set_scale(number(node, "scale", 1.0)?)
+                .set_space(space(node, TextureSpace::Object)?);
+            Box::new(t)
+        },
+        s => return Err(format!("unknown texture type \"{}\"", s)),
+    };
+
+    Ok(texture)
+}
+
+struct ConstantTexture<T>(T);
+
+impl<T: TextureValue> Texture<T> for ConstantTexture<T> {
+    fn evaluate(&self, _p: &TexturePoint) -> T {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod graph_test {
+    use super::parse_texture;
+    use crate::material::MaterialValue1;
+    use crate::texture::{Texture, TexturePoint};
+    use cgmath::{InnerSpace, Vector3};
+
+    #[test]
+    fn test_parse_graph() {
+        let json = serde_json::json!({
+            "type": "mix",
+            "a": [1.0, 0.0, 0.0],
+            "b": { "type": "invert", "input": [1.0, 1.0, 0.0] },
+            "factor": { "type": "remap", "input": 0.5, "from": [0.0, 2.0] }
+        });
+        let texture = parse_texture::<Vector3<f64>>(&json).unwrap();
+        let value = texture.evaluate(&TexturePoint::from_uv(0.0, 0.0));
+        assert_eq!(value, Vector3::new(0.75, 0.0, 0.25));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let json = serde_json::json!({ "type": "multiply", "a": 1.0 });
+        assert!(parse_texture::<f64>(&json).is_err());
+
+        // missing images are reported, not a panic
+        let missing = std::env::temp_dir().join("hakaze_graph_test_missing.png");
+        let json = serde_json::json!({ "type": "remap", "input": missing.to_str().unwrap() });
+        let err = parse_texture::<Vector3<f64>>(&json).err().unwrap();
+        assert!(err.contains("hakaze_graph_test_missing.png"));
+
+        // as are files that are not images
+        let corrupt = std::env::temp_dir().join(format!("hakaze_graph_test_{}.png", std::process::id()));
+        std::fs::write(&corrupt, b"not a png").unwrap();
+        let result = parse_texture::<f64>(&serde_json::json!({ "type": "image", "file": corrupt.to_str().unwrap() }));
+        std::fs::remove_file(&corrupt).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_image_repeats() {
+        // black on the left half, white on the right
+        let path = std::env::temp_dir().join(format!("hakaze_graph_image_{}.png", std::process::id()));
+        image::RgbImage::from_fn(2, 1, |x, _| image::Rgb([255 * x as u8; 3])).save(&path).unwrap();
+        let json = serde_json::json!({ "type": "image", "file": path.to_str().unwrap() });
+        let scalar = parse_texture::<f64>(&json).unwrap();
+        let color = parse_texture::<Vector3<f64>>(&json).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // tiled uvs sample like their fraction, as a scalar and as a color
+        for (u, expected) in [(0.25, 0.0), (1.25, 0.0), (1.75, 1.0), (-0.25, 1.0)] {
+            let p = TexturePoint::from_uv(u, 3.5);
+            assert!((scalar.evaluate(&p) - expected).abs() < 1e-9);
+            assert!((color.evaluate(&p) - Vector3::new(expected, expected, expected)).magnitude() < 1e-9);
+        }
+        let edge = TexturePoint::from_uv(1.0, 1.0);
+        assert!((scalar.evaluate(&edge) - 0.5).abs() < 1e-9);
+
+        // scalar images kept as materials wrap as well
+        let gray = MaterialValue1::Image(image::GrayImage::from_fn(2, 2, |x, _| image::Luma([255 * x as u8])));
+        assert_eq!(gray.get_value(&TexturePoint::from_uv(1.0, 1.0)), 0.0);
+        assert_eq!(gray.get_value(&TexturePoint::from_uv(2.75, -0.5)), 1.0);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue};
+
+/// Thin lines on a regular lattice over a background value
+pub struct GridTexture<T> {
+    pub line: T,
+    pub background: T,
+    // cells per unit length
+    pub scale: f64,
+    // width of a line relative to the cell size, in [0, 1]
+    pub line_width: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> GridTexture<T> {
+    pub fn new(line: T, background: T) -> GridTexture<T> {
+        GridTexture {
+            line,
+            background,
+            scale: 1.0,
+            line_width: 0.05,
+            space: TextureSpace::Uv,
+        }
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_line_width(&mut self, line_width: f64) -> &mut Self {
+        self.line_width = line_width;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+}
+
+impl<T: TextureValue> Texture<T> for GridTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let c = self.space.coordinate(p) * self.scale;
+        // uv has no third axis, a lattice plane at w = 0 would cover everything
+        let axes = if matches!(self.space, TextureSpace::Uv | TextureSpace::UvSet(_)) { 2 } else { 3 };
+        let half = self.line_width / 2.0;
+
+        let on_line = (0..axes).any(|i| {
+            let f = c[i] - c[i].floor();
+            f < half || f > 1.0 - half
+        });
+
+        if on_line {
+            self.line
+        } else {
+            self.background
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::rc::Rc;
+
+use cgmath::Vector3;
+use image::{DynamicImage, RgbImage};
+use image::io::Reader as ImageReader;
+
+use crate::texture::{Texture, TexturePoint};
+
+/// Bilinearly filtered image addressed by uv, repeating outside [0, 1].
+/// v points up, so v = 0 is the bottom row. The image is shared, several
+/// textures can read different channels of it
+pub struct ImageTexture {
+    pub image: Rc<RgbImage>,
+    // channel read when used as a scalar texture
+    pub channel: usize,
+    // texture coordinate set addressing the image, see `TexturePoint::uv_set`
+    pub uv_set: usize,
+}
+
+fn read_image(name: &str) -> Result<DynamicImage, String> {
+    ImageReader::open(name)
+        .map_err(|e| format!("cannot open image {}: {}", name, e))?
+        .decode()
+        .map_err(|e| format!("cannot decode image {}: {}", name, e))
+}
+
+impl ImageTexture {
+    pub fn new(image: Rc<RgbImage>) -> ImageTexture {
+        ImageTexture {
+            image,
+            channel: 0,
+            uv_set: 0,
+        }
+    }
+
+    /// Reads an image file, failing when it is missing or can't be decoded
+    pub fn try_from_file(name: &str) -> Result<ImageTexture, String> {
+        Ok(ImageTexture::new(Rc::new(read_image(name)?.to_rgb8())))
+    }
+
+    /// Like `try_from_file` with the image turned to gray first, so every
+    /// channel holds its luma
+    pub fn try_from_file_gray(name: &str) -> Result<ImageTexture, String> {
+        let gray = read_image(name)?.to_luma8();
+        Ok(ImageTexture::new(Rc::new(DynamicImage::ImageLuma8(gray).to_rgb8())))
+    }
+
+    pub fn set_channel(&mut self, channel: usize) -> &mut Self {
+        self.channel = channel;
+
+        self
+    }
+
+    pub fn set_uv_set(&mut self, uv_set: usize) -> &mut Self {
+        self.uv_set = uv_set;
+
+        self
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vector3<f64> {
+        let width = self.image.width() as i64;
+        let height = self.image.height() as i64;
+        let pixel = self.image.get_pixel(x.rem_euclid(width) as u32, y.rem_euclid(height) as u32);
+
+        Vector3::new(pixel.0[0] as f64, pixel.0[1] as f64, pixel.0[2] as f64) / 255.0
+    }
+
+    fn sample(&self, p: &TexturePoint) -> Vector3<f64> {
+        // texel centers are at half integers
+        let uv = p.uv_set(self.uv_set);
+        let x = uv.x * self.image.width() as f64 - 0.5;
+        let y = (1.0 - uv.y) * self.image.height() as f64 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+impl Texture<Vector3<f64>> for ImageTexture {
+    fn evaluate(&self, p: &TexturePoint) -> Vector3<f64> {
+        self.sample(p)
+    }
+}
+
+impl Texture<f64> for ImageTexture {
+    fn evaluate(&self, p: &TexturePoint) -> f64 {
+        self.sample(p)[self.channel]
+    }
+}
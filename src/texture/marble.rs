@@ -0,0 +1,58 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue, Noise};
+
+/// Sine veins along the x axis, distorted by turbulence
+pub struct MarbleTexture<T> {
+    pub a: T,
+    pub b: T,
+    // veins per unit length along x
+    pub frequency: f64,
+    // how much turbulence bends the veins
+    pub distortion: f64,
+    pub octaves: u32,
+    pub noise: Noise,
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> MarbleTexture<T> {
+    pub fn new(a: T, b: T) -> MarbleTexture<T> {
+        MarbleTexture {
+            a,
+            b,
+            frequency: 4.0,
+            distortion: 6.0,
+            octaves: 5,
+            noise: Noise::default(),
+            scale: 1.0,
+            space: TextureSpace::Object,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) -> &mut Self {
+        self.noise = Noise::new(seed);
+
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+}
+
+impl<T: TextureValue> Texture<T> for MarbleTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let c = self.space.coordinate(p) * self.scale;
+        let turbulence = self.noise.turbulence(c, self.octaves, 2.0, 0.5);
+        let t = 0.5 + 0.5 * (self.frequency * c.x + self.distortion * turbulence).sin();
+
+        self.a.mix(self.b, t)
+    }
+}
@@ -0,0 +1,29 @@
+#[allow(clippy::module_inception)]
+pub mod texture;
+pub mod noise;
+pub mod checker;
+pub mod grid;
+pub mod noise_texture;
+pub mod voronoi;
+pub mod marble;
+pub mod wood;
+pub mod nodes;
+pub mod graph;
+pub mod image_texture;
+pub mod vertex_color;
+
+pub use texture::Texture;
+pub use texture::TexturePoint;
+pub use texture::TextureSpace;
+pub use texture::TextureValue;
+pub use noise::Noise;
+pub use checker::CheckerTexture;
+pub use grid::GridTexture;
+pub use noise_texture::{NoiseTexture, NoiseKind};
+pub use voronoi::{VoronoiTexture, VoronoiFeature};
+pub use marble::MarbleTexture;
+pub use wood::WoodTexture;
+pub use nodes::{Multiply, Mix, Remap, ColorRamp, Invert, Gray};
+pub use graph::parse_texture;
+pub use image_texture::ImageTexture;
+pub use vertex_color::VertexColorTexture;
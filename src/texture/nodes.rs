@@ -0,0 +1,205 @@
+use cgmath::Vector3;
+
+use crate::texture::{Texture, TexturePoint, TextureValue};
+
+// Shading graph nodes. Every input is itself a texture, so constants, images,
+// procedural sources and other nodes can be wired together freely.
+
+/// Channel-wise product of two inputs
+pub struct Multiply<T> {
+    pub a: Box<dyn Texture<T>>,
+    pub b: Box<dyn Texture<T>>,
+}
+
+impl<T: TextureValue> Multiply<T> {
+    pub fn new<A, B>(a: A, b: B) -> Multiply<T>
+        where A: Texture<T> + 'static, B: Texture<T> + 'static
+    {
+        Multiply {
+            a: Box::new(a),
+            b: Box::new(b),
+        }
+    }
+}
+
+impl<T: TextureValue> Texture<T> for Multiply<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        self.a.evaluate(p).product(self.b.evaluate(p))
+    }
+}
+
+/// Linear blend from `a` (factor 0) to `b` (factor 1)
+pub struct Mix<T> {
+    pub a: Box<dyn Texture<T>>,
+    pub b: Box<dyn Texture<T>>,
+    pub factor: Box<dyn Texture<f64>>,
+}
+
+impl<T: TextureValue> Mix<T> {
+    pub fn new<A, B, F>(a: A, b: B, factor: F) -> Mix<T>
+        where A: Texture<T> + 'static, B: Texture<T> + 'static, F: Texture<f64> + 'static
+    {
+        Mix {
+            a: Box::new(a),
+            b: Box::new(b),
+            factor: Box::new(factor),
+        }
+    }
+}
+
+impl<T: TextureValue> Texture<T> for Mix<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let t = self.factor.evaluate(p);
+        self.a.evaluate(p).mix(self.b.evaluate(p), t)
+    }
+}
+
+/// Maps a scalar from one range to another
+pub struct Remap {
+    pub input: Box<dyn Texture<f64>>,
+    pub from_min: f64,
+    pub from_max: f64,
+    pub to_min: f64,
+    pub to_max: f64,
+    pub clamp: bool,
+}
+
+impl Remap {
+    pub fn new<I: Texture<f64> + 'static>(input: I, from_min: f64, from_max: f64, to_min: f64, to_max: f64) -> Remap {
+        Remap {
+            input: Box::new(input),
+            from_min,
+            from_max,
+            to_min,
+            to_max,
+            clamp: true,
+        }
+    }
+
+    pub fn set_clamp(&mut self, clamp: bool) -> &mut Self {
+        self.clamp = clamp;
+
+        self
+    }
+}
+
+impl Texture<f64> for Remap {
+    fn evaluate(&self, p: &TexturePoint) -> f64 {
+        let range = self.from_max - self.from_min;
+        let mut t = if range.abs() < 1e-12 {
+            0.0
+        } else {
+            (self.input.evaluate(p) - self.from_min) / range
+        };
+        if self.clamp {
+            t = t.clamp(0.0, 1.0);
+        }
+
+        self.to_min + t * (self.to_max - self.to_min)
+    }
+}
+
+/// Piecewise linear lookup of a scalar input into a list of stops
+pub struct ColorRamp<T> {
+    pub input: Box<dyn Texture<f64>>,
+    // (position, value), sorted by position
+    pub stops: Vec<(f64, T)>,
+}
+
+impl<T: TextureValue> ColorRamp<T> {
+    pub fn new<I: Texture<f64> + 'static>(input: I, mut stops: Vec<(f64, T)>) -> ColorRamp<T> {
+        assert!(!stops.is_empty(), "color ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        ColorRamp {
+            input: Box::new(input),
+            stops,
+        }
+    }
+
+    pub fn lookup(&self, t: f64) -> T {
+        let first = self.stops[0];
+        if t <= first.0 {
+            return first.1;
+        }
+        for w in self.stops.windows(2) {
+            let (p0, v0) = w[0];
+            let (p1, v1) = w[1];
+            if t <= p1 {
+                let f = if p1 - p0 > 1e-12 { (t - p0) / (p1 - p0) } else { 1.0 };
+                return v0.mix(v1, f);
+            }
+        }
+
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+impl<T: TextureValue> Texture<T> for ColorRamp<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        self.lookup(self.input.evaluate(p))
+    }
+}
+
+/// One minus the input, per channel
+pub struct Invert<T> {
+    pub input: Box<dyn Texture<T>>,
+}
+
+impl<T: TextureValue> Invert<T> {
+    pub fn new<I: Texture<T> + 'static>(input: I) -> Invert<T> {
+        Invert {
+            input: Box::new(input),
+        }
+    }
+}
+
+impl<T: TextureValue> Texture<T> for Invert<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        T::splat(1.0) - self.input.evaluate(p)
+    }
+}
+
+/// Turns a scalar texture into a gray color, e.g. to multiply an albedo by an ao map
+pub struct Gray {
+    pub input: Box<dyn Texture<f64>>,
+}
+
+impl Gray {
+    pub fn new<I: Texture<f64> + 'static>(input: I) -> Gray {
+        Gray {
+            input: Box::new(input),
+        }
+    }
+}
+
+impl Texture<Vector3<f64>> for Gray {
+    fn evaluate(&self, p: &TexturePoint) -> Vector3<f64> {
+        Vector3::splat(self.input.evaluate(p))
+    }
+}
+
+#[cfg(test)]
+mod nodes_test {
+    use super::{ColorRamp, Invert, Mix, Multiply, Remap};
+    use crate::texture::{Texture, TexturePoint};
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_nodes() {
+        let p = TexturePoint::from_uv(0.0, 0.0);
+        assert_eq!(Multiply::new(0.5, 0.5).evaluate(&p), 0.25);
+        assert_eq!(Mix::new(0.0, 2.0, 0.25).evaluate(&p), 0.5);
+        assert_eq!(Remap::new(0.5, 0.0, 2.0, 10.0, 20.0).evaluate(&p), 12.5);
+        assert_eq!(Invert::new(Vector3::new(0.25, 0.5, 1.0)).evaluate(&p), Vector3::new(0.75, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_color_ramp() {
+        let ramp = ColorRamp::new(0.0, vec![(1.0, 10.0), (0.0, 0.0), (0.5, 2.0)]);
+        assert_eq!(ramp.lookup(-1.0), 0.0);
+        assert_eq!(ramp.lookup(0.25), 1.0);
+        assert_eq!(ramp.lookup(0.75), 6.0);
+        assert_eq!(ramp.lookup(2.0), 10.0);
+    }
+}
@@ -0,0 +1,266 @@
+use cgmath::{Vector3, InnerSpace};
+
+const GRAD3: [[f64; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+fn hash_u32(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Seeded gradient and cellular noise functions shared by the procedural textures
+#[derive(Clone)]
+pub struct Noise {
+    pub seed: u32,
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Noise {
+        let mut table: [u8; 256] = [0; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+
+        // Fisher-Yates shuffle driven by the seed
+        let mut state = hash_u32(seed);
+        for i in (1..256).rev() {
+            state = hash_u32(state.wrapping_add(i as u32));
+            let j = state as usize % (i + 1);
+            table.swap(i, j);
+        }
+
+        let mut perm: [u8; 512] = [0; 512];
+        for i in 0..512 {
+            perm[i] = table[i & 255];
+        }
+
+        Noise {
+            seed,
+            perm,
+        }
+    }
+
+    fn p(&self, i: usize) -> usize {
+        self.perm[i] as usize
+    }
+
+    /// Improved Perlin noise, roughly in [-1, 1]
+    pub fn perlin(&self, p: Vector3<f64>) -> f64 {
+        let fx = p.x.floor();
+        let fy = p.y.floor();
+        let fz = p.z.floor();
+        let xi = (fx as i64 & 255) as usize;
+        let yi = (fy as i64 & 255) as usize;
+        let zi = (fz as i64 & 255) as usize;
+        let x = p.x - fx;
+        let y = p.y - fy;
+        let z = p.z - fz;
+
+        let u = fade(x);
+        let v = fade(y);
+        let w = fade(z);
+
+        let a = self.p(xi) + yi;
+        let aa = self.p(a) + zi;
+        let ab = self.p(a + 1) + zi;
+        let b = self.p(xi + 1) + yi;
+        let ba = self.p(b) + zi;
+        let bb = self.p(b + 1) + zi;
+
+        lerp(w,
+             lerp(v,
+                  lerp(u, grad(self.perm[aa], x, y, z), grad(self.perm[ba], x - 1.0, y, z)),
+                  lerp(u, grad(self.perm[ab], x, y - 1.0, z), grad(self.perm[bb], x - 1.0, y - 1.0, z))),
+             lerp(v,
+                  lerp(u, grad(self.perm[aa + 1], x, y, z - 1.0), grad(self.perm[ba + 1], x - 1.0, y, z - 1.0)),
+                  lerp(u, grad(self.perm[ab + 1], x, y - 1.0, z - 1.0), grad(self.perm[bb + 1], x - 1.0, y - 1.0, z - 1.0))))
+    }
+
+    /// 3D simplex noise, roughly in [-1, 1]
+    pub fn simplex(&self, p: Vector3<f64>) -> f64 {
+        const F3: f64 = 1.0 / 3.0;
+        const G3: f64 = 1.0 / 6.0;
+
+        let s = (p.x + p.y + p.z) * F3;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let k = (p.z + s).floor();
+        let t = (i + j + k) * G3;
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+
+        // which simplex of the skewed cube we are in
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1, 0, 0, 1, 1, 0)
+            } else if x0 >= z0 {
+                (1, 0, 0, 1, 0, 1)
+            } else {
+                (0, 0, 1, 1, 0, 1)
+            }
+        } else if y0 < z0 {
+            (0, 0, 1, 0, 1, 1)
+        } else if x0 < z0 {
+            (0, 1, 0, 0, 1, 1)
+        } else {
+            (0, 1, 0, 1, 1, 0)
+        };
+
+        let corners = [
+            (0, 0, 0, Vector3::new(x0, y0, z0)),
+            (i1, j1, k1, Vector3::new(x0 - i1 as f64 + G3, y0 - j1 as f64 + G3, z0 - k1 as f64 + G3)),
+            (i2, j2, k2, Vector3::new(x0 - i2 as f64 + 2.0 * G3, y0 - j2 as f64 + 2.0 * G3, z0 - k2 as f64 + 2.0 * G3)),
+            (1, 1, 1, Vector3::new(x0 - 1.0 + 3.0 * G3, y0 - 1.0 + 3.0 * G3, z0 - 1.0 + 3.0 * G3)),
+        ];
+
+        let ii = (i as i64 & 255) as usize;
+        let jj = (j as i64 & 255) as usize;
+        let kk = (k as i64 & 255) as usize;
+
+        let mut sum = 0.0;
+        for &(ci, cj, ck, d) in corners.iter() {
+            let t = 0.6 - d.magnitude2();
+            if t < 0.0 {
+                continue;
+            }
+            let gi = self.p(ii + ci + self.p(jj + cj + self.p(kk + ck))) % 12;
+            let g = GRAD3[gi];
+            let t2 = t * t;
+            sum += t2 * t2 * (g[0] * d.x + g[1] * d.y + g[2] * d.z);
+        }
+
+        32.0 * sum
+    }
+
+    /// Fractional Brownian motion, a sum of `octaves` layers of Perlin noise
+    pub fn fbm(&self, p: Vector3<f64>, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut sum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.perlin(p * frequency);
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        sum
+    }
+
+    /// Like `fbm` but sums the absolute value of each octave
+    pub fn turbulence(&self, p: Vector3<f64>, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut sum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.perlin(p * frequency).abs();
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        sum
+    }
+
+    fn feature_point(&self, x: i64, y: i64, z: i64) -> Vector3<f64> {
+        let h = hash_u32(x as u32 ^ hash_u32(y as u32 ^ hash_u32(z as u32 ^ hash_u32(self.seed))));
+        let h2 = hash_u32(h);
+        Vector3::new(
+            x as f64 + (h & 0xffff) as f64 / 65536.0,
+            y as f64 + (h >> 16) as f64 / 65536.0,
+            z as f64 + (h2 & 0xffff) as f64 / 65536.0,
+        )
+    }
+
+    /// Worley (cellular) noise, distances to the nearest and second nearest feature points
+    pub fn worley(&self, p: Vector3<f64>) -> (f64, f64) {
+        let cx = p.x.floor() as i64;
+        let cy = p.y.floor() as i64;
+        let cz = p.z.floor() as i64;
+
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let d = (self.feature_point(cx + dx, cy + dy, cz + dz) - p).magnitude();
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+
+        (f1, f2)
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise::new(0)
+    }
+}
+
+#[cfg(test)]
+mod noise_test {
+    use super::Noise;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_perlin_zero_at_lattice() {
+        let noise = Noise::new(7);
+        assert_eq!(noise.perlin(Vector3::new(3.0, -2.0, 5.0)), 0.0);
+    }
+
+    #[test]
+    fn test_noise_range() {
+        let noise = Noise::new(1);
+        for i in 0..1000 {
+            let t = i as f64 * 0.137;
+            let p = Vector3::new(t, t * 0.71 - 3.0, 11.0 - t * 1.3);
+            assert!(noise.perlin(p).abs() <= 1.0);
+            assert!(noise.simplex(p).abs() <= 1.0);
+            let (f1, f2) = noise.worley(p);
+            assert!(f1 <= f2);
+        }
+    }
+
+    #[test]
+    fn test_seed_changes_noise() {
+        let p = Vector3::new(0.3, 0.6, 0.9);
+        assert_ne!(Noise::new(1).perlin(p), Noise::new(2).perlin(p));
+        assert_eq!(Noise::new(1).perlin(p), Noise::new(1).perlin(p));
+    }
+}
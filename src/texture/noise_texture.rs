@@ -0,0 +1,77 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue, Noise};
+
+#[derive(Clone, Copy, Debug)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+    Fbm {
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+    },
+    Turbulence {
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+    },
+}
+
+/// Blends between `low` and `high` using a noise function
+pub struct NoiseTexture<T> {
+    pub low: T,
+    pub high: T,
+    pub kind: NoiseKind,
+    pub noise: Noise,
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> NoiseTexture<T> {
+    pub fn new(low: T, high: T, kind: NoiseKind) -> NoiseTexture<T> {
+        NoiseTexture {
+            low,
+            high,
+            kind,
+            noise: Noise::default(),
+            scale: 1.0,
+            space: TextureSpace::Uv,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) -> &mut Self {
+        self.noise = Noise::new(seed);
+
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+
+    /// The blend factor in [0, 1]
+    pub fn factor(&self, p: &TexturePoint) -> f64 {
+        let c = self.space.coordinate(p) * self.scale;
+        let t = match self.kind {
+            NoiseKind::Perlin => 0.5 + 0.5 * self.noise.perlin(c),
+            NoiseKind::Simplex => 0.5 + 0.5 * self.noise.simplex(c),
+            NoiseKind::Fbm { octaves, lacunarity, gain } => 0.5 + 0.5 * self.noise.fbm(c, octaves, lacunarity, gain),
+            NoiseKind::Turbulence { octaves, lacunarity, gain } => self.noise.turbulence(c, octaves, lacunarity, gain),
+        };
+
+        t.clamp(0.0, 1.0)
+    }
+}
+
+impl<T: TextureValue> Texture<T> for NoiseTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        self.low.mix(self.high, self.factor(p))
+    }
+}
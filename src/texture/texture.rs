@@ -0,0 +1,151 @@
+use std::ops::{Add, Mul, Sub};
+
+use cgmath::{Vector2, Vector3, ElementWise};
+
+/// Everything a texture may need to know about the point being shaded
+#[derive(Clone, Debug)]
+pub struct TexturePoint {
+    pub uv: Vector2<f64>,
+    pub object: Vector3<f64>,
+    pub world: Vector3<f64>,
+    // direction of increasing u along the surface, zero when unknown
+    pub tangent: Vector3<f64>,
+    // interpolated vertex color, None when the surface has none
+    pub color: Option<Vector3<f64>>,
+    // texture coordinate sets after the first one, which is `uv`
+    pub uv_sets: Vec<Vector2<f64>>,
+}
+
+impl TexturePoint {
+    pub fn new(uv: Vector2<f64>, object: Vector3<f64>, world: Vector3<f64>) -> TexturePoint {
+        TexturePoint {
+            uv,
+            object,
+            world,
+            tangent: Vector3::new(0.0, 0.0, 0.0),
+            color: None,
+            uv_sets: Vec::new(),
+        }
+    }
+
+    pub fn with_tangent(self, tangent: Vector3<f64>) -> TexturePoint {
+        TexturePoint {
+            tangent,
+            ..self
+        }
+    }
+
+    pub fn with_color(self, color: Option<Vector3<f64>>) -> TexturePoint {
+        TexturePoint {
+            color,
+            ..self
+        }
+    }
+
+    pub fn with_uv_sets(self, uv_sets: Vec<Vector2<f64>>) -> TexturePoint {
+        TexturePoint {
+            uv_sets,
+            ..self
+        }
+    }
+
+    /// Texture coordinates of set `index`, 0 being `uv`. Sets the surface
+    /// doesn't have fall back to `uv`
+    pub fn uv_set(&self, index: usize) -> Vector2<f64> {
+        match index {
+            0 => self.uv,
+            i => self.uv_sets.get(i - 1).copied().unwrap_or(self.uv),
+        }
+    }
+
+    /// A point that only carries texture coordinates, object and world position are zero
+    pub fn from_uv(u: f64, v: f64) -> TexturePoint {
+        TexturePoint {
+            uv: Vector2::new(u, v),
+            object: Vector3::new(0.0, 0.0, 0.0),
+            world: Vector3::new(0.0, 0.0, 0.0),
+            tangent: Vector3::new(0.0, 0.0, 0.0),
+            color: None,
+            uv_sets: Vec::new(),
+        }
+    }
+}
+
+/// The coordinate system a procedural texture is evaluated in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureSpace {
+    /// (u, v, 0)
+    Uv,
+    /// (u, v, 0) of a texture coordinate set, see `TexturePoint::uv_set`
+    UvSet(usize),
+    Object,
+    World,
+}
+
+impl TextureSpace {
+    pub fn coordinate(&self, p: &TexturePoint) -> Vector3<f64> {
+        match *self {
+            TextureSpace::Uv => Vector3::new(p.uv.x, p.uv.y, 0.0),
+            TextureSpace::UvSet(index) => p.uv_set(index).extend(0.0),
+            TextureSpace::Object => p.object,
+            TextureSpace::World => p.world,
+        }
+    }
+}
+
+/// Values a texture can produce, scalars and colors
+pub trait TextureValue: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self> {
+    /// A value with every channel set to `value`
+    fn splat(value: f64) -> Self;
+
+    /// Channel-wise product
+    fn product(self, other: Self) -> Self;
+
+    fn mix(self, other: Self, t: f64) -> Self {
+        self * (1.0 - t) + other * t
+    }
+}
+
+impl TextureValue for f64 {
+    fn splat(value: f64) -> Self {
+        value
+    }
+
+    fn product(self, other: Self) -> Self {
+        self * other
+    }
+}
+
+impl TextureValue for Vector3<f64> {
+    fn splat(value: f64) -> Self {
+        Vector3::new(value, value, value)
+    }
+
+    fn product(self, other: Self) -> Self {
+        self.mul_element_wise(other)
+    }
+}
+
+pub trait Texture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T;
+}
+
+// constants can be plugged into any texture input
+
+impl Texture<f64> for f64 {
+    fn evaluate(&self, _p: &TexturePoint) -> f64 {
+        *self
+    }
+}
+
+impl Texture<Vector3<f64>> for Vector3<f64> {
+    fn evaluate(&self, _p: &TexturePoint) -> Vector3<f64> {
+        *self
+    }
+}
+
+impl<T, X: Texture<T> + ?Sized> Texture<T> for Box<X> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        (**self).evaluate(p)
+    }
+}
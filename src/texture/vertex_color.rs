@@ -0,0 +1,60 @@
+use cgmath::Vector3;
+
+use crate::texture::{Texture, TexturePoint};
+
+/// Color interpolated from the vertices of the surface, e.g. from
+/// `v x y z r g b` lines of an OBJ file. As a scalar it is the mean of the
+/// channels. Surfaces without vertex colors get `fallback`
+pub struct VertexColorTexture {
+    pub fallback: Vector3<f64>,
+}
+
+impl VertexColorTexture {
+    pub fn new(fallback: Vector3<f64>) -> VertexColorTexture {
+        VertexColorTexture {
+            fallback,
+        }
+    }
+}
+
+impl Texture<Vector3<f64>> for VertexColorTexture {
+    fn evaluate(&self, p: &TexturePoint) -> Vector3<f64> {
+        p.color.unwrap_or(self.fallback)
+    }
+}
+
+impl Texture<f64> for VertexColorTexture {
+    fn evaluate(&self, p: &TexturePoint) -> f64 {
+        let c: Vector3<f64> = self.evaluate(p);
+        (c.x + c.y + c.z) / 3.0
+    }
+}
+
+#[cfg(test)]
+mod vertex_color_test {
+    use crate::texture::{Texture, TexturePoint, parse_texture};
+    use cgmath::{Vector2, Vector3};
+
+    #[test]
+    fn test_vertex_color_and_uv_sets() {
+        let p = TexturePoint::from_uv(0.1, 0.1)
+            .with_color(Some(Vector3::new(0.2, 0.4, 0.6)))
+            .with_uv_sets(vec![Vector2::new(0.6, 0.1)]);
+
+        let color = parse_texture::<Vector3<f64>>(&serde_json::json!({ "type": "vertex_color" })).unwrap();
+        assert_eq!(color.evaluate(&p), Vector3::new(0.2, 0.4, 0.6));
+        assert_eq!(color.evaluate(&TexturePoint::from_uv(0.1, 0.1)), Vector3::new(1.0, 1.0, 1.0));
+        let gray = parse_texture::<f64>(&serde_json::json!({ "type": "vertex_color" })).unwrap();
+        assert!((gray.evaluate(&p) - 0.4).abs() < 1e-12);
+
+        // the same checker on the second uv set lands in the other cell
+        let checker = |space: &str| parse_texture::<f64>(&serde_json::json!({
+            "type": "checker", "a": 0.0, "b": 1.0, "scale": 2.0, "space": space,
+        })).unwrap();
+        assert_eq!(checker("uv").evaluate(&p), 0.0);
+        assert_eq!(checker("uv1").evaluate(&p), 1.0);
+        // a set the surface lacks falls back to uv
+        assert_eq!(checker("uv2").evaluate(&p), 0.0);
+        assert!(parse_texture::<f64>(&serde_json::json!({ "type": "checker", "a": 0.0, "b": 1.0, "space": "uvx" })).is_err());
+    }
+}
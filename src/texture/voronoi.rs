@@ -0,0 +1,66 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue, Noise};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VoronoiFeature {
+    // distance to the nearest feature point
+    F1,
+    // distance to the second nearest feature point
+    F2,
+    // cell borders
+    F2MinusF1,
+}
+
+/// Blends between `low` and `high` using Worley cellular noise
+pub struct VoronoiTexture<T> {
+    pub low: T,
+    pub high: T,
+    pub feature: VoronoiFeature,
+    pub noise: Noise,
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> VoronoiTexture<T> {
+    pub fn new(low: T, high: T, feature: VoronoiFeature) -> VoronoiTexture<T> {
+        VoronoiTexture {
+            low,
+            high,
+            feature,
+            noise: Noise::default(),
+            scale: 1.0,
+            space: TextureSpace::Uv,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) -> &mut Self {
+        self.noise = Noise::new(seed);
+
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+}
+
+impl<T: TextureValue> Texture<T> for VoronoiTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let c = self.space.coordinate(p) * self.scale;
+        let (f1, f2) = self.noise.worley(c);
+        let t = match self.feature {
+            VoronoiFeature::F1 => f1,
+            VoronoiFeature::F2 => f2,
+            VoronoiFeature::F2MinusF1 => f2 - f1,
+        };
+
+        self.low.mix(self.high, t.clamp(0.0, 1.0))
+    }
+}
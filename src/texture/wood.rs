@@ -0,0 +1,58 @@
+use crate::texture::{Texture, TexturePoint, TextureSpace, TextureValue, Noise};
+
+/// Concentric growth rings around the y axis, perturbed by noise
+pub struct WoodTexture<T> {
+    pub a: T,
+    pub b: T,
+    // rings per unit length
+    pub ring_frequency: f64,
+    // how much noise wobbles the rings
+    pub distortion: f64,
+    pub noise: Noise,
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+impl<T: TextureValue> WoodTexture<T> {
+    pub fn new(a: T, b: T) -> WoodTexture<T> {
+        WoodTexture {
+            a,
+            b,
+            ring_frequency: 8.0,
+            distortion: 0.1,
+            noise: Noise::default(),
+            scale: 1.0,
+            space: TextureSpace::Object,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) -> &mut Self {
+        self.noise = Noise::new(seed);
+
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    pub fn set_space(&mut self, space: TextureSpace) -> &mut Self {
+        self.space = space;
+
+        self
+    }
+}
+
+impl<T: TextureValue> Texture<T> for WoodTexture<T> {
+    fn evaluate(&self, p: &TexturePoint) -> T {
+        let c = self.space.coordinate(p) * self.scale;
+        let r = (c.x * c.x + c.z * c.z).sqrt() + self.distortion * self.noise.perlin(c * 4.0);
+        let ring = r * self.ring_frequency;
+        let t = ring - ring.floor();
+
+        // sharpen the late wood so the rings read as lines
+        self.a.mix(self.b, t * t)
+    }
+}
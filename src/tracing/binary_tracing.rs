@@ -1,7 +1,7 @@
 use cgmath::prelude::*;
 
 use super::tracing::Tracing;
-use image::{RgbImage, Rgb, ImageBuffer};
+use image::{RgbImage, Rgb};
 use crate::ray::Ray;
 use crate::scene::Scene;
 use crate::camera::Camera;
@@ -39,11 +39,11 @@ impl<'a> BinaryTracing<'a> {
     }
 
     fn trace_helper(&self, ray: &Ray) -> Rgb<u8> {
-        let intersect_result = self.scene.intersect(&ray);
-        return if intersect_result.is_intersect {
+        let intersect_result = self.scene.intersect(ray);
+        if intersect_result.is_intersect {
             let point = intersect_result.point.unwrap();
             // let cos = point.normal.dot(ray.dir.clone()).abs();
-            let cos = point.normal.normalize().dot(-ray.dir.clone());
+            let cos = point.normal.normalize().dot(-ray.dir);
             // println!("{}", cos);
             // Rgb([255, 0, 0])
             Rgb([(255.0 * cos) as u8, 0, 0])
@@ -1,7 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod tracing;
 pub mod binary_tracing;
 pub mod my_tracing;
+pub mod sequence;
 
 pub use tracing::Tracing;
 pub use binary_tracing::BinaryTracing;
-pub use my_tracing::MyTracing;
\ No newline at end of file
+pub use my_tracing::MyTracing;
+pub use my_tracing::BranchMode;
+pub use my_tracing::VolumeMode;
+pub use sequence::render_sequence;
@@ -1,31 +1,81 @@
 use cgmath::prelude::*;
 
 use super::tracing::Tracing;
-use image::{RgbImage, Rgb, ImageBuffer};
+use image::{RgbImage, Rgb};
 use crate::ray::Ray;
 use crate::scene::Scene;
 use crate::camera::Camera;
-use crate::object::IntersectDirection;
+use crate::object::{IntersectDirection, IntersectResult, Primitive};
+use crate::light::Light;
+use crate::medium::{HomogeneousMedium, HenyeyGreenstein, GridVolume};
+use crate::texture::TexturePoint;
+use crate::material::fresnel::fresnel_dielectric;
+use crate::ray::coordinate_system;
+use crate::spectrum::{rgb_to_spectrum, sample_wavelength, wavelength_to_xyz, xyz_to_rgb};
 use cgmath::Vector3;
+use rand::random;
+
+/// How light is scattered inside participating media
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VolumeMode {
+    /// only light arriving straight from the lights is scattered
+    SingleScattering,
+    /// scattering events also continue the path in a direction drawn from the phase function
+    PathTracing,
+}
+
+/// How a hit on a reflective and refractive surface continues the path
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BranchMode {
+    /// trace both the reflected and the refracted ray
+    Split,
+    /// trace one of them, chosen with probability proportional to its weight
+    Stochastic,
+}
 
 pub struct MyTracing<'a> {
     pub scene: &'a Scene,
     pub camera: &'a dyn Camera,
 
     pub max_depth: u32,
+    pub branch_mode: BranchMode,
+    // jittered rays per pixel, stochastic branching needs more than one to
+    // converge. 0 is traced as 1
+    pub samples_per_pixel: u32,
+    // trace a single sampled wavelength per path and develop the film through XYZ
+    pub spectral: bool,
+    pub volume_mode: VolumeMode,
 }
 
 impl<'a> Tracing for MyTracing<'a> {
     fn trace(&self, width: u32, height: u32) -> RgbImage {
         let mut img: RgbImage = RgbImage::new(width, height);
+        let samples = self.samples_per_pixel.max(1);
 
         for i in 0..width {
             for j in 0..height {
-                let x = (2.0 * i as f64 - width as f64) / width as f64;
-                let y = (height as f64 - 2.0 * j as f64) / height as f64;
-                let ray = self.camera.get_ray(x, y);
-
-                let color = self.trace_helper(&ray, 0);
+                let mut color: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+                for s in 0..samples {
+                    // the first sample goes through the pixel corner like a single ray always did
+                    let (dx, dy) = if s == 0 { (0.0, 0.0) } else { (random::<f64>(), random::<f64>()) };
+                    let x = (2.0 * (i as f64 + dx) - width as f64) / width as f64;
+                    let y = (height as f64 - 2.0 * (j as f64 + dy)) / height as f64;
+                    let ray = self.camera.get_ray(x, y);
+
+                    if self.spectral {
+                        // stratify the wavelengths over the pixel's samples to cut down color noise
+                        let u = (s as f64 + random::<f64>()) / samples as f64;
+                        let (lambda, pdf) = sample_wavelength(u);
+                        let value = self.trace_helper(&ray, 0, Some(lambda)).x;
+                        color += wavelength_to_xyz(value, lambda, pdf);
+                    } else {
+                        color += self.trace_helper(&ray, 0, None);
+                    }
+                }
+                color /= samples as f64;
+                if self.spectral {
+                    color = xyz_to_rgb(color);
+                }
                 let color = vec3_to_rgb(&color);
                 img.put_pixel(i, j, color);
             }
@@ -43,9 +93,35 @@ fn vec3_to_rgb(color: &Vector3<f64>) -> Rgb<u8> {
     Rgb([r, g, b])
 }
 
-fn reflect(a: Vector3<f64>, axis: Vector3<f64>) -> Vector3<f64> {
-    let temp = a.dot(axis) * axis;
-    2.0 * temp - a
+/// In spectral mode every color is replaced by the value of its uplifted
+/// spectrum at the path's wavelength, stored in all three channels
+fn to_spectral(color: Vector3<f64>, wavelength: Option<f64>) -> Vector3<f64> {
+    match wavelength {
+        None => color,
+        Some(lambda) => {
+            let v = rgb_to_spectrum(color, lambda);
+            Vector3::new(v, v, v)
+        },
+    }
+}
+
+fn exp3(v: Vector3<f64>) -> Vector3<f64> {
+    Vector3::new(v.x.exp(), v.y.exp(), v.z.exp())
+}
+
+fn luminance(color: Vector3<f64>) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Everything the material of the hit may read, in the object's space at `time`
+fn texture_point(hit: &IntersectResult, time: f64) -> TexturePoint {
+    let point = hit.point.as_ref().unwrap();
+    let object = hit.object.unwrap();
+
+    TexturePoint::new(point.texture, object.world_to_object(point.vertex, time), point.vertex)
+        .with_tangent(point.tangent)
+        .with_color(point.color)
+        .with_uv_sets(hit.uv_sets.clone())
 }
 
 fn refract(a: Vector3<f64>, n: Vector3<f64>, index: f64) -> Option<Vector3<f64>> {
@@ -58,130 +134,434 @@ fn refract(a: Vector3<f64>, n: Vector3<f64>, index: f64) -> Option<Vector3<f64>>
     }
 }
 
+// bound on the scattering events of a single subsurface random walk
+const MAX_WALK_STEPS: u32 = 256;
+
 impl<'a> MyTracing<'a> {
     pub fn new(scene: &'a Scene, camera: &'a dyn Camera) -> MyTracing<'a> {
         MyTracing {
             scene,
             camera,
 
-            max_depth: 8
+            max_depth: 8,
+            branch_mode: BranchMode::Split,
+            samples_per_pixel: 1,
+            spectral: false,
+            volume_mode: VolumeMode::SingleScattering,
         }
     }
 
-    fn trace_helper(&self, ray: &Ray, depth: u32) -> Vector3<f64> {
+    fn trace_helper(&self, ray: &Ray, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
         if depth >= self.max_depth {
             return Vector3::new(0.0, 0.0, 0.0);
         }
 
-        let intersect_result = self.scene.intersect(&ray);
+        let intersect_result = self.scene.intersect(ray);
+        let t_hit = if intersect_result.is_intersect { intersect_result.dis } else { f64::INFINITY };
+        let grid_event = self.sample_volumes(ray, t_hit);
+        let t_end = grid_event.map_or(t_hit, |(_, t)| t);
+
+        if let Some(medium) = self.segment_medium(&intersect_result) {
+            let sigma_t = to_spectral(medium.sigma_t(), wavelength);
+            // sample the free flight distance with the mean extinction,
+            // the per channel difference goes into the weights
+            let density = (sigma_t.x + sigma_t.y + sigma_t.z) / 3.0;
+            if density > 0.0 {
+                let t = -(1.0 - random::<f64>()).ln() / density;
+
+                if t < t_end {
+                    let pos = ray.pos + t * ray.dir;
+                    let sigma_s = to_spectral(medium.sigma_s, wavelength);
+                    let weight = exp3((Vector3::new(density, density, density) - sigma_t) * t)
+                        .mul_element_wise(sigma_s) / density;
+                    return self.scatter(&medium.phase, ray, pos, depth, wavelength).mul_element_wise(weight);
+                }
+                if t_end.is_infinite() {
+                    return Vector3::new(0.0, 0.0, 0.0);
+                }
+
+                let weight = exp3((Vector3::new(density, density, density) - sigma_t) * t_end);
+                return self.shade_event(ray, &intersect_result, grid_event, depth, wavelength).mul_element_wise(weight);
+            }
+        }
+
+        if t_end.is_infinite() {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        self.shade_event(ray, &intersect_result, grid_event, depth, wavelength)
+    }
+
+    /// Delta tracks the ray through every density grid before `t_max`. The grids
+    /// are independent media, so the nearest collision among them is the real one.
+    fn sample_volumes(&self, ray: &Ray, t_max: f64) -> Option<(&'a GridVolume, f64)> {
+        let mut result: Option<(&'a GridVolume, f64)> = None;
+        for volume in self.scene.volumes.iter() {
+            let limit = result.map_or(t_max, |(_, t)| t);
+            if let Some(t) = volume.sample_collision(ray, limit) {
+                result = Some((volume, t));
+            }
+        }
+
+        result
+    }
+
+    /// Shades whatever ended the ray's free flight: a collision inside a
+    /// density grid, or the surface it hit
+    fn shade_event(&self, ray: &Ray, intersect_result: &IntersectResult, grid_event: Option<(&GridVolume, f64)>, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        match grid_event {
+            Some((volume, t)) => {
+                // delta tracking already accounted for the extinction, only the albedo remains
+                let albedo = to_spectral(volume.albedo, wavelength);
+                self.scatter(&volume.phase, ray, ray.pos + t * ray.dir, depth, wavelength).mul_element_wise(albedo)
+            },
+            None => self.shade_surface(ray, intersect_result, depth, wavelength),
+        }
+    }
+
+    /// The medium the ray travelled through before reaching the hit: the
+    /// interior of the object it leaves, or the scene's fog
+    fn segment_medium(&self, intersect_result: &IntersectResult<'a>) -> Option<&'a HomogeneousMedium> {
+        if intersect_result.is_intersect && intersect_result.direction == IntersectDirection::Negative {
+            intersect_result.object.unwrap().interior()
+        } else {
+            self.scene.fog.as_ref()
+        }
+    }
+
+    fn get_light_color(&self, light: &dyn Light, point: Vector3<f64>, wavelength: Option<f64>) -> Vector3<f64> {
+        match wavelength {
+            Some(lambda) => {
+                let v = light.get_spectrum(point, lambda);
+                Vector3::new(v, v, v)
+            },
+            None => light.get_color(point),
+        }
+    }
+
+    /// Fraction of light surviving from `ray.pos` up to `ray.t_max` along `ray`.
+    /// Media are attenuated, surfaces that refract let their refracted share through.
+    fn transmittance(&self, ray: &Ray, wavelength: Option<f64>) -> Vector3<f64> {
+        let mut result = Vector3::new(1.0, 1.0, 1.0);
+        let mut segment_ray = *ray;
+
+        for _ in 0..self.max_depth {
+            let hit = self.scene.intersect(&segment_ray);
+            let reaches_end = !hit.is_intersect;
+            let length = if reaches_end { segment_ray.t_max } else { hit.dis };
+
+            if let Some(medium) = self.segment_medium(&hit) {
+                let sigma_t = to_spectral(medium.sigma_t(), wavelength);
+                if length.is_finite() {
+                    result = result.mul_element_wise(exp3(-sigma_t * length));
+                } else if sigma_t.x + sigma_t.y + sigma_t.z > 0.0 {
+                    return Vector3::new(0.0, 0.0, 0.0);
+                }
+            }
+            for volume in self.scene.volumes.iter() {
+                result *= volume.transmittance(&segment_ray, length);
+            }
+            if reaches_end {
+                return result;
+            }
+
+            let object = hit.object.unwrap();
+            let texture_point = texture_point(&hit, ray.time);
+            let refract = to_spectral(Vector3::new(1.0, 1.0, 1.0) * object.material().get_refract_ratio(&texture_point), wavelength);
+            if luminance(refract) <= 1e-6 {
+                return Vector3::new(0.0, 0.0, 0.0);
+            }
+            result = result.mul_element_wise(refract);
+            segment_ray = hit.spawn_ray(ray.dir).with_time(ray.time).with_t_max(segment_ray.t_max - hit.dis);
+        }
+
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Light scattered towards the viewer at a point inside a medium, `dir` is
+    /// the direction the view ray was travelling
+    fn scatter(&self, phase: &HenyeyGreenstein, ray: &Ray, pos: Vector3<f64>, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        let dir = ray.dir;
+        let mut color = Vector3::new(0.0, 0.0, 0.0);
+
+        // single scattering from the lights
+        for light in self.scene.lights.iter() {
+            let shadow_ray = light.get_ray(pos).with_time(ray.time);
+            let transmittance = self.transmittance(&shadow_ray, wavelength);
+            if luminance(transmittance) <= 0.0 {
+                continue;
+            }
+
+            // light propagates along -shadow_ray.dir and leaves along -dir
+            let phase_value = phase.p(-shadow_ray.dir, -dir);
+            color += self.get_light_color(light.as_ref(), pos, wavelength).mul_element_wise(transmittance) * phase_value;
+        }
+
+        // multiple scattering, the phase function is sampled exactly so the weight is one
+        if self.volume_mode == VolumeMode::PathTracing {
+            let new_dir = phase.sample(dir, random::<f64>(), random::<f64>());
+            let scattered_ray = Ray::new(pos, new_dir).with_time(ray.time);
+            color += self.trace_helper(&scattered_ray, depth + 1, wavelength);
+        }
+
+        color
+    }
+
+    /// Follows light refracted into a surface, either as a plain ray or, for
+    /// subsurface materials, as a random walk through the medium below it
+    fn trace_refracted(&self, ray: &Ray, object: &dyn Primitive, subsurface: Option<&HomogeneousMedium>, eta: f64, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        match subsurface {
+            Some(medium) => self.random_walk(ray, object, medium, eta, depth, wavelength),
+            None => self.trace_helper(ray, depth + 1, wavelength),
+        }
+    }
 
-        if !intersect_result.is_intersect {
+    /// Random walk inside `object` starting at the refracted `ray`, returns the
+    /// light entering where the walk leaves the surface. `eta` is the refract
+    /// index outside / inside used when the ray entered.
+    fn random_walk(&self, ray: &Ray, object: &dyn Primitive, medium: &HomogeneousMedium, eta: f64, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        let sigma_t = to_spectral(medium.sigma_t(), wavelength);
+        let sigma_s = to_spectral(medium.sigma_s, wavelength);
+        if sigma_t.x.min(sigma_t.y).min(sigma_t.z) <= 0.0 {
             return Vector3::new(0.0, 0.0, 0.0);
         }
 
-        let point = intersect_result.point.unwrap();
-        let u = point.texture.x;
-        let v = point.texture.y;
+        let mut throughput = Vector3::new(1.0, 1.0, 1.0);
+        let mut pos = ray.pos;
+        let mut dir = ray.dir;
+        for _ in 0..MAX_WALK_STEPS {
+            let walk_ray = Ray::new(pos, dir).with_time(ray.time);
+            // only the object itself, the walk never leaves it
+            let hit = object.intersect(&walk_ray);
+            if !hit.is_intersect {
+                // the mesh is not closed, the walk is lost
+                return Vector3::new(0.0, 0.0, 0.0);
+            }
+
+            // the distance is sampled with the extinction of a random channel,
+            // weights divide by the pdf averaged over the three channels
+            let channel = ((random::<f64>() * 3.0) as usize).min(2);
+            let t = -(1.0 - random::<f64>()).ln() / sigma_t[channel];
+            let distance = t.min(hit.dis);
+            let transmittance = exp3(-sigma_t * distance);
+            if t < hit.dis {
+                let pdf = sigma_t.mul_element_wise(transmittance).sum() / 3.0;
+                throughput = throughput.mul_element_wise(transmittance).mul_element_wise(sigma_s) / pdf;
+                pos += t * dir;
+                dir = medium.phase.sample(dir, random::<f64>(), random::<f64>());
+
+                // russian roulette once most of the energy is absorbed
+                let survive = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+                if survive < 0.1 {
+                    if random::<f64>() >= survive {
+                        return Vector3::new(0.0, 0.0, 0.0);
+                    }
+                    throughput /= survive;
+                }
+                continue;
+            }
+
+            throughput = throughput.mul_element_wise(transmittance) / (transmittance.sum() / 3.0);
+            let point = hit.point.as_ref().unwrap();
+            let normal = if dir.dot(point.normal) > 0.0 { point.normal } else { -point.normal };
+            let cos = dir.dot(normal);
+            // the surface reflects the walk back inside with the Fresnel probability
+            if random::<f64>() < fresnel_dielectric(cos, 1.0 / eta) {
+                dir -= 2.0 * cos * normal;
+                pos = hit.spawn_origin(dir);
+                continue;
+            }
+
+            let exit = hit.spawn_origin(normal);
+            return throughput.mul_element_wise(self.exit_radiance(exit, normal, eta, ray.time, depth, wavelength));
+        }
+
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Light entering a subsurface material at `pos` from outside, `normal`
+    /// points out of the object
+    fn exit_radiance(&self, pos: Vector3<f64>, normal: Vector3<f64>, eta: f64, time: f64, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        let mut color = Vector3::new(0.0, 0.0, 0.0);
+
+        for light in self.scene.lights.iter() {
+            let shadow_ray = light.get_ray(pos).with_time(time);
+            let cos = shadow_ray.dir.dot(normal);
+            if cos <= 0.0 {
+                continue;
+            }
+            if self.scene.occluded(&shadow_ray) {
+                continue;
+            }
+
+            let light_color = self.get_light_color(light.as_ref(), pos, wavelength);
+            color += light_color * cos * (1.0 - fresnel_dielectric(cos, eta));
+        }
+
+        // indirect light through a cosine distributed direction, the cosine cancels with the pdf
+        if self.volume_mode == VolumeMode::PathTracing {
+            let (tangent, bitangent) = coordinate_system(normal);
+            let phi = 2.0 * std::f64::consts::PI * random::<f64>();
+            let r2 = random::<f64>();
+            let r = r2.sqrt();
+            let dir = tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - r2).sqrt();
+            let cos = dir.dot(normal);
+            let ray = Ray::new(pos, dir).with_time(time);
+            color += self.trace_helper(&ray, depth + 1, wavelength) * (1.0 - fresnel_dielectric(cos, eta));
+        }
+
+        color
+    }
+
+    fn shade_surface(&self, ray: &Ray, intersect_result: &IntersectResult, depth: u32, wavelength: Option<f64>) -> Vector3<f64> {
+        let point = intersect_result.point.clone().unwrap();
         // todo normal
-        let normal = point.normal.clone();
+        let normal = point.normal;
 
         let collide_object = intersect_result.object.unwrap();
+        let texture_point = texture_point(intersect_result, ray.time);
 
-        let mut color: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
-        let object_base_color = collide_object.material.get_color(u, v);
+        let material = collide_object.material();
 
-        let mut reflect_ratio = collide_object.material.get_reflect_ratio(u, v);
-        let mut refract_ratio = collide_object.material.get_refract_ratio(u, v);
-        let mut refract_index = collide_object.material.get_refract_index(u, v);
-        let mut specular_strength = collide_object.material.get_specular_strength(u, v);
-        let mut diffuse_strength = collide_object.material.get_diffuse_strength(u, v);
+        let mut color: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+        let mut refract_index = match wavelength {
+            Some(lambda) => material.get_refract_index_at(&texture_point, lambda),
+            None => material.get_refract_index(&texture_point),
+        };
 
         // lights
         // if let IntersectDirection::Positive = intersect_result.direction {
             for light in self.scene.lights.iter() {
-                let shadow_ray = light.get_ray(point.vertex.clone());
+                let to_light = light.get_ray(point.vertex).dir;
+                let shadow_ray = light.get_ray(intersect_result.spawn_origin(to_light)).with_time(ray.time);
                 // if shadow_ray.dir.dot(normal) < 0.0 {
                 //     continue;
                 // }
-                let shadow_ray_intersect_result = self.scene.intersect(&shadow_ray);
 
-                // let light_color = light.get_color(point.vertex.clone());
-                let light_ambient_strength = light.get_ambient_strength(point.vertex.clone());
-                let light_diffuse_strength = light.get_diffuse_strength(point.vertex.clone());
-                let light_specular_strength = light.get_specular_strength(point.vertex.clone());
+                // let light_color = light.get_color(point.vertex);
+                // let light_ambient_strength = light.get_ambient_strength(point.vertex);
+                // let light_diffuse_strength = light.get_diffuse_strength(point.vertex);
+                // let light_specular_strength = light.get_specular_strength(point.vertex);
 
-                let mut is_blocked = if !shadow_ray_intersect_result.is_intersect {
-                    false
-                } else {
-                    let shadow_ray_intersect_pos = shadow_ray_intersect_result.point.unwrap().vertex;
-                    light.is_blocked(point.vertex, shadow_ray_intersect_pos)
-                };
+                let is_blocked = self.scene.occluded(&shadow_ray);
 
                 let light_color = if !is_blocked {
                     // ambient
                     // let ambient = light_color * light_ambient_strength;
                     // color += ambient.mul_element_wise(object_base_color);
-                    light.get_color(point.vertex)
+                    let light_color = self.get_light_color(light.as_ref(), point.vertex, wavelength);
+                    let in_media = self.scene.fog.is_some() || !self.scene.volumes.is_empty();
+                    if intersect_result.direction == IntersectDirection::Positive && in_media {
+                        light_color.mul_element_wise(self.transmittance(&shadow_ray, wavelength))
+                    } else {
+                        light_color
+                    }
                 } else if shadow_ray.dir.dot(normal) > 0.0 {
-                    self.trace_helper(&shadow_ray, depth + 1)
+                    self.trace_helper(&shadow_ray, depth + 1, wavelength)
                 } else {
                     Vector3::new(0.0, 0.0, 0.0)
                 };
 
-                // diffuse
-                let diffuse = light_color * shadow_ray.dir.dot(normal.clone()).max(0.0) * diffuse_strength;
-
-                // specular
-                let half = (-ray.dir + shadow_ray.dir).normalize();
-                // let shininess = collide_object.material.get_shininess(u, v);
-                let shininess = 128.0;
-                let specular = light_color * half.dot(normal.clone()).max(0.0).powf(shininess) * specular_strength;
-
-                color += (diffuse + specular).mul_element_wise(object_base_color);
+                let shaded = material.shade(&texture_point, normal, -ray.dir, shadow_ray.dir, light_color);
+                color += to_spectral(shaded, wavelength);
             }
         // }
 
-        let reflect_dir = reflect(-ray.dir, normal).normalize();
         let refract_normal = match intersect_result.direction {
             IntersectDirection::Positive => normal,
             IntersectDirection::Negative => -normal,
         };
+        let (reflect_dir, reflect_sample_weight) = material.sample_reflect(&texture_point, refract_normal, -ray.dir);
         refract_index = match intersect_result.direction {
             IntersectDirection::Positive => refract_index,
             IntersectDirection::Negative => 1.0 / refract_index,
         };
         let refract_dir = refract(ray.dir, refract_normal, refract_index);
 
-        if let None = refract_dir {
+        let cos_i = -ray.dir.dot(refract_normal);
+        let (mut reflect_weight, mut refract_weight) = material.get_split(&texture_point, cos_i, refract_index);
+        if refract_dir.is_none() {
             // 全反射
-            reflect_ratio += refract_ratio;
-            refract_ratio = 0.0;
+            reflect_weight += refract_weight;
+            refract_weight = Vector3::new(0.0, 0.0, 0.0);
         }
+        reflect_weight = to_spectral(reflect_weight.mul_element_wise(reflect_sample_weight), wavelength);
+        refract_weight = to_spectral(refract_weight, wavelength);
 
-        // reflect
-        let reflect_ray = Ray {
-            pos: point.vertex,
-            dir: reflect_dir,
-        };
-
-        let reflect_color = if reflect_ratio > 1e-6 {
-            self.trace_helper(&reflect_ray, depth + 1) * reflect_ratio
-        } else {
-            Vector3::new(0.0, 0.0, 0.0)
+        let reflect_ray = intersect_result.spawn_ray(reflect_dir).with_time(ray.time);
+        let subsurface = match intersect_result.direction {
+            IntersectDirection::Positive => material.get_subsurface(&texture_point),
+            IntersectDirection::Negative => None,
         };
-        color += reflect_color;
 
-        // refract
-        if refract_ratio > 1e-6 {
-            let refract_ray = Ray {
-                pos: point.vertex,
-                dir: refract_dir.unwrap(),
-            };
+        match self.branch_mode {
+            BranchMode::Split => {
+                // reflect
+                if luminance(reflect_weight) > 1e-6 {
+                    color += self.trace_helper(&reflect_ray, depth + 1, wavelength).mul_element_wise(reflect_weight);
+                }
+
+                // refract
+                if luminance(refract_weight) > 1e-6 {
+                    let refract_ray = intersect_result.spawn_ray(refract_dir.unwrap()).with_time(ray.time);
+                    color += self.trace_refracted(&refract_ray, collide_object, subsurface.as_ref(), refract_index, depth, wavelength)
+                        .mul_element_wise(refract_weight);
+                }
+            },
+            BranchMode::Stochastic => {
+                let reflect_p = luminance(reflect_weight).max(0.0);
+                let refract_p = luminance(refract_weight).max(0.0);
+                let total = reflect_p + refract_p;
+
+                if total > 1e-6 {
+                    // the chosen branch is divided by its probability so the estimate stays unbiased
+                    if random::<f64>() * total < reflect_p {
+                        let weight = reflect_weight * (total / reflect_p);
+                        color += self.trace_helper(&reflect_ray, depth + 1, wavelength).mul_element_wise(weight);
+                    } else {
+                        let refract_ray = intersect_result.spawn_ray(refract_dir.unwrap()).with_time(ray.time);
+                        let weight = refract_weight * (total / refract_p);
+                        color += self.trace_refracted(&refract_ray, collide_object, subsurface.as_ref(), refract_index, depth, wavelength)
+                            .mul_element_wise(weight);
+                    }
+                }
+            },
+        }
 
-            let refract_color = self.trace_helper(&refract_ray, depth + 1) * refract_ratio;
-            color += refract_color;
+        // Beer-Lambert, a back face hit means the ray travelled inside the object
+        if intersect_result.direction == IntersectDirection::Negative {
+            let absorption = to_spectral(material.get_absorption(&texture_point), wavelength);
+            color = color.mul_element_wise(exp3(-absorption * intersect_result.dis));
         }
 
         color * ((self.max_depth + 30 - depth) as f64) / (self.max_depth as f64 + 30.0)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod my_tracing_test {
+    use super::MyTracing;
+    use crate::camera::PerspectiveCamera;
+    use crate::light::PointLight;
+    use crate::object::{Mesh, Object};
+    use crate::scene::Scene;
+    use crate::tracing::Tracing;
+    use cgmath::Vector3;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_zero_samples_per_pixel() {
+        let mut scene = Scene::new();
+        let wall = Mesh::from_obj_str("v -5 -5 -3\nv 5 -5 -3\nv 5 5 -3\nv -5 5 -3\nf 1 2 3 4\n");
+        scene.add_object(Object::from_mesh(Rc::new(wall)));
+        scene.add_light(Box::new(PointLight::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(1.0, 1.0, 1.0), 0.2, 1.0, 1.0)));
+        let camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 1.0, 0.1, 10.0);
+
+        let mut tracing = MyTracing::new(&scene, &camera);
+        let one = tracing.trace(4, 4);
+        assert!(one.pixels().any(|p| p.0 != [0, 0, 0]));
+        // no average over nothing, the image is the same as with one sample
+        tracing.samples_per_pixel = 0;
+        assert_eq!(tracing.trace(4, 4), one);
+    }
+}
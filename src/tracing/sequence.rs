@@ -0,0 +1,89 @@
+use std::ops::RangeInclusive;
+
+use super::tracing::Tracing;
+use super::my_tracing::MyTracing;
+use crate::scene::Scene;
+use crate::camera::{Camera, PerspectiveCamera};
+
+/// Renders `frames` of the scene's timeline to `output_dir/frame_0001.png` and
+/// so on. Every frame poses the scene and the camera and then picks the levels
+/// of detail. The camera's shutter is relative to the frame: rays of a frame
+/// shown at time `t` get times in `[t + shutter_open, t + shutter_close]`, the
+/// times `motion` keyframes are given in. `setup` configures the tracer of
+/// every frame, e.g. its sample count.
+pub fn render_sequence<F: Fn(&mut MyTracing)>(
+    scene: &mut Scene,
+    camera: &mut PerspectiveCamera,
+    frames: RangeInclusive<u32>,
+    width: u32,
+    height: u32,
+    output_dir: &str,
+    setup: F,
+) -> Result<(), String> {
+    std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+    let shutter = (camera.shutter_open, camera.shutter_close);
+    let mut result = Ok(());
+    for frame in frames {
+        let time = scene.timeline.frame_time(frame);
+        scene.set_time(time);
+        scene.timeline.camera.apply(camera, time);
+        camera.set_shutter(time + shutter.0, time + shutter.1);
+        // levels of detail follow the camera, judged in the middle of the shutter
+        let shutter_time = (camera.shutter_open + camera.shutter_close) / 2.0;
+        scene.select_lods(&*camera, height as usize, shutter_time);
+
+        let mut tracing = MyTracing::new(scene, &*camera as &dyn Camera);
+        setup(&mut tracing);
+        let img = tracing.trace(width, height);
+
+        let filename = format!("{}/frame_{:04}.png", output_dir, frame);
+        if let Err(e) = img.save(&filename) {
+            result = Err(format!("cannot write {}: {}", filename, e));
+            break;
+        }
+    }
+    camera.set_shutter(shutter.0, shutter.1);
+
+    result
+}
+
+#[cfg(test)]
+mod sequence_test {
+    use super::render_sequence;
+    use crate::camera::PerspectiveCamera;
+    use crate::light::PointLight;
+    use crate::object::{Mesh, Object};
+    use crate::scene::Scene;
+    use crate::transform::Transform;
+    use cgmath::Vector3;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_motion_follows_frames() {
+        // a small square keyed to cross from left to right within a second
+        let mut scene = Scene::new();
+        let square = Mesh::from_obj_str("v -0.5 -0.5 0\nv 0.5 -0.5 0\nv 0.5 0.5 0\nv -0.5 0.5 0\nf 1 2 3 4\n");
+        let mut object = Object::from_mesh(Rc::new(square));
+        object.add_keyframe(0.0, Transform::translation(Vector3::new(-1.5, 0.0, -3.0)));
+        object.add_keyframe(1.0, Transform::translation(Vector3::new(1.5, 0.0, -3.0)));
+        scene.add_object(object);
+        scene.add_light(Box::new(PointLight::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0), 0.5, 1.0, 0.0)));
+        scene.timeline.frame_rate = 1.0;
+        let mut camera = PerspectiveCamera::new(std::f64::consts::PI / 2.0, 1.0, 0.1, 10.0);
+        camera.set_shutter(0.0, 0.0);
+
+        let dir = std::env::temp_dir().join(format!("hakaze_sequence_test_{}", std::process::id()));
+        render_sequence(&mut scene, &mut camera, 0..=1, 8, 8, dir.to_str().unwrap(), |_| ()).unwrap();
+        let frames: Vec<image::RgbImage> = (0..=1)
+            .map(|f| image::open(dir.join(format!("frame_{:04}.png", f))).unwrap().to_rgb8())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let lit = |img: &image::RgbImage, x: u32| img.get_pixel(x, 4).0 != [0, 0, 0];
+        assert!(lit(&frames[0], 2) && !lit(&frames[0], 6));
+        assert!(!lit(&frames[1], 2) && lit(&frames[1], 6));
+        // the shutter is given back as it was
+        assert_eq!((camera.shutter_open, camera.shutter_close), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,233 @@
+use cgmath::prelude::*;
+use cgmath::{Vector3, Matrix3, Matrix4, Quaternion};
+
+use super::transform::Transform;
+use crate::accel::Aabb;
+
+// transforms sampled per keyframe interval when bounding the motion
+const MOTION_BOUND_STEPS: usize = 16;
+
+/// A keyframe split into translation, rotation and scale, so rotations
+/// can be interpolated along the sphere instead of entry by entry
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f64,
+    pub transform: Transform,
+    translation: Vector3<f64>,
+    rotation: Quaternion<f64>,
+    scale: Vector3<f64>,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, transform: Transform) -> Keyframe {
+        let (translation, rotation, scale) = decompose(&transform.matrix);
+
+        Keyframe {
+            time,
+            transform,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+}
+
+/// Splits an affine matrix into translation * rotation * scale. Shear is not
+/// supported and ends up partly in the rotation. Axes scaled to nothing are
+/// completed from the others so the rotation stays valid.
+pub fn decompose(m: &Matrix4<f64>) -> (Vector3<f64>, Quaternion<f64>, Vector3<f64>) {
+    let translation = m.w.truncate();
+    let cols = [m.x.truncate(), m.y.truncate(), m.z.truncate()];
+    let mut scale = Vector3::new(cols[0].magnitude(), cols[1].magnitude(), cols[2].magnitude());
+    if Matrix3::from_cols(cols[0], cols[1], cols[2]).determinant() < 0.0 {
+        // a mirror is kept in the scale so the rest is a proper rotation
+        scale.x = -scale.x;
+    }
+    let axis = |i: usize| if scale[i] != 0.0 { Some(cols[i] / scale[i]) } else { None };
+    let rotation = match (axis(0), axis(1), axis(2)) {
+        (Some(x), Some(y), Some(z)) => Quaternion::from(Matrix3::from_cols(x, y, z)),
+        (None, Some(y), Some(z)) => Quaternion::from(Matrix3::from_cols(y.cross(z), y, z)),
+        (Some(x), None, Some(z)) => Quaternion::from(Matrix3::from_cols(x, z.cross(x), z)),
+        (Some(x), Some(y), None) => Quaternion::from(Matrix3::from_cols(x, y, x.cross(y))),
+        (Some(x), None, None) => Quaternion::from_arc(Vector3::unit_x(), x, None),
+        (None, Some(y), None) => Quaternion::from_arc(Vector3::unit_y(), y, None),
+        (None, None, Some(z)) => Quaternion::from_arc(Vector3::unit_z(), z, None),
+        (None, None, None) => Quaternion::one(),
+    };
+
+    (translation, rotation.normalize(), scale)
+}
+
+/// Transform moving through a list of keyframes, held constant before the
+/// first and after the last one
+#[derive(Clone, Debug, Default)]
+pub struct AnimatedTransform {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new() -> AnimatedTransform {
+        AnimatedTransform {
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Moves linearly from `start` at time 0 to `end` at time 1
+    pub fn from_start_end(start: Transform, end: Transform) -> AnimatedTransform {
+        let mut result = AnimatedTransform::new();
+        result.add_keyframe(0.0, start);
+        result.add_keyframe(1.0, end);
+
+        result
+    }
+
+    pub fn add_keyframe(&mut self, time: f64, transform: Transform) -> &mut Self {
+        let index = self.keyframes.iter().position(|k| k.time > time).unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, Keyframe::new(time, transform));
+
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Applies `transform` after every keyframe
+    pub fn premultiply(&mut self, transform: Transform) {
+        for k in self.keyframes.iter_mut() {
+            *k = Keyframe::new(k.time, transform * k.transform);
+        }
+    }
+
+    /// Applies `transform` before every keyframe
+    pub fn postmultiply(&mut self, transform: Transform) {
+        for k in self.keyframes.iter_mut() {
+            *k = Keyframe::new(k.time, k.transform * transform);
+        }
+    }
+
+    pub fn interpolate(&self, time: f64) -> Transform {
+        let first = match self.keyframes.first() {
+            Some(k) => k,
+            None => return Transform::identity(),
+        };
+        if time <= first.time {
+            return first.transform;
+        }
+        let index = match self.keyframes.iter().position(|k| k.time > time) {
+            Some(i) => i,
+            None => return self.keyframes.last().unwrap().transform,
+        };
+
+        let a = &self.keyframes[index - 1];
+        let b = &self.keyframes[index];
+        let u = (time - a.time) / (b.time - a.time);
+
+        let translation = a.translation.lerp(b.translation, u);
+        let scale = a.scale.lerp(b.scale, u);
+        // take the shorter way around
+        let to = if a.rotation.dot(b.rotation) < 0.0 { -b.rotation } else { b.rotation };
+        let rotation = a.rotation.slerp(to, u).normalize();
+
+        Transform::translation(translation) * Transform::rotation(rotation) * Transform::scale(scale.x, scale.y, scale.z)
+    }
+
+    /// Box holding `b` at every time of the animation. The transform is
+    /// sampled at a fixed number of steps between keyframes and each sample
+    /// is padded by how far the box can move before the next one
+    pub fn motion_bounds(&self, b: &Aabb) -> Aabb {
+        let mut result = Aabb::empty();
+        for (i, k) in self.keyframes.iter().enumerate() {
+            result = result.union(&k.transform.transform_aabb(b));
+            let next = match self.keyframes.get(i + 1) {
+                Some(next) if !b.is_empty() => next,
+                _ => continue,
+            };
+
+            // over the interval a point moves at most |Δt| + angle * |S p| + |ΔS p|,
+            // the worst points being corners since these are convex in p, and
+            // every moment is within half a step of a sample
+            let cos = k.rotation.dot(next.rotation).abs().min(1.0);
+            let angle = 2.0 * cos.acos();
+            let corners = b.corners();
+            let radius = corners.iter()
+                .map(|p| k.scale.mul_element_wise(*p).magnitude().max(next.scale.mul_element_wise(*p).magnitude()))
+                .fold(0.0, f64::max);
+            let stretch = corners.iter()
+                .map(|p| (next.scale - k.scale).mul_element_wise(*p).magnitude())
+                .fold(0.0, f64::max);
+            let path = (next.translation - k.translation).magnitude() + angle * radius + stretch;
+            let pad = path / (2 * MOTION_BOUND_STEPS) as f64;
+            let pad = Vector3::new(pad, pad, pad);
+
+            for step in 0..=MOTION_BOUND_STEPS {
+                let time = k.time + (next.time - k.time) * step as f64 / MOTION_BOUND_STEPS as f64;
+                let sample = self.interpolate(time).transform_aabb(b);
+                result = result.union(&Aabb::new(sample.min - pad, sample.max + pad));
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod animated_transform_test {
+    use super::{AnimatedTransform, decompose};
+    use crate::accel::Aabb;
+    use crate::transform::Transform;
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    #[test]
+    fn test_interpolate_rotation() {
+        let start = Transform::translation(Vector3::new(0.0, 0.0, -5.0));
+        let end = Transform::translation(Vector3::new(2.0, 0.0, -5.0)) * Transform::rotate_y(std::f64::consts::PI / 2.0);
+        let animated = AnimatedTransform::from_start_end(start, end);
+
+        let middle = animated.interpolate(0.5);
+        let p = middle.transform_point(Vector3::new(0.0, 0.0, -1.0));
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((p - Vector3::new(1.0 - s, 0.0, -5.0 - s)).magnitude() < 1e-9);
+        assert_eq!(animated.interpolate(-1.0), start);
+        assert_eq!(animated.interpolate(2.0), end);
+    }
+
+    #[test]
+    fn test_motion_bounds_hold_every_moment() {
+        // a long thin box swept most of a turn while moving and growing
+        let start = Transform::translation(Vector3::new(0.0, 0.0, -5.0));
+        let end = Transform::translation(Vector3::new(1.0, 0.0, -5.0)) * Transform::rotate_y(3.0) * Transform::scale(2.0, 1.0, 1.0);
+        let animated = AnimatedTransform::from_start_end(start, end);
+        let b = Aabb::new(Vector3::new(-2.0, -0.1, -0.1), Vector3::new(2.0, 0.1, 0.1));
+        let bounds = animated.motion_bounds(&b);
+
+        for step in 0..=1000 {
+            let transform = animated.interpolate(step as f64 / 1000.0);
+            for corner in b.corners().iter() {
+                let p = transform.transform_point(*corner);
+                assert!(p.x >= bounds.min.x && p.y >= bounds.min.y && p.z >= bounds.min.z);
+                assert!(p.x <= bounds.max.x && p.y <= bounds.max.y && p.z <= bounds.max.z);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompose_zero_scale() {
+        let m = Transform::translation(Vector3::new(1.0, 2.0, 3.0)) * Transform::rotate_y(0.5) * Transform::scale(0.0, 2.0, 1.0);
+        let (translation, rotation, scale) = decompose(&m.matrix);
+        assert_eq!(translation, Vector3::new(1.0, 2.0, 3.0));
+        assert!((scale - Vector3::new(0.0, 2.0, 1.0)).magnitude() < 1e-9);
+        let expected = Transform::rotate_y(0.5).transform_vector(Vector3::new(0.0, 0.0, 1.0));
+        assert!((rotation.rotate_vector(Vector3::new(0.0, 0.0, 1.0)) - expected).magnitude() < 1e-9);
+
+        let (_, rotation, scale) = decompose(&Transform::scale(0.0, 0.0, 0.0).matrix);
+        assert_eq!(scale, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(rotation, cgmath::Quaternion::one());
+
+        // keyframes collapsing to nothing interpolate without NaNs
+        let animated = AnimatedTransform::from_start_end(Transform::scale(0.0, 0.0, 0.0), Transform::identity());
+        let p = animated.interpolate(0.5).transform_point(Vector3::new(1.0, 1.0, 1.0));
+        assert!((p - Vector3::new(0.5, 0.5, 0.5)).magnitude() < 1e-9);
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod transform;
+pub mod animated_transform;
+
+pub use transform::Transform;
+pub use animated_transform::AnimatedTransform;
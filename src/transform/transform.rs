@@ -0,0 +1,207 @@
+use std::ops::Mul;
+
+use cgmath::prelude::*;
+use cgmath::{Vector3, Matrix3, Matrix4, Quaternion, Point3, Rad};
+
+use crate::ray::{Ray, gamma};
+use crate::accel::Aabb;
+
+/// Affine transform stored together with its inverse. Composition follows
+/// matrix order, `a * b` applies `b` first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix4<f64>,
+    pub inverse: Matrix4<f64>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        self.compose(&rhs)
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    /// Panics when `matrix` is singular, see `try_from_matrix`
+    pub fn from_matrix(matrix: Matrix4<f64>) -> Transform {
+        Transform::try_from_matrix(matrix).expect("transform matrix is not invertible")
+    }
+
+    pub fn try_from_matrix(matrix: Matrix4<f64>) -> Option<Transform> {
+        matrix.invert().map(|inverse| Transform {
+            matrix,
+            inverse,
+        })
+    }
+
+    pub fn translation(v: Vector3<f64>) -> Transform {
+        Transform {
+            matrix: Matrix4::from_translation(v),
+            inverse: Matrix4::from_translation(-v),
+        }
+    }
+
+    pub fn scale(x: f64, y: f64, z: f64) -> Transform {
+        Transform {
+            matrix: Matrix4::from_nonuniform_scale(x, y, z),
+            inverse: Matrix4::from_nonuniform_scale(1.0 / x, 1.0 / y, 1.0 / z),
+        }
+    }
+
+    pub fn uniform_scale(s: f64) -> Transform {
+        Transform::scale(s, s, s)
+    }
+
+    /// Rotation by a quaternion, which does not need to be normalized
+    pub fn rotation(q: Quaternion<f64>) -> Transform {
+        let q = q.normalize();
+        Transform {
+            matrix: Matrix4::from(q),
+            inverse: Matrix4::from(q.conjugate()),
+        }
+    }
+
+    /// Counter clockwise rotation of `angle` radians around `axis`
+    pub fn axis_angle(axis: Vector3<f64>, angle: f64) -> Transform {
+        Transform::rotation(Quaternion::from_axis_angle(axis.normalize(), Rad(angle)))
+    }
+
+    pub fn rotate_x(angle: f64) -> Transform {
+        Transform::axis_angle(Vector3::unit_x(), angle)
+    }
+
+    pub fn rotate_y(angle: f64) -> Transform {
+        Transform::axis_angle(Vector3::unit_y(), angle)
+    }
+
+    pub fn rotate_z(angle: f64) -> Transform {
+        Transform::axis_angle(Vector3::unit_z(), angle)
+    }
+
+    /// Places a model at `eye` with its -z axis pointing at `target`, the
+    /// convention the cameras use
+    pub fn look_at(eye: Vector3<f64>, target: Vector3<f64>, up: Vector3<f64>) -> Transform {
+        let view = Matrix4::look_at_rh(Point3::from_vec(eye), Point3::from_vec(target), up);
+
+        Transform::from_matrix(view).inverse()
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// `self * other`, the transform applying `other` first
+    pub fn compose(&self, other: &Transform) -> Transform {
+        Transform {
+            matrix: self.matrix * other.matrix,
+            inverse: other.inverse * self.inverse,
+        }
+    }
+
+    pub fn transform_point(&self, p: Vector3<f64>) -> Vector3<f64> {
+        let r = self.matrix * p.extend(1.0);
+        r.truncate() / r.w
+    }
+
+    /// Transforms a point that carries an absolute error bound, the returned
+    /// bound also covers the rounding of the transform itself. Assumes an
+    /// affine matrix
+    pub fn transform_point_with_error(&self, p: Vector3<f64>, error: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let m = &self.matrix;
+        let mut result_error = Vector3::new(0.0, 0.0, 0.0);
+        for row in 0..3 {
+            let abs_p = (m[0][row] * p.x).abs() + (m[1][row] * p.y).abs() + (m[2][row] * p.z).abs() + m[3][row].abs();
+            let abs_e = m[0][row].abs() * error.x + m[1][row].abs() * error.y + m[2][row].abs() * error.z;
+            result_error[row] = (gamma(3) + 1.0) * abs_e + gamma(3) * abs_p;
+        }
+
+        (self.transform_point(p), result_error)
+    }
+
+    pub fn transform_vector(&self, v: Vector3<f64>) -> Vector3<f64> {
+        (self.matrix * v.extend(0.0)).truncate()
+    }
+
+    /// Inverse transpose of the linear part, keeps normals perpendicular
+    /// to transformed surfaces under non uniform scaling
+    pub fn normal_matrix(&self) -> Matrix3<f64> {
+        let i = self.inverse;
+        Matrix3::new(
+            i.x.x, i.y.x, i.z.x,
+            i.x.y, i.y.y, i.z.y,
+            i.x.z, i.y.z, i.z.z,
+        )
+    }
+
+    /// Transformed normal, not renormalized
+    pub fn transform_normal(&self, n: Vector3<f64>) -> Vector3<f64> {
+        self.normal_matrix() * n
+    }
+
+    /// The direction is not renormalized so distances along the ray are preserved
+    pub fn transform_ray(&self, ray: &Ray) -> Ray {
+        Ray {
+            pos: self.transform_point(ray.pos),
+            dir: self.transform_vector(ray.dir),
+            ..*ray
+        }
+    }
+
+    pub fn transform_aabb(&self, b: &Aabb) -> Aabb {
+        b.transform(&self.matrix)
+    }
+}
+
+#[cfg(test)]
+mod transform_test {
+    use super::Transform;
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    fn close(a: Vector3<f64>, b: Vector3<f64>) -> bool {
+        (a - b).magnitude() < 1e-9
+    }
+
+    #[test]
+    fn test_compose_and_inverse() {
+        let t = Transform::translation(Vector3::new(1.0, 2.0, 3.0))
+            * Transform::axis_angle(Vector3::new(1.0, 1.0, 0.0), 0.7)
+            * Transform::scale(2.0, 3.0, 0.5);
+        let p = Vector3::new(0.3, -1.2, 4.0);
+        assert!(close(t.inverse().transform_point(t.transform_point(p)), p));
+        assert!(close(t.compose(&t.inverse()).transform_point(p), p));
+    }
+
+    #[test]
+    fn test_normal_under_non_uniform_scale() {
+        // a plane x + y = 0 squashed along x keeps its normal perpendicular
+        let t = Transform::scale(4.0, 1.0, 1.0);
+        let tangent = t.transform_vector(Vector3::new(1.0, -1.0, 0.0));
+        let normal = t.transform_normal(Vector3::new(1.0, 1.0, 0.0));
+        assert!(tangent.dot(normal).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_look_at() {
+        let t = Transform::look_at(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        assert!(close(t.transform_point(Vector3::new(0.0, 0.0, 0.0)), Vector3::new(0.0, 0.0, 5.0)));
+        assert!(close(t.transform_vector(-Vector3::unit_z()), -Vector3::unit_z()));
+    }
+}
@@ -0,0 +1,110 @@
+use cgmath::{Vector2, Vector3};
+
+/// Little endian encoder for binary files such as the mesh cache
+pub(crate) struct BinWriter {
+    pub data: Vec<u8>,
+}
+
+impl BinWriter {
+    pub fn new() -> BinWriter {
+        BinWriter {
+            data: Vec::new(),
+        }
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, v: f64) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn vector2(&mut self, v: Vector2<f64>) {
+        self.f64(v.x);
+        self.f64(v.y);
+    }
+
+    pub fn vector3(&mut self, v: Vector3<f64>) {
+        self.f64(v.x);
+        self.f64(v.y);
+        self.f64(v.z);
+    }
+
+    pub fn vector2s(&mut self, vs: &[Vector2<f64>]) {
+        self.u64(vs.len() as u64);
+        vs.iter().for_each(|&v| self.vector2(v));
+    }
+
+    pub fn vector3s(&mut self, vs: &[Vector3<f64>]) {
+        self.u64(vs.len() as u64);
+        vs.iter().for_each(|&v| self.vector3(v));
+    }
+}
+
+/// Decoder matching `BinWriter`, every read fails on truncated data
+pub(crate) struct BinReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BinReader<'a> {
+    pub fn new(data: &'a [u8]) -> BinReader<'a> {
+        BinReader {
+            data,
+            pos: 0,
+        }
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self.data.get(self.pos..self.pos + N).ok_or("unexpected end of data")?;
+        self.pos += N;
+        let mut result = [0; N];
+        result.copy_from_slice(bytes);
+        Ok(result)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.bytes()?))
+    }
+
+    /// Length of an array of `item_size` byte items, checked against the
+    /// remaining data so a corrupt count can't cause a huge allocation
+    pub fn len(&mut self, item_size: usize) -> Result<usize, String> {
+        let len = self.u64()? as usize;
+        if len.saturating_mul(item_size) > self.data.len() - self.pos {
+            return Err(String::from("unexpected end of data"));
+        }
+        Ok(len)
+    }
+
+    pub fn vector2(&mut self) -> Result<Vector2<f64>, String> {
+        Ok(Vector2::new(self.f64()?, self.f64()?))
+    }
+
+    pub fn vector3(&mut self) -> Result<Vector3<f64>, String> {
+        Ok(Vector3::new(self.f64()?, self.f64()?, self.f64()?))
+    }
+
+    pub fn vector2s(&mut self) -> Result<Vec<Vector2<f64>>, String> {
+        let len = self.len(16)?;
+        (0..len).map(|_| self.vector2()).collect()
+    }
+
+    pub fn vector3s(&mut self) -> Result<Vec<Vector3<f64>>, String> {
+        let len = self.len(24)?;
+        (0..len).map(|_| self.vector3()).collect()
+    }
+}